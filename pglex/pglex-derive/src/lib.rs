@@ -156,7 +156,10 @@ pub fn derive_lexable(input: TokenStream) -> TokenStream {
         if !token_start_val.is_empty() && !token_end_val.is_empty() {
             let match_statement = quote! {
                 if input.starts_with(#token_start_val) {
-                    if !input[0..input.len() - 1].ends_with(#token_end_val) {
+                    // Matches until the first end delimiter, which is part of the token
+                    let rest = &input[#token_start_val.len()..];
+                    let end_pos = rest.find(#token_end_val);
+                    if end_pos.is_none() || end_pos == Some(rest.len() - #token_end_val.len()) {
                         matches.push(#name::#variant_ident);
                     }
                 }
@@ -110,14 +110,10 @@ impl<'source, T, S> Lexer<T, S>
 
                 for token in last_matches.iter() {
                     if !token_matches.contains(token) {
+                        // The token ends in front of the current character,
+                        // for inclusive tokens this includes the whole end delimiter
                         if let Some(range) = token_match_map.get_mut(token) {
-                            if token.is_inclusive() {
-                                //println!("It is inclusive.");
-                                *range = range.start..self.current_pos - 1;
-                                //println!("Slice: {}", self.source.get_slice(range.start, range.end));
-                            } else {
-                                *range = range.start..self.current_pos;
-                            }
+                            *range = range.start..self.current_pos;
                         }
                     }
                 }
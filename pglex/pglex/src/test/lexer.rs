@@ -232,6 +232,21 @@ fn test_lexer_comments() {
     assert_eq!(lexer.token, Token::Float);
 }
 
+#[test]
+fn test_lexer_comments_without_whitespace() {
+    let code = "#!/usr/bin/env pgsh\nfn/* multiline */42// comment\n::";
+
+    let mut lexer = Token::lexer(code);
+    assert_eq!(lexer.token, Token::Fn);
+    lexer.advance();
+    assert_eq!(lexer.token, Token::IntLiteral);
+    assert_eq!(lexer.slice(), "42");
+    lexer.advance();
+    assert_eq!(lexer.token, Token::DoubleColon);
+    lexer.advance();
+    assert_eq!(lexer.token, Token::End);
+}
+
 #[test]
 fn test_lexer_fn() {
     let code = "fn: main";
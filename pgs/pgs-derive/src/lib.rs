@@ -0,0 +1,226 @@
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate syn;
+extern crate quote;
+
+use proc_macro::TokenStream;
+use proc_macro2::{
+    TokenStream as TokenStream2,
+    Span
+};
+use syn::{
+    ItemFn,
+    ItemStruct,
+    Fields,
+    FnArg,
+    Ident,
+    Pat,
+    ReturnType,
+    Type,
+    Meta,
+    NestedMeta
+};
+use quote::quote;
+
+/// Exposes a Rust function to scripts.
+/// Next to the function, `<name>_function()` is generated, which builds the `pgs::prelude::Function`.
+/// Argument and return types have to implement `FromArg` and `IntoReturn`,
+/// returning a `ForeignResult` raises its error into the script.
+/// Functions with a `&self` or `&mut self` receiver are member functions of a native container,
+/// `#[pgs_function(static)]` marks its static functions.
+#[proc_macro_attribute]
+pub fn pgs_function(attr: TokenStream, input: TokenStream) -> TokenStream {
+    let is_static = if attr.is_empty() {
+        false
+    } else {
+        syn::parse::<syn::Token![static]>(attr).expect("Only `static` is allowed as an argument of pgs_function.");
+        true
+    };
+    let item: ItemFn = syn::parse(input).expect("Only functions can be exposed with pgs_function.");
+
+    let sig = &item.sig;
+    let vis = &item.vis;
+    let name = &sig.ident;
+    let name_string = name.to_string();
+
+    if !sig.generics.params.is_empty() {
+        panic!("`{}` is generic, this is not allowed for a pgs_function.", name);
+    }
+    if sig.asyncness.is_some() {
+        panic!("`{}` is async, this is not allowed for a pgs_function.", name);
+    }
+
+    let mut receiver_mut = None;
+    let mut arg_idents: Vec<Ident> = Vec::new();
+    let mut arg_types: Vec<Type> = Vec::new();
+
+    for input in &sig.inputs {
+        match input {
+            FnArg::Receiver(receiver) => {
+                if receiver.reference.is_none() {
+                    panic!("`{}` takes self by value, only `&self` and `&mut self` are allowed for a pgs_function.", name);
+                }
+                receiver_mut = Some(receiver.mutability.is_some());
+            },
+            FnArg::Typed(pat_type) => {
+                if let Pat::Ident(pat_ident) = pat_type.pat.as_ref() {
+                    if pat_ident.ident == "self" {
+                        panic!("`{}` has a typed self argument, use `&self` or `&mut self` instead.", name);
+                    }
+                }
+                let arg_ident = Ident::new(&format!("arg{}", arg_idents.len()), Span::call_site());
+                arg_idents.push(arg_ident);
+                arg_types.push(pat_type.ty.as_ref().clone());
+            }
+        }
+    }
+
+    if is_static && receiver_mut.is_some() {
+        panic!("`{}` has a receiver, this is not allowed for a static pgs_function.", name);
+    }
+
+    let ret_type = match &sig.output {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ret_type) => quote! { #ret_type }
+    };
+
+    // The reference to the instance of a member function is its first argument
+    let first_arg_index = if receiver_mut.is_some() { 1usize } else { 0usize };
+    let arg_indices: Vec<usize> = (first_arg_index..first_arg_index + arg_idents.len()).collect();
+
+    let call_statement: TokenStream2 = match receiver_mut {
+        Some(is_mut) => {
+            let this_ref = if is_mut {
+                quote! { &mut *this_lock }
+            } else {
+                quote! { &*this_lock }
+            };
+            quote! {
+                let ret = {
+                    let this = adapter.get_this::<Self>()?;
+                    let mut this_lock = this.lock()
+                        .map_err(|_| ::pgs::prelude::ForeignError::new("the instance was poisoned by a panic"))?;
+                    Self::#name(#this_ref, #(#arg_idents),*)
+                };
+            }
+        },
+        None if is_static => quote! {
+            let ret = Self::#name(#(#arg_idents),*);
+        },
+        None => quote! {
+            let ret = #name(#(#arg_idents),*);
+        }
+    };
+
+    let builder_ident = Ident::new(&format!("{}_function", name), Span::call_site());
+
+    let token_stream = quote! {
+        #item
+
+        #vis fn #builder_ident() -> ::pgs::prelude::Function {
+            ::pgs::prelude::Function::new(#name_string)
+                #(
+                    .with_arg(<#arg_types as ::pgs::prelude::FromArg>::arg_type())
+                )*
+                .with_ret_type(<#ret_type as ::pgs::prelude::IntoReturn>::ret_type())
+                .with_closure(Box::new(|adapter: &mut ::pgs::prelude::Adapter| {
+                    #(
+                        let #arg_idents: #arg_types = adapter.get_arg(#arg_indices)?;
+                    )*
+                    #call_statement
+                    adapter.return_value(ret)
+                }))
+        }
+    };
+    token_stream.into()
+}
+
+/// Exposes a struct as a native container.
+/// Every named field gets an accessor, fields marked with `#[pgs(skip)]` are left out.
+/// Field types have to implement `IntoReturn` and `Clone`.
+#[proc_macro_derive(PgsContainer, attributes(pgs))]
+pub fn derive_pgs_container(input: TokenStream) -> TokenStream {
+    let item: ItemStruct = syn::parse(input).expect("Only structs can be used as a PgsContainer.");
+
+    let name = &item.ident;
+    let name_string = name.to_string();
+
+    if !item.generics.params.is_empty() {
+        panic!("`{}` is generic, this is not allowed for a PgsContainer.", name);
+    }
+
+    let mut variable_statements: Vec<TokenStream2> = Vec::new();
+
+    match &item.fields {
+        Fields::Named(fields) => {
+            for field in &fields.named {
+                if is_skipped(field) {
+                    continue;
+                }
+                let field_ident = field.ident.as_ref().unwrap();
+                let field_type = &field.ty;
+                let field_string = field_ident.to_string();
+                let accessor_string = format!("get_{}", field_ident);
+
+                let variable_statement = quote! {
+                    .with_variable((
+                        String::from(#field_string),
+                        ::pgs::prelude::Function::new(#accessor_string)
+                            .with_ret_type(<#field_type as ::pgs::prelude::IntoReturn>::ret_type())
+                            .with_closure(Box::new(|adapter: &mut ::pgs::prelude::Adapter| {
+                                let this = adapter.get_this::<#name>()?;
+                                let value = this.lock()
+                                    .map_err(|_| ::pgs::prelude::ForeignError::new("the instance was poisoned by a panic"))?
+                                    .#field_ident
+                                    .clone();
+                                adapter.return_value(value)
+                            }))
+                    ))
+                };
+                variable_statements.push(variable_statement);
+            }
+        },
+        Fields::Unit => {},
+        Fields::Unnamed(_) => panic!("`{}` has unnamed fields, this is not allowed for a PgsContainer.", name)
+    }
+
+    let token_stream = quote! {
+        impl ::pgs::prelude::PgsContainer for #name {
+            fn container_name() -> &'static str {
+                #name_string
+            }
+
+            fn container() -> ::pgs::prelude::Container {
+                ::pgs::prelude::Container::new(String::from(#name_string))
+                    #(
+                        #variable_statements
+                    )*
+            }
+        }
+    };
+    token_stream.into()
+}
+
+/// Whether a field is marked with `#[pgs(skip)]`
+fn is_skipped(field: &syn::Field) -> bool {
+    for attr in &field.attrs {
+        if !attr.path.is_ident("pgs") {
+            continue;
+        }
+        let meta = attr.parse_meta().expect("Attribute malformed: Meta parsing failed.");
+        let is_skip = match meta {
+            Meta::List(list) => {
+                list.nested.len() == 1 && match list.nested.first() {
+                    Some(NestedMeta::Meta(Meta::Path(path))) => path.is_ident("skip"),
+                    _ => false
+                }
+            },
+            _ => false
+        };
+        if !is_skip {
+            panic!("Attribute malformed: Only `#[pgs(skip)]` is supported.");
+        }
+        return true;
+    }
+    false
+}
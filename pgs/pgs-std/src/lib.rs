@@ -1,73 +1,94 @@
 extern crate pgs;
 
-use pgs::{
-    engine::{
-        Engine,
-        EngineResult
-    },
-    api::{
-        function::{
-            Function
-        },
-        adapter::Adapter,
-        module::{
-            Module
-        }
-    },
-    parser::{
-        ast::{
-            Type
-        }
-    },
-    vm::{
-        core::Core
-    }
+use pgs::prelude::*;
+
+use std::{
+    io::stdin,
+    env::var
 };
 
-fn register_std_print(engine: &mut Engine) -> EngineResult<()> {
+fn std_io_module() -> Module {
+    let read_line_function = Function::new("read_line")
+        .with_ret_type(ValueType::String)
+        .with_closure(Box::new(|adapter: &mut Adapter| {
+            let mut line = String::new();
+            stdin().read_line(&mut line)
+                .map_err(|err| ForeignError::new(err.to_string()))?;
+            // Strip the line ending
+            let line_len = line.trim_end_matches(&['\r', '\n'][..]).len();
+            line.truncate(line_len);
+            adapter.return_value(line)
+        }));
+
+    Module::new("io")
+        .with_function(read_line_function)
+}
+
+fn std_env_module() -> Module {
+    let getenv_function = Function::new("getenv")
+        .with_arg(ValueType::String)
+        .with_ret_type(ValueType::String)
+        .with_closure(Box::new(|adapter: &mut Adapter| {
+            let name: String = adapter.get_arg(0)?;
+            // Unset variables are returned as empty strings
+            let value = var(name).unwrap_or_default();
+            adapter.return_value(value)
+        }));
+
+    Module::new("env")
+        .with_function(getenv_function)
+}
+
+fn register_std(engine: &mut Engine) -> EngineResult<()> {
     let printi_function = Function::new("printi")
-        .with_arg(Type::Int)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::Int)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter: &mut Adapter| {
             //println!("Calling printi!");
-            let arg: i64 = adapter.get_arg(0);
+            let arg: i64 = adapter.get_arg(0)?;
             print!("{}", arg);
+            Ok(())
         }));
     let print_function = Function::new("print")
-        .with_arg(Type::String)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::String)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter: &mut Adapter| {
             //println!("Calling print!");
-            let arg: String = adapter.get_arg(0);
+            let arg: String = adapter.get_arg(0)?;
             print!("{}", arg);
+            Ok(())
         }));
     let printf_function = Function::new("printf")
-        .with_arg(Type::Float)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::Float)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter| {
-            let arg: f32 = adapter.get_arg(0);
+            let arg: f32 = adapter.get_arg(0)?;
             print!("{}", arg);
+            Ok(())
         }));
     let println_function = Function::new("println")
-        .with_arg(Type::String)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::String)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter: &mut Adapter| {
             //println!("Calling println!");
-            let arg: String = adapter.get_arg(0);
+            let arg: String = adapter.get_arg(0)?;
             println!("{}", arg);
+            Ok(())
         }));
     
     let module = Module::new("std")
         .with_function(printi_function)
         .with_function(print_function)
         .with_function(println_function)
-        .with_function(printf_function);
-    
+        .with_function(printf_function)
+        .with_module(std_io_module())
+        .with_module(std_env_module());
+
     engine.register_module(module)
 }
 
 #[no_mangle]
 pub extern fn register_extension(engine: &mut Engine) -> EngineResult<()> {
-    register_std_print(engine)?;
+    register_std(engine)?;
     Ok(())
 }
\ No newline at end of file
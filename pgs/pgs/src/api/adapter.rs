@@ -1,7 +1,15 @@
 use crate::{
     api::{
         function::{
-            Function
+            Function,
+            ForeignError,
+            ForeignResult
+        },
+        container::{
+            PgsContainer
+        },
+        value::{
+            ValueType
         }
     },
     codegen::{
@@ -9,6 +17,11 @@ use crate::{
             Register
         }
     },
+    parser::{
+        ast::{
+            Type
+        }
+    },
     vm::{
         core::{
             Core
@@ -27,86 +40,275 @@ use std::{
     }
 };
 
-use serde::{
-    de::DeserializeOwned
-};
-
 pub struct Adapter<'c> {
     pub function: Function,
-    pub core: &'c mut Core
+    pub(crate) core: &'c mut Core,
+    has_returned: bool
 }
 
 impl<'c> Adapter<'c> {
-    pub fn new(func: &Function, core: &'c mut Core) -> Adapter<'c> {
+    pub(crate) fn new(func: &Function, core: &'c mut Core) -> Adapter<'c> {
         Adapter {
             function: func.clone(),
-            core: core
+            core: core,
+            has_returned: false
         }
     }
 
-    pub fn get_arg<T>(&mut self, arg_index: usize) -> T
+    pub fn get_arg<T>(&mut self, arg_index: usize) -> ForeignResult<T>
     where T: FromArg {
         T::get(self, arg_index)
     }
 
-    pub fn return_value<T>(&mut self, value: T)
-    where RegisterUnion: RegisterAccess<T> {
-        self.core.reg(Register::R0.into()).unwrap().set::<T>(value);
+    pub fn return_value<T>(&mut self, value: T) -> ForeignResult<()>
+    where T: IntoReturn {
+        value.set(self)?;
+        self.has_returned = true;
+        Ok(())
+    }
+
+    /// Whether return_value() was called
+    pub fn has_returned(&self) -> bool {
+        self.has_returned
     }
 
     // Retrieves a foreign pointer and returns the correct
     /// Arc<Mutex<T>> if found.
-    pub fn get_foreign_ptr<T>(&self, ptr: u64) -> Arc<Mutex<T>> {
-        self.core.get_foreign_ptr(ptr).unwrap()
+    pub fn get_foreign_ptr<T: Send + 'static>(&self, ptr: u64) -> ForeignResult<Arc<Mutex<T>>> {
+        Ok(self.core.get_foreign_ptr(ptr)?)
     }
 
     /// Inserts a foreign pointer
-    pub fn insert_foreign_ptr<T>(&mut self, item: Arc<Mutex<T>>) -> u64 {
-        self.core.insert_foreign_ptr(item).unwrap()
+    pub fn insert_foreign_ptr<T: Send + 'static>(&mut self, item: Arc<Mutex<T>>) -> ForeignResult<u64> {
+        Ok(self.core.insert_foreign_ptr(item)?)
     }
 
     /// Removes a foreign pointer
-    pub fn remove_foreign_ptr<T>(&mut self, ptr: u64) -> Arc<Mutex<T>> {
-        self.core.remove_foreign_ptr(ptr).unwrap()
+    pub fn remove_foreign_ptr<T: Send + 'static>(&mut self, ptr: u64) -> ForeignResult<Arc<Mutex<T>>> {
+        Ok(self.core.remove_foreign_ptr(ptr)?)
+    }
+
+    /// Gets the Rust value of the native container a member function or accessor is called on.
+    /// Fails if the instance holds a different Rust type.
+    pub fn get_this<T: Send + 'static>(&mut self) -> ForeignResult<Arc<Mutex<T>>> {
+        // The first argument is a reference to the handle of the instance
+        let this_addr = self.get_arg::<u64>(0)?;
+        let ptr = self.core.mem_get::<u64>((this_addr, 0))?;
+        self.get_foreign_ptr(ptr)
+    }
+
+    /// Gets the Rust value of a native container that is passed as an argument.
+    /// Fails if the instance holds a different Rust type.
+    pub fn get_native<T: Send + 'static>(&mut self, arg_index: usize) -> ForeignResult<Arc<Mutex<T>>> {
+        let ptr = self.get_arg::<u64>(arg_index)?;
+        self.get_foreign_ptr(ptr)
+    }
+
+    /// Returns a Rust value as a new instance of the native container the function returns.
+    /// Like all non-primitive values, its handle is pushed onto the stack.
+    pub fn return_native<T: Send + 'static>(&mut self, item: Arc<Mutex<T>>) -> ForeignResult<()> {
+        let container = match &self.function.return_type {
+            Type::Other(container) => container.clone(),
+            _ => return Err(ForeignError::new(format!("{} does not return a native container", self.function.name)))
+        };
+        let ptr = self.core.insert_native_ptr(item, &container)?;
+        self.core.push_stack(ptr)?;
+        self.has_returned = true;
+        Ok(())
+    }
+
+    /// Gets the address of the stack frame the arguments are read from
+    fn get_frame_addr(&mut self) -> ForeignResult<u64> {
+        Ok(self.core.reg(16)?.get::<u64>())
     }
 }
 
-pub trait FromArg: DeserializeOwned {
-    fn get(adapter: &mut Adapter, arg_index: usize) -> Self;
+pub trait FromArg: Sized {
+    /// The script type of arguments read as this Rust type
+    fn arg_type() -> ValueType;
+    fn get(adapter: &mut Adapter, arg_index: usize) -> ForeignResult<Self>;
 }
 
 impl FromArg for String {
-    fn get(adapter: &mut Adapter, arg_index: usize) -> String {
-        let arg_offset = adapter.function.get_arg_offset(arg_index).abs() as u64;
+    fn arg_type() -> ValueType {
+        ValueType::String
+    }
+
+    fn get(adapter: &mut Adapter, arg_index: usize) -> ForeignResult<String> {
+        let arg_offset = adapter.function.get_arg_offset(arg_index)?.abs() as u64;
         //println!("Arg offset of Arg #{}: -{}B", arg_index, arg_offset);
-        let mut stack_addr = adapter.core.reg(16).unwrap().get::<u64>();
+        let mut stack_addr = adapter.get_frame_addr()?;
         stack_addr -= arg_offset;
         let string_res = adapter.core.mem_get_string(stack_addr);
         //println!("{:?}", string_res);
-        string_res.unwrap()
+        Ok(string_res?)
     }
 }
 
 impl FromArg for i64 {
-    fn get(adapter: &mut Adapter, arg_index: usize) -> i64 {
-        let arg_offset = adapter.function.get_arg_offset(arg_index) as i16;
-        let addr = adapter.core.reg(16).unwrap().get::<u64>();
-        adapter.core.mem_get((addr, arg_offset)).unwrap()
+    fn arg_type() -> ValueType {
+        ValueType::Int
+    }
+
+    fn get(adapter: &mut Adapter, arg_index: usize) -> ForeignResult<i64> {
+        let arg_offset = adapter.function.get_arg_offset(arg_index)? as i16;
+        let addr = adapter.get_frame_addr()?;
+        Ok(adapter.core.mem_get((addr, arg_offset))?)
     }
 }
 
 impl FromArg for f32 {
-    fn get(adapter: &mut Adapter, arg_index: usize) -> f32 {
-        let arg_offset = adapter.function.get_arg_offset(arg_index) as i16;
-        let addr = adapter.core.reg(16).unwrap().get::<u64>();
-        adapter.core.mem_get((addr, arg_offset)).unwrap()
+    fn arg_type() -> ValueType {
+        ValueType::Float
+    }
+
+    fn get(adapter: &mut Adapter, arg_index: usize) -> ForeignResult<f32> {
+        let arg_offset = adapter.function.get_arg_offset(arg_index)? as i16;
+        let addr = adapter.get_frame_addr()?;
+        Ok(adapter.core.mem_get((addr, arg_offset))?)
     }
 }
 
 impl FromArg for u64 {
-    fn get(adapter: &mut Adapter, arg_index: usize) -> u64 {
-        let arg_offset = adapter.function.get_arg_offset(arg_index) as i16;
-        let addr = adapter.core.reg(16).unwrap().get::<u64>();
-        adapter.core.mem_get((addr, arg_offset)).unwrap()
+    /// Addresses and handles
+    fn arg_type() -> ValueType {
+        ValueType::Reference(Box::new(ValueType::Void))
+    }
+
+    fn get(adapter: &mut Adapter, arg_index: usize) -> ForeignResult<u64> {
+        let arg_offset = adapter.function.get_arg_offset(arg_index)? as i16;
+        let addr = adapter.get_frame_addr()?;
+        Ok(adapter.core.mem_get((addr, arg_offset))?)
+    }
+}
+
+/// Booleans take up 4 bytes on the stack
+impl FromArg for bool {
+    fn arg_type() -> ValueType {
+        ValueType::Bool
+    }
+
+    fn get(adapter: &mut Adapter, arg_index: usize) -> ForeignResult<bool> {
+        let arg_offset = adapter.function.get_arg_offset(arg_index)? as i16;
+        let addr = adapter.get_frame_addr()?;
+        Ok(adapter.core.mem_get::<u32>((addr, arg_offset))? != 0)
+    }
+}
+
+impl<T: PgsContainer> FromArg for Arc<Mutex<T>> {
+    fn arg_type() -> ValueType {
+        ValueType::Container(String::from(T::container_name()))
+    }
+
+    fn get(adapter: &mut Adapter, arg_index: usize) -> ForeignResult<Arc<Mutex<T>>> {
+        adapter.get_native(arg_index)
+    }
+}
+
+pub trait IntoReturn {
+    /// The script return type of this Rust type
+    fn ret_type() -> ValueType;
+    fn set(self, adapter: &mut Adapter) -> ForeignResult<()>;
+}
+
+/// Primitive values are returned in the R0 register
+fn set_return_register<T>(adapter: &mut Adapter, value: T) -> ForeignResult<()>
+where RegisterUnion: RegisterAccess<T> {
+    adapter.core.reg(Register::R0.into())?.set::<T>(value);
+    Ok(())
+}
+
+impl IntoReturn for i64 {
+    fn ret_type() -> ValueType {
+        ValueType::Int
+    }
+
+    fn set(self, adapter: &mut Adapter) -> ForeignResult<()> {
+        set_return_register(adapter, self)
+    }
+}
+
+impl IntoReturn for u64 {
+    fn ret_type() -> ValueType {
+        ValueType::Reference(Box::new(ValueType::Void))
+    }
+
+    fn set(self, adapter: &mut Adapter) -> ForeignResult<()> {
+        set_return_register(adapter, self)
+    }
+}
+
+impl IntoReturn for f32 {
+    fn ret_type() -> ValueType {
+        ValueType::Float
+    }
+
+    fn set(self, adapter: &mut Adapter) -> ForeignResult<()> {
+        set_return_register(adapter, self)
+    }
+}
+
+impl IntoReturn for bool {
+    fn ret_type() -> ValueType {
+        ValueType::Bool
+    }
+
+    fn set(self, adapter: &mut Adapter) -> ForeignResult<()> {
+        set_return_register(adapter, self)
+    }
+}
+
+/// Strings are copied to the heap, their (size, address) header is pushed onto the stack
+impl IntoReturn for &str {
+    fn ret_type() -> ValueType {
+        ValueType::String
+    }
+
+    fn set(self, adapter: &mut Adapter) -> ForeignResult<()> {
+        let string_addr = adapter.core.alloc_bytes(self.as_bytes())?;
+        adapter.core.push_stack(self.len() as u64)?;
+        adapter.core.push_stack(string_addr)?;
+        Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl IntoReturn for String {
+    fn ret_type() -> ValueType {
+        ValueType::String
+    }
+
+    fn set(self, adapter: &mut Adapter) -> ForeignResult<()> {
+        self.as_str().set(adapter)
+    }
+}
+
+impl IntoReturn for () {
+    fn ret_type() -> ValueType {
+        ValueType::Void
+    }
+
+    fn set(self, _adapter: &mut Adapter) -> ForeignResult<()> {
+        Ok(())
+    }
+}
+
+impl<T: PgsContainer> IntoReturn for Arc<Mutex<T>> {
+    fn ret_type() -> ValueType {
+        ValueType::Container(String::from(T::container_name()))
+    }
+
+    fn set(self, adapter: &mut Adapter) -> ForeignResult<()> {
+        adapter.return_native(self)
+    }
+}
+
+/// Failures are raised into the script instead of returning a value
+impl<T: IntoReturn> IntoReturn for ForeignResult<T> {
+    fn ret_type() -> ValueType {
+        T::ret_type()
+    }
+
+    fn set(self, adapter: &mut Adapter) -> ForeignResult<()> {
+        self?.set(adapter)
+    }
+}
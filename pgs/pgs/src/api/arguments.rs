@@ -0,0 +1,212 @@
+use crate::{
+    api::{
+        value::{
+            Value,
+            ValueType
+        }
+    }
+};
+
+use std::{
+    error::Error,
+    fmt::{
+        Display,
+        Formatter,
+        Result as FmtResult
+    }
+};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum ArgumentError {
+    /// The function takes an argument of a type that can't be passed from the outside
+    UnsupportedType(String, ValueType),
+    /// A value could not be parsed as the type of its argument
+    InvalidValue {
+        name: String,
+        expected: ValueType,
+        value: String
+    },
+    /// No value was given for an argument
+    MissingValue(String),
+    /// A named value doesn't belong to any argument
+    UnknownName(String),
+    /// An argument was given a value more than once
+    DuplicateValue(String),
+    /// More positional values were given than the function takes
+    TooManyValues(Vec<String>),
+    /// The function takes more than one auto array, so the positional values can't be split between them
+    AmbiguousAutoArray(String)
+}
+
+impl Display for ArgumentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            ArgumentError::UnsupportedType(name, arg_type) => write!(f, "argument \"{}\" has the unsupported type {}", name, arg_type),
+            ArgumentError::InvalidValue { name, expected, value } => write!(f, "invalid value \"{}\" for argument \"{}\", expected {}", value, name, expected),
+            ArgumentError::MissingValue(name) => write!(f, "missing value for argument \"{}\"", name),
+            ArgumentError::UnknownName(name) => write!(f, "unknown argument \"--{}\"", name),
+            ArgumentError::DuplicateValue(name) => write!(f, "argument \"{}\" was given more than once", name),
+            ArgumentError::TooManyValues(values) => write!(f, "unexpected values: {}", values.join(" ")),
+            ArgumentError::AmbiguousAutoArray(name) => write!(f, "argument \"{}\" is a second auto array, only one can take the remaining values", name)
+        }
+    }
+}
+
+impl Error for ArgumentError {
+}
+
+pub type ArgumentResult<T> = Result<T, ArgumentError>;
+
+/// Checks if values of the type can be passed from the outside, i.e. primitives, strings and arrays of them
+fn is_supported_type(arg_type: &ValueType) -> bool {
+    match arg_type {
+        ValueType::Int | ValueType::Float | ValueType::Bool | ValueType::String => true,
+        ValueType::Array(inner_type, _) |
+        ValueType::AutoArray(inner_type) => !is_array_type(inner_type) && is_supported_type(inner_type),
+        _ => false
+    }
+}
+
+fn is_array_type(arg_type: &ValueType) -> bool {
+    matches!(arg_type, ValueType::Array(_, _) | ValueType::AutoArray(_))
+}
+
+/// Returns how many positional values an argument takes, None for auto arrays which take the rest
+fn get_value_count(arg_type: &ValueType) -> Option<usize> {
+    match arg_type {
+        ValueType::Array(_, size) => Some(*size),
+        ValueType::AutoArray(_) => None,
+        ValueType::Bool => Some(0),
+        _ => Some(1)
+    }
+}
+
+fn parse_value(name: &str, value: &str, arg_type: &ValueType) -> ArgumentResult<Value> {
+    Value::parse(value, arg_type)
+        .ok_or_else(|| ArgumentError::InvalidValue {
+            name: String::from(name),
+            expected: arg_type.clone(),
+            value: String::from(value)
+        })
+}
+
+/// Matches command line style values to the arguments of a function.
+///
+/// Values of the form `--name value` or `--name=value` are assigned to the argument of that name.
+/// Bool arguments are flags, a plain `--name` sets them to true and they are false if not given.
+/// The remaining values fill the other arguments in order, an array argument `[T; N]` takes N values.
+/// An auto array argument `[T]` takes every value that is not needed by the arguments after it.
+/// A value of `--` ends the named values, everything after it is positional.
+pub fn parse_arguments(arguments: &[(String, ValueType)], values: &[&str]) -> ArgumentResult<Vec<Value>> {
+    let mut has_auto_array = false;
+    for (name, arg_type) in arguments.iter() {
+        if !is_supported_type(arg_type) {
+            return Err(ArgumentError::UnsupportedType(name.clone(), arg_type.clone()));
+        }
+        if let ValueType::AutoArray(_) = arg_type {
+            if has_auto_array {
+                return Err(ArgumentError::AmbiguousAutoArray(name.clone()));
+            }
+            has_auto_array = true;
+        }
+    }
+
+    let mut parsed: Vec<Option<Value>> = vec![None; arguments.len()];
+    let mut positional = Vec::new();
+    let mut values = values.iter();
+    while let Some(value) = values.next() {
+        if *value == "--" {
+            positional.extend(values.by_ref());
+            break;
+        }
+        let flag = match value.strip_prefix("--") {
+            Some(flag) if !flag.is_empty() => flag,
+            _ => {
+                positional.push(value);
+                continue;
+            }
+        };
+
+        let (name, inline_value) = match flag.find('=') {
+            Some(pos) => (&flag[..pos], Some(&flag[pos + 1..])),
+            None => (flag, None)
+        };
+        let index = arguments.iter()
+            .position(|(arg_name, _)| arg_name == name)
+            .ok_or_else(|| ArgumentError::UnknownName(String::from(name)))?;
+        if parsed[index].is_some() {
+            return Err(ArgumentError::DuplicateValue(String::from(name)));
+        }
+        let arg_type = &arguments[index].1;
+        if is_array_type(arg_type) {
+            // Arrays are filled by positional values only
+            return Err(ArgumentError::UnknownName(String::from(name)));
+        }
+        let value = match (inline_value, arg_type) {
+            (Some(inline_value), _) => inline_value,
+            (None, ValueType::Bool) => "true",
+            (None, _) => values.next()
+                .ok_or_else(|| ArgumentError::MissingValue(String::from(name)))?
+        };
+        parsed[index] = Some(parse_value(name, value, arg_type)?);
+    }
+
+    // The auto array leaves enough values for the other unnamed arguments
+    let reserved: usize = arguments.iter()
+        .zip(parsed.iter())
+        .filter(|(_, value_opt)| value_opt.is_none())
+        .filter_map(|((_, arg_type), _)| get_value_count(arg_type))
+        .sum();
+    let auto_array_size = positional.len().saturating_sub(reserved);
+
+    let mut positional = positional.into_iter();
+    for ((name, arg_type), value_opt) in arguments.iter().zip(parsed.iter_mut()) {
+        if value_opt.is_some() {
+            continue;
+        }
+        let value = match arg_type {
+            ValueType::AutoArray(inner_type) => {
+                let elements = positional.by_ref()
+                    .take(auto_array_size)
+                    .map(|element| parse_value(name, element, inner_type))
+                    .collect::<ArgumentResult<Vec<Value>>>()?;
+                Value::AutoArray(elements)
+            },
+            ValueType::Array(inner_type, size) => {
+                let mut elements = Vec::new();
+                for _ in 0..*size {
+                    let element = positional.next()
+                        .ok_or_else(|| ArgumentError::MissingValue(name.clone()))?;
+                    elements.push(parse_value(name, element, inner_type)?);
+                }
+                Value::Array(elements)
+            },
+            ValueType::Bool => Value::Bool(false),
+            _ => {
+                let value = positional.next()
+                    .ok_or_else(|| ArgumentError::MissingValue(name.clone()))?;
+                parse_value(name, value, arg_type)?
+            }
+        };
+        *value_opt = Some(value);
+    }
+
+    let remaining: Vec<String> = positional.map(|value| value.to_string()).collect();
+    if !remaining.is_empty() {
+        return Err(ArgumentError::TooManyValues(remaining));
+    }
+
+    Ok(parsed.into_iter().flatten().collect())
+}
+
+/// Renders the arguments of a function as a usage string, e.g. `<count: int> [--verbose] <files: [string]...>`
+pub fn get_usage(arguments: &[(String, ValueType)]) -> String {
+    let usage: Vec<String> = arguments.iter()
+        .map(|(name, arg_type)| match arg_type {
+            ValueType::Bool => format!("[--{}]", name),
+            ValueType::AutoArray(_) => format!("<{}: {}...>", name, arg_type),
+            _ => format!("<{}: {}>", name, arg_type)
+        })
+        .collect();
+    usage.join(" ")
+}
@@ -8,7 +8,8 @@ use std::{
     collections::HashMap
 };
 
-/// A Container definition
+/// A Container definition, registered with `Module::with_container`.
+/// Instances are handles to Rust values, see `Adapter::get_this` and `Adapter::return_native`.
 pub struct Container {
     pub name: String,
     pub members: HashMap<String, ContainerMember>
@@ -23,13 +24,19 @@ impl Container {
         }
     }
 
-    /// ...with a member function
+    /// ...with a member function, the reference to the instance is added as its first argument
     pub fn with_function(mut self, function: Function) -> Container {
         self.members.insert(function.name.clone(), ContainerMember::Function(function));
         self
     }
 
-    /// ...with a member variable
+    /// ...with a function that is called without an instance, e.g. `Counter::create()`
+    pub fn with_static_function(mut self, function: Function) -> Container {
+        self.members.insert(function.name.clone(), ContainerMember::StaticFunction(function));
+        self
+    }
+
+    /// ...with a member variable, read by calling the accessor function like a member function
     pub fn with_variable(mut self, (name, acc_fn): (String, Function)) -> Container {
         self.members.insert(name.clone(), ContainerMember::Variable {
             name: name,
@@ -41,9 +48,18 @@ impl Container {
 
 pub enum ContainerMember {
     Function(Function),
+    StaticFunction(Function),
     Variable {
         name: String,
         accessor_fn: Function
     }
 }
 
+/// A Rust type that is exposed as a native container, usually implemented with `#[derive(PgsContainer)]`.
+/// Instances are passed to and returned from foreign functions as `Arc<Mutex<T>>`, so the type has to be `Send`.
+pub trait PgsContainer: Send + 'static {
+    /// Name of the container in scripts
+    fn container_name() -> &'static str;
+    /// Builds the container definition, with an accessor for every exposed field
+    fn container() -> Container;
+}
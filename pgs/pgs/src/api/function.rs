@@ -5,12 +5,20 @@ use crate::{
         },
         adapter::{
             Adapter
+        },
+        value::{
+            ValueType
         }
     },
     parser::{
         ast::{
             Type
         }
+    },
+    vm::{
+        core::{
+            CoreError
+        }
     }
 };
 
@@ -28,7 +36,11 @@ use std::{
     fmt::{
         Formatter,
         Result as FmtResult,
-        Debug
+        Debug,
+        Display
+    },
+    error::{
+        Error
     },
     clone::{
         Clone
@@ -45,11 +57,11 @@ pub struct Function {
     /// Name of this function
     pub name: String,
     /// Type signature
-    pub arg_types: Vec<Type>,
+    pub(crate) arg_types: Vec<Type>,
     arg_offsets: HashMap<usize, i64>,
     arg_sizes: HashMap<usize, usize>,
     /// Return type
-    pub return_type: Type,
+    pub(crate) return_type: Type,
     closure: Option<Arc<Mutex<FunctionClosureType>>>
 }
 
@@ -78,7 +90,51 @@ impl Clone for Function {
     }
 }*/
 
-pub type FunctionClosureType = dyn FnMut(&mut Adapter) -> ();
+pub type FunctionClosureType = dyn FnMut(&mut Adapter) -> ForeignResult<()>;
+
+pub type ForeignResult<T> = Result<T, ForeignError>;
+
+/// The failure of a foreign function, raised as `CoreError::ForeignFunction` into the script
+#[derive(Debug, PartialEq, Clone)]
+pub struct ForeignError {
+    pub message: String
+}
+
+impl ForeignError {
+    pub fn new<T>(message: T) -> ForeignError
+    where String: From<T> {
+        ForeignError {
+            message: String::from(message)
+        }
+    }
+}
+
+impl Display for ForeignError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ForeignError {
+}
+
+impl From<CoreError> for ForeignError {
+    fn from(err: CoreError) -> ForeignError {
+        ForeignError::new(err.to_string())
+    }
+}
+
+impl From<&str> for ForeignError {
+    fn from(message: &str) -> ForeignError {
+        ForeignError::new(message)
+    }
+}
+
+impl From<String> for ForeignError {
+    fn from(message: String) -> ForeignError {
+        ForeignError::new(message)
+    }
+}
 
 impl Function {
     /// Creates a new function
@@ -96,42 +152,58 @@ impl Function {
     }
 
     /// Sets the return type
-    pub fn with_ret_type(mut self, ret_type: Type) -> Function {
-        self.return_type = ret_type;
+    pub fn with_ret_type(mut self, ret_type: ValueType) -> Function {
+        self.return_type = Type::from(&ret_type);
         self
     }
 
     /// Sets the next argument type
-    pub fn with_arg(mut self, arg_type: Type) -> Function {
-        self.arg_types.push(arg_type);
+    pub fn with_arg(mut self, arg_type: ValueType) -> Function {
+        self.arg_types.push(Type::from(&arg_type));
         self
     }
 
+    /// Gets the argument types, in order
+    pub fn arg_types(&self) -> Vec<ValueType> {
+        self.arg_types.iter()
+            .map(ValueType::from)
+            .collect()
+    }
+
+    /// Gets the return type
+    pub fn return_type(&self) -> ValueType {
+        ValueType::from(&self.return_type)
+    }
+
     /// INTERNAL: Sets the correct argument offsets
-    pub fn set_arg_offsets(&mut self, arg_offsets: Vec<i64>) {
+    pub(crate) fn set_arg_offsets(&mut self, arg_offsets: Vec<i64>) {
         for i in 0..arg_offsets.len() {
             self.arg_offsets.insert(i, arg_offsets[i]);
         }
     }
 
     /// INTERNAL: Sets the correct argument sizes
-    pub fn set_arg_sizes(&mut self, arg_sizes: Vec<usize>) {
+    pub(crate) fn set_arg_sizes(&mut self, arg_sizes: Vec<usize>) {
         for i in 0..arg_sizes.len() {
             self.arg_sizes.insert(i, arg_sizes[i]);
         }
     }
 
     /// Gets the byte offset of an argument
-    pub fn get_arg_offset(&self, arg_index: usize) -> i64 {
-        *self.arg_offsets.get(&arg_index).unwrap()
+    pub(crate) fn get_arg_offset(&self, arg_index: usize) -> ForeignResult<i64> {
+        self.arg_offsets.get(&arg_index)
+            .cloned()
+            .ok_or_else(|| ForeignError::new(format!("{} has no argument #{}", self.name, arg_index)))
     }
 
     /// Runs the internal closure
-    pub fn run(&self, adapter: &mut Adapter) {
-        let closure_arc = self.closure.as_ref().unwrap();
-        let mut closure_lock = closure_arc.lock().unwrap();
+    pub(crate) fn run(&self, adapter: &mut Adapter) -> ForeignResult<()> {
+        let closure_arc = self.closure.as_ref()
+            .ok_or_else(|| ForeignError::new(format!("{} has no closure", self.name)))?;
+        let mut closure_lock = closure_arc.lock()
+            .map_err(|_| ForeignError::new(format!("{} panicked in an earlier call", self.name)))?;
         let closure = closure_lock.deref_mut();
-        closure(adapter);
+        closure(adapter)
     }
     
     /// Sets the closure to be executes
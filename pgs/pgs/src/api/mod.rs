@@ -8,4 +8,13 @@ pub mod module;
 pub mod adapter;
 
 /// Contains the container API
-pub mod container;
\ No newline at end of file
+pub mod container;
+
+/// Contains the parsing of function arguments passed from the outside
+pub mod arguments;
+
+/// Contains the conversion of Rust values to and from script values
+pub mod value;
+
+/// Contains the handles of script functions
+pub mod script_function;
\ No newline at end of file
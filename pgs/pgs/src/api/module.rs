@@ -8,6 +8,9 @@ use crate::{
     api::{
         function::{
             Function
+        },
+        container::{
+            Container
         }
     }
 };
@@ -15,6 +18,7 @@ use crate::{
 pub struct Module {
     pub name: String,
     pub functions: HashMap<String, Function>,
+    pub containers: HashMap<String, Container>,
     pub modules: HashMap<String, Module>
 }
 
@@ -25,6 +29,7 @@ impl Module {
         Module {
             name: name,
             functions: HashMap::new(),
+            containers: HashMap::new(),
             modules: HashMap::new()
         }
     }
@@ -34,6 +39,11 @@ impl Module {
         self
     }
 
+    pub fn with_container(mut self, container: Container) -> Module {
+        self.containers.insert(container.name.clone(), container);
+        self
+    }
+
     pub fn with_module(mut self, module: Module) -> Module {
         self.modules.insert(module.name.clone(), module);
         self
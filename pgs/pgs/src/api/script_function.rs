@@ -0,0 +1,74 @@
+use crate::{
+    codegen::{
+        bytecode
+    },
+    parser::{
+        ast::{
+            Type
+        }
+    },
+    api::{
+        value::{
+            ValueType
+        }
+    }
+};
+
+/// Represents a script function that can be called from the outside
+#[derive(PartialEq, Debug, Clone)]
+pub struct ScriptFunction {
+    /// Full name of this function, e.g. `root::main`
+    pub name: String,
+    /// Names and types of the arguments
+    pub(crate) arguments: Vec<(String, Type)>,
+    /// Return type
+    pub(crate) return_type: Type
+}
+
+impl ScriptFunction {
+    /// Creates a function with no arguments or return type
+    pub fn new(name: &str) -> ScriptFunction {
+        ScriptFunction {
+            name: String::from(name),
+            arguments: Vec::new(),
+            return_type: Type::Void
+        }
+    }
+
+    pub fn with_arg(mut self, name: &str, arg_type: ValueType) -> ScriptFunction {
+        self.arguments.push((String::from(name), Type::from(&arg_type)));
+        self
+    }
+
+    pub fn with_ret_type(mut self, ret_type: ValueType) -> ScriptFunction {
+        self.return_type = Type::from(&ret_type);
+        self
+    }
+
+    /// Returns the names and types of the arguments, in order
+    pub fn arguments(&self) -> Vec<(String, ValueType)> {
+        self.arguments.iter()
+            .map(|(arg_name, arg_type)| (arg_name.clone(), ValueType::from(arg_type)))
+            .collect()
+    }
+
+    /// Returns the types of the arguments, in order
+    pub fn arg_types(&self) -> Vec<ValueType> {
+        self.arguments.iter()
+            .map(|(_, arg_type)| ValueType::from(arg_type))
+            .collect()
+    }
+
+    /// Returns the return type
+    pub fn return_type(&self) -> ValueType {
+        ValueType::from(&self.return_type)
+    }
+
+    /// Renders the signature, e.g. `(Int, String) ~ Bool`
+    pub fn signature(&self) -> String {
+        let arg_types: Vec<Type> = self.arguments.iter()
+            .map(|(_, arg_type)| arg_type.clone())
+            .collect();
+        bytecode::signature_string(&arg_types, &self.return_type)
+    }
+}
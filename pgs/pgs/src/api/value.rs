@@ -0,0 +1,422 @@
+use crate::{
+    vm::{
+        core::{
+            Core,
+            CoreError,
+            CoreResult
+        }
+    },
+    parser::{
+        ast::{
+            Type
+        },
+        printer::type_name
+    }
+};
+
+use std::{
+    convert::{
+        TryInto
+    },
+    fmt::{
+        Display,
+        Formatter,
+        Result as FmtResult
+    }
+};
+
+/// The type of a script value as seen from the outside, e.g. in the signature of a script function
+#[derive(PartialEq, Debug, Clone)]
+pub enum ValueType {
+    Void,
+    Int,
+    Float,
+    Bool,
+    String,
+    Array(Box<ValueType>, usize),
+    AutoArray(Box<ValueType>),
+    /// A container by name
+    Container(String),
+    Reference(Box<ValueType>),
+    /// A type that is not known, e.g. the elements of an empty array
+    Unknown
+}
+
+impl From<&Type> for ValueType {
+    fn from(var_type: &Type) -> ValueType {
+        match var_type {
+            Type::Void => ValueType::Void,
+            Type::Int => ValueType::Int,
+            Type::Float => ValueType::Float,
+            Type::Bool => ValueType::Bool,
+            Type::String => ValueType::String,
+            Type::Array(inner_type, size) => ValueType::Array(Box::new(ValueType::from(inner_type.as_ref())), *size),
+            Type::AutoArray(inner_type) => ValueType::AutoArray(Box::new(ValueType::from(inner_type.as_ref()))),
+            Type::Other(name) => ValueType::Container(name.clone()),
+            Type::Reference(inner_type) => ValueType::Reference(Box::new(ValueType::from(inner_type.as_ref()))),
+            Type::Auto | Type::Tuple(_) => ValueType::Unknown
+        }
+    }
+}
+
+impl From<&ValueType> for Type {
+    fn from(value_type: &ValueType) -> Type {
+        match value_type {
+            ValueType::Void => Type::Void,
+            ValueType::Int => Type::Int,
+            ValueType::Float => Type::Float,
+            ValueType::Bool => Type::Bool,
+            ValueType::String => Type::String,
+            ValueType::Array(inner_type, size) => Type::Array(Box::new(Type::from(inner_type.as_ref())), *size),
+            ValueType::AutoArray(inner_type) => Type::AutoArray(Box::new(Type::from(inner_type.as_ref()))),
+            ValueType::Container(name) => Type::Other(name.clone()),
+            ValueType::Reference(inner_type) => Type::Reference(Box::new(Type::from(inner_type.as_ref()))),
+            ValueType::Unknown => Type::Auto
+        }
+    }
+}
+
+/// Renders the type the way it is written in scripts, e.g. `[int; 4]`
+impl Display for ValueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", type_name(&Type::from(self)))
+    }
+}
+
+/// A script value of a type that is only known at runtime, e.g. for calls through `Engine::call_function`
+#[derive(PartialEq, Debug, Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f32),
+    Bool(bool),
+    String(String),
+    Array(Vec<Value>),
+    /// An array whose size is only known at runtime, its elements are stored on the heap
+    AutoArray(Vec<Value>),
+    /// A container by name, with its members in declaration order
+    Container(String, Vec<(String, Value)>),
+    /// A handle to a Rust value owned by the engine, e.g. an instance of a native container
+    ForeignRef(u64)
+}
+
+impl Value {
+    /// Parses a single value of a primitive type or a string
+    pub fn parse(value: &str, value_type: &ValueType) -> Option<Value> {
+        match value_type {
+            ValueType::Int => value.parse().ok().map(Value::Int),
+            ValueType::Float => value.parse().ok().map(Value::Float),
+            ValueType::Bool => value.parse().ok().map(Value::Bool),
+            ValueType::String => Some(Value::String(String::from(value))),
+            _ => None
+        }
+    }
+
+    /// Returns the script type of the value, arrays take the type of their first element
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::Int(_) => ValueType::Int,
+            Value::Float(_) => ValueType::Float,
+            Value::Bool(_) => ValueType::Bool,
+            Value::String(_) => ValueType::String,
+            Value::Array(elements) => ValueType::Array(Box::new(Value::element_type(elements)), elements.len()),
+            Value::AutoArray(elements) => ValueType::AutoArray(Box::new(Value::element_type(elements))),
+            Value::Container(name, _) => ValueType::Container(name.clone()),
+            Value::ForeignRef(_) => ValueType::Reference(Box::new(ValueType::Void))
+        }
+    }
+
+    /// Gets a member of a container value by name
+    pub fn member(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Container(_, members) => {
+                members.iter()
+                    .find(|(member_name, _)| member_name == name)
+                    .map(|(_, member)| member)
+            },
+            _ => None
+        }
+    }
+
+    fn element_type(elements: &[Value]) -> ValueType {
+        elements.first()
+            .map(|element| element.value_type())
+            .unwrap_or(ValueType::Unknown)
+    }
+}
+
+/// A Rust type that can be passed to script functions and returned from them.
+///
+/// Values are converted from and to `Value`s. For a script container,
+/// implement the trait with `ValueType::Container` and list the members in declaration order:
+///
+/// ```ignore
+/// impl ScriptValue for Point {
+///     fn script_type() -> ValueType {
+///         ValueType::Container(String::from("Point"))
+///     }
+///
+///     fn to_value(&self) -> Value {
+///         Value::Container(String::from("Point"), vec![
+///             (String::from("x"), self.x.to_value()),
+///             (String::from("y"), self.y.to_value())
+///         ])
+///     }
+///
+///     fn from_value(value: Value) -> Option<Point> {
+///         Some(Point {
+///             x: i64::from_value(value.member("x")?.clone())?,
+///             y: f32::from_value(value.member("y")?.clone())?
+///         })
+///     }
+/// }
+/// ```
+pub trait ScriptValue: Sized {
+    /// The script type of the value, calls are checked against the function signature with it
+    fn script_type() -> ValueType;
+    /// Converts the Rust value into a script value
+    fn to_value(&self) -> Value;
+    /// Converts a script value back, fails if it has a different type
+    fn from_value(value: Value) -> Option<Self>;
+    /// The result of a void function, only `()` can be one
+    fn from_void() -> Option<Self> {
+        None
+    }
+}
+
+/// The arguments of a script function call, implemented for tuples of `ScriptValue`s
+pub trait ScriptArgs {
+    /// The script types of the arguments, in order
+    fn arg_types() -> Vec<ValueType>;
+    /// Converts the arguments into script values, in order
+    fn to_values(&self) -> Vec<Value>;
+}
+
+/// Writes values in the memory layout of scripts, strings are copied to the heap
+pub(crate) struct ValueWriter<'c> {
+    core: &'c mut Core,
+    bytes: Vec<u8>
+}
+
+impl<'c> ValueWriter<'c> {
+    pub fn new(core: &'c mut Core) -> ValueWriter<'c> {
+        ValueWriter {
+            core,
+            bytes: Vec::new()
+        }
+    }
+
+    /// Writes a runtime typed value, container members are written in the order they are given
+    pub fn write_value(&mut self, value: &Value) -> CoreResult<()> {
+        match value {
+            Value::Int(int) => self.write_bytes(&int.to_le_bytes()),
+            Value::Float(float) => self.write_bytes(&float.to_le_bytes()),
+            // Bools take up 4 bytes, only the first one is used
+            Value::Bool(boolean) => self.write_bytes(&(*boolean as u32).to_le_bytes()),
+            // Strings are a size followed by the address of their bytes
+            Value::String(string) => {
+                self.write_heap_bytes(string.len(), string.as_bytes())?;
+            },
+            Value::Array(elements) => {
+                for element in elements.iter() {
+                    self.write_value(element)?;
+                }
+            },
+            // Auto arrays are a size followed by the address of their elements, like strings
+            Value::AutoArray(elements) => {
+                let mut element_writer = ValueWriter::new(self.core);
+                for element in elements.iter() {
+                    element_writer.write_value(element)?;
+                }
+                let element_bytes = element_writer.into_bytes();
+                self.write_heap_bytes(elements.len(), &element_bytes)?;
+            },
+            Value::Container(_, members) => {
+                for (_, member) in members.iter() {
+                    self.write_value(member)?;
+                }
+            },
+            Value::ForeignRef(ptr) => self.write_bytes(&ptr.to_le_bytes())
+        };
+        Ok(())
+    }
+
+    /// Copies bytes to the heap and writes the size and their address
+    fn write_heap_bytes(&mut self, size: usize, bytes: &[u8]) -> CoreResult<()> {
+        let addr = match bytes.is_empty() {
+            true => 0,
+            false => self.core.alloc_bytes(bytes)?
+        };
+        self.write_bytes(&(size as u64).to_le_bytes());
+        self.write_bytes(&addr.to_le_bytes());
+        Ok(())
+    }
+
+    /// Appends raw bytes
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// Returns the written bytes
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads values in the memory layout of scripts
+pub(crate) struct ValueReader<'c> {
+    core: &'c Core,
+    bytes: &'c [u8],
+    pos: usize
+}
+
+impl<'c> ValueReader<'c> {
+    pub fn new(core: &'c Core, bytes: &'c [u8]) -> ValueReader<'c> {
+        ValueReader {
+            core,
+            bytes,
+            pos: 0
+        }
+    }
+
+    /// Takes the next n raw bytes
+    pub fn read_bytes(&mut self, n: usize) -> CoreResult<&'c [u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos + n)
+            .ok_or(CoreError::OperatorDeserialize)?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    pub fn read_u64(&mut self) -> CoreResult<u64> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn read_int(&mut self) -> CoreResult<i64> {
+        Ok(i64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn read_float(&mut self) -> CoreResult<f32> {
+        Ok(f32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn read_bool(&mut self) -> CoreResult<bool> {
+        Ok(self.read_bytes(4)?[0] != 0)
+    }
+
+    pub fn read_string(&mut self) -> CoreResult<String> {
+        let size = self.read_u64()?;
+        let addr = self.read_u64()?;
+        let bytes = match size {
+            0 => Vec::new(),
+            size => self.core.mem_get_n((addr, 0), size as usize)?
+        };
+        String::from_utf8(bytes)
+            .map_err(|_| CoreError::OperatorDeserialize)
+    }
+}
+
+macro_rules! impl_script_value {
+    ($rust_type:ty, $value_type:ident) => {
+        impl ScriptValue for $rust_type {
+            fn script_type() -> ValueType {
+                ValueType::$value_type
+            }
+
+            fn to_value(&self) -> Value {
+                Value::$value_type(self.clone())
+            }
+
+            fn from_value(value: Value) -> Option<$rust_type> {
+                match value {
+                    Value::$value_type(value) => Some(value),
+                    _ => None
+                }
+            }
+        }
+    };
+}
+
+impl_script_value!(i64, Int);
+impl_script_value!(f32, Float);
+impl_script_value!(bool, Bool);
+impl_script_value!(String, String);
+
+impl<T: ScriptValue, const N: usize> ScriptValue for [T; N] {
+    fn script_type() -> ValueType {
+        ValueType::Array(Box::new(T::script_type()), N)
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Array(self.iter().map(T::to_value).collect())
+    }
+
+    fn from_value(value: Value) -> Option<[T; N]> {
+        match value {
+            Value::Array(elements) => {
+                elements.into_iter()
+                    .map(T::from_value)
+                    .collect::<Option<Vec<T>>>()?
+                    .try_into()
+                    .ok()
+            },
+            _ => None
+        }
+    }
+}
+
+/// Vectors are auto arrays `[T]`
+impl<T: ScriptValue> ScriptValue for Vec<T> {
+    fn script_type() -> ValueType {
+        ValueType::AutoArray(Box::new(T::script_type()))
+    }
+
+    fn to_value(&self) -> Value {
+        Value::AutoArray(self.iter().map(T::to_value).collect())
+    }
+
+    fn from_value(value: Value) -> Option<Vec<T>> {
+        match value {
+            Value::AutoArray(elements) => elements.into_iter().map(T::from_value).collect(),
+            _ => None
+        }
+    }
+}
+
+impl ScriptValue for () {
+    fn script_type() -> ValueType {
+        ValueType::Void
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Array(Vec::new())
+    }
+
+    fn from_value(_value: Value) -> Option<()> {
+        None
+    }
+
+    fn from_void() -> Option<()> {
+        Some(())
+    }
+}
+
+macro_rules! impl_script_args {
+    ($($arg:ident: $index:tt),*) => {
+        impl<$($arg: ScriptValue),*> ScriptArgs for ($($arg,)*) {
+            fn arg_types() -> Vec<ValueType> {
+                vec![$($arg::script_type()),*]
+            }
+
+            fn to_values(&self) -> Vec<Value> {
+                vec![$(self.$index.to_value()),*]
+            }
+        }
+    };
+}
+
+impl_script_args!();
+impl_script_args!(A: 0);
+impl_script_args!(A: 0, B: 1);
+impl_script_args!(A: 0, B: 1, C: 2);
+impl_script_args!(A: 0, B: 1, C: 2, D: 3);
+impl_script_args!(A: 0, B: 1, C: 2, D: 3, E: 4);
+impl_script_args!(A: 0, B: 1, C: 2, D: 3, E: 4, F: 5);
@@ -0,0 +1,471 @@
+use super::{
+    operand::{
+        OperandKind,
+        get_operand_kinds,
+        get_instruction_size,
+        parse_mnemonic,
+        parse_register,
+        is_identifier
+    }
+};
+use crate::{
+    vm::{
+        is::Opcode
+    },
+    codegen::{
+        instruction::Instruction,
+        program::Program,
+        uid_generator::UIDGenerator
+    }
+};
+
+use std::{
+    collections::{
+        HashMap
+    },
+    fmt::{
+        Display,
+        Formatter,
+        Result as FmtResult
+    },
+    ops::Range
+};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum AssemblerErrorType {
+    UnknownMnemonic(String),
+    UnknownDirective(String),
+    UnknownRegister(String),
+    UnknownLabel(String),
+    UnknownFunction(String),
+    DuplicateLabel(String),
+    InvalidOperand(String),
+    /// Expected and found amount of operands
+    OperandCount(usize, usize),
+    InvalidString
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct AssemblerError {
+    pub error_type: AssemblerErrorType,
+    /// Line of the error, starting at 1
+    pub line: usize
+}
+
+impl AssemblerError {
+    pub fn new(error_type: AssemblerErrorType, line: usize) -> AssemblerError {
+        AssemblerError {
+            error_type,
+            line
+        }
+    }
+}
+
+impl Display for AssemblerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "line {}: {:?}", self.line, self.error_type)
+    }
+}
+
+pub type AssemblerResult<T> = Result<T, AssemblerError>;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Section {
+    Data,
+    Code
+}
+
+enum Item {
+    Instr(Opcode, Vec<String>),
+    Bytes(Vec<u8>)
+}
+
+struct Line {
+    item: Item,
+    section: Section,
+    line: usize
+}
+
+/// Assembles the textual assembly format into a Program.
+///
+/// Every line holds an optional `label:`, followed by an instruction or a directive.
+/// Comments start with `;`. Operands are separated by commas and follow the encoding order,
+/// e.g. `LDI 58, r0`, `MOVI_RA r0, [sp-8]` or `JMPF r1, loop_end`.
+///
+/// Directives:
+/// * `.data` / `.code` switch sections, the data section is placed in front of the code
+/// * `.fn name` or `.fn name #uid` starts a function, which can be called with `CALL name`
+/// * `.bytes 1, 2`, `.string "text"`, `.i64 -1`, `.u64 8` and `.f32 1.5` emit raw bytes
+/// * `.static` in front of a data directive marks the bytes as a static variable
+pub struct Assembler {
+    source: String
+}
+
+impl Assembler {
+    pub fn new(source: String) -> Assembler {
+        Assembler {
+            source
+        }
+    }
+
+    pub fn assemble(&self) -> AssemblerResult<Program> {
+        let mut lines = Vec::new();
+        let mut labels: HashMap<String, (Section, usize)> = HashMap::new();
+        let mut function_labels: Vec<(String, Option<u64>)> = Vec::new();
+        let mut statics: Vec<(Section, Range<usize>)> = Vec::new();
+        let mut section = Section::Code;
+        let mut data_len = 0;
+        let mut code_len = 0;
+
+        // First pass, lays out the sections and collects the labels
+        for (i, raw_line) in self.source.lines().enumerate() {
+            let line_nr = i + 1;
+            let err = |error_type| AssemblerError::new(error_type, line_nr);
+            let mut rest = strip_comment(raw_line).trim();
+            let offset = match section {
+                Section::Data => data_len,
+                Section::Code => code_len
+            };
+
+            while let Some((label, tail)) = split_label(rest) {
+                if labels.insert(String::from(label), (section, offset)).is_some() {
+                    return Err(err(AssemblerErrorType::DuplicateLabel(String::from(label))));
+                }
+                rest = tail;
+            }
+            if rest.is_empty() {
+                continue;
+            }
+
+            let (head, mut args) = split_head(rest);
+            let mut is_static = false;
+            let mut directive = head;
+            if head == ".static" {
+                is_static = true;
+                let (next_head, next_args) = split_head(args);
+                directive = next_head;
+                args = next_args;
+            }
+
+            let item = match directive {
+                ".data" | ".code" if !is_static => {
+                    section = if directive == ".data" { Section::Data } else { Section::Code };
+                    continue;
+                },
+                ".fn" if !is_static => {
+                    let (name, uid) = split_head(args);
+                    if name.is_empty() {
+                        return Err(err(AssemblerErrorType::InvalidOperand(String::from(args))));
+                    }
+                    let uid = match uid {
+                        "" => None,
+                        uid => Some(
+                            parse_uid(uid)
+                                .ok_or_else(|| err(AssemblerErrorType::InvalidOperand(String::from(uid))))?
+                        )
+                    };
+                    if labels.insert(String::from(name), (section, offset)).is_some() {
+                        return Err(err(AssemblerErrorType::DuplicateLabel(String::from(name))));
+                    }
+                    function_labels.push((String::from(name), uid));
+                    continue;
+                },
+                _ if directive.starts_with('.') => {
+                    let bytes = parse_data_directive(directive, args)
+                        .map_err(err)?;
+                    if is_static {
+                        statics.push((section, offset..offset + bytes.len()));
+                    }
+                    Item::Bytes(bytes)
+                },
+                _ if is_static => {
+                    return Err(err(AssemblerErrorType::UnknownDirective(String::from(directive))));
+                },
+                mnemonic => {
+                    let opcode = parse_mnemonic(mnemonic)
+                        .ok_or_else(|| err(AssemblerErrorType::UnknownMnemonic(String::from(mnemonic))))?;
+                    let operands = split_operands(args);
+                    let expected = get_operand_kinds(&opcode).len();
+                    if operands.len() != expected {
+                        return Err(err(AssemblerErrorType::OperandCount(expected, operands.len())));
+                    }
+                    Item::Instr(opcode, operands)
+                }
+            };
+
+            let size = match &item {
+                Item::Instr(opcode, _) => get_instruction_size(opcode),
+                Item::Bytes(bytes) => bytes.len()
+            };
+            match section {
+                Section::Data => data_len += size,
+                Section::Code => code_len += size
+            };
+            lines.push(Line {
+                item,
+                section,
+                line: line_nr
+            });
+        }
+
+        let resolve = |(section, offset): (Section, usize)| match section {
+            Section::Data => offset,
+            Section::Code => data_len + offset
+        };
+        let addresses: HashMap<String, u64> = labels.into_iter()
+            .map(|(name, pos)| (name, resolve(pos) as u64))
+            .collect();
+
+        let mut uid_generator = UIDGenerator::new();
+        let mut functions = HashMap::new();
+        let mut function_uids = HashMap::new();
+        for (name, uid) in function_labels.iter() {
+            let uid = match uid {
+                Some(uid) => *uid,
+                None => uid_generator.get_function_uid(name)
+            };
+            functions.insert(uid, addresses[name] as usize);
+            function_uids.insert(name.clone(), uid);
+        }
+
+        // Second pass, encodes the instructions now that every label is known
+        let mut data = Vec::new();
+        let mut code = Vec::new();
+        for line in lines.into_iter() {
+            let line_nr = line.line;
+            let err = |error_type| AssemblerError::new(error_type, line_nr);
+            let mut bytes = match line.item {
+                Item::Bytes(bytes) => bytes,
+                Item::Instr(opcode, operands) => {
+                    let mut instr = Instruction::new(opcode.clone());
+                    for (kind, operand) in get_operand_kinds(&opcode).iter().zip(operands.iter()) {
+                        encode_operand(&mut instr, *kind, operand, &addresses, &function_uids)
+                            .map_err(err)?;
+                    }
+                    instr.get_code()
+                }
+            };
+            match line.section {
+                Section::Data => data.append(&mut bytes),
+                Section::Code => code.append(&mut bytes)
+            };
+        }
+        data.append(&mut code);
+
+        let static_vars = statics.into_iter()
+            .map(|(section, range)| resolve((section, range.start))..resolve((section, range.end)))
+            .collect();
+
+        let program = Program::new()
+            .with_code(data)
+            .with_functions(functions)
+            .with_static_vars(static_vars)
+            .with_function_uids(function_uids);
+
+        Ok(program)
+    }
+}
+
+fn encode_operand(instr: &mut Instruction, kind: OperandKind, operand: &str, addresses: &HashMap<String, u64>, function_uids: &HashMap<String, u64>) -> Result<(), AssemblerErrorType> {
+    let invalid = || AssemblerErrorType::InvalidOperand(String::from(operand));
+    match kind {
+        OperandKind::Register => {
+            let reg = parse_register(operand)
+                .ok_or_else(|| AssemblerErrorType::UnknownRegister(String::from(operand)))?;
+            instr.append_operand(reg);
+        },
+        OperandKind::Memory => {
+            let (reg, offset) = parse_memory(operand)?;
+            instr.append_operand(reg);
+            instr.append_operand(offset);
+        },
+        OperandKind::Int => instr.append_operand(parse_int::<i64>(operand).ok_or_else(invalid)?),
+        OperandKind::Float => instr.append_operand(operand.parse::<f32>().map_err(|_| invalid())?),
+        OperandKind::Bool => instr.append_operand(operand.parse::<bool>().map_err(|_| invalid())?),
+        OperandKind::Byte => instr.append_operand(parse_int::<u8>(operand).ok_or_else(invalid)?),
+        OperandKind::Word => instr.append_operand(parse_int::<u32>(operand).ok_or_else(invalid)?),
+        OperandKind::UInt |
+        OperandKind::Target => {
+            let value = match parse_int::<u64>(operand) {
+                Some(value) => value,
+                None if is_identifier(operand) => *addresses.get(operand)
+                    .ok_or_else(|| AssemblerErrorType::UnknownLabel(String::from(operand)))?,
+                None => return Err(invalid())
+            };
+            instr.append_operand(value);
+        },
+        OperandKind::Function => {
+            let uid = match parse_uid(operand) {
+                Some(uid) => uid,
+                None if is_identifier(operand) => *function_uids.get(operand)
+                    .ok_or_else(|| AssemblerErrorType::UnknownFunction(String::from(operand)))?,
+                None => return Err(invalid())
+            };
+            instr.append_operand(uid);
+        }
+    };
+    Ok(())
+}
+
+fn parse_data_directive(directive: &str, args: &str) -> Result<Vec<u8>, AssemblerErrorType> {
+    let values = split_operands(args);
+    let invalid = |value: &String| AssemblerErrorType::InvalidOperand(value.clone());
+    let mut bytes = Vec::new();
+    match directive {
+        ".string" => {
+            if values.len() != 1 {
+                return Err(AssemblerErrorType::InvalidString);
+            }
+            bytes = parse_string(&values[0])
+                .ok_or(AssemblerErrorType::InvalidString)?;
+        },
+        ".bytes" => for value in values.iter() {
+            bytes.push(parse_int::<u8>(value).ok_or_else(|| invalid(value))?);
+        },
+        ".i64" => for value in values.iter() {
+            bytes.extend_from_slice(&parse_int::<i64>(value).ok_or_else(|| invalid(value))?.to_le_bytes());
+        },
+        ".u64" => for value in values.iter() {
+            bytes.extend_from_slice(&parse_int::<u64>(value).ok_or_else(|| invalid(value))?.to_le_bytes());
+        },
+        ".f32" => for value in values.iter() {
+            bytes.extend_from_slice(&value.parse::<f32>().map_err(|_| invalid(value))?.to_le_bytes());
+        },
+        _ => return Err(AssemblerErrorType::UnknownDirective(String::from(directive)))
+    };
+    Ok(bytes)
+}
+
+/// Parses a memory operand like `[sp-8]`, `[r1+16]` or `[r2]`
+fn parse_memory(operand: &str) -> Result<(u8, i16), AssemblerErrorType> {
+    let invalid = || AssemblerErrorType::InvalidOperand(String::from(operand));
+    let inner = operand.strip_prefix('[')
+        .and_then(|o| o.strip_suffix(']'))
+        .ok_or_else(invalid)?
+        .trim();
+    let (reg_name, offset) = match inner.find(['+', '-']) {
+        Some(index) => {
+            let offset: String = inner[index..].chars()
+                .filter(|c| !c.is_whitespace())
+                .collect();
+            let offset = offset.trim_start_matches('+');
+            (inner[..index].trim(), parse_int::<i16>(offset).ok_or_else(invalid)?)
+        },
+        None => (inner, 0)
+    };
+    let reg = parse_register(reg_name)
+        .ok_or_else(|| AssemblerErrorType::UnknownRegister(String::from(reg_name)))?;
+    Ok((reg, offset))
+}
+
+/// Parses a decimal or `0x` prefixed hexadecimal integer
+fn parse_int<T: std::convert::TryFrom<i128>>(value: &str) -> Option<T> {
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value)
+    };
+    let magnitude = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+        None if digits.starts_with(|c: char| c.is_ascii_digit()) => digits.parse::<i128>().ok()?,
+        None => return None
+    };
+    T::try_from(if negative { -magnitude } else { magnitude }).ok()
+}
+
+/// Parses an explicit function uid like `#1234`
+fn parse_uid(value: &str) -> Option<u64> {
+    parse_int(value.strip_prefix('#')?)
+}
+
+fn parse_string(value: &str) -> Option<Vec<u8>> {
+    let inner = value.strip_prefix('"')?
+        .strip_suffix('"')?;
+    let mut bytes = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        let escaped = match chars.next()? {
+            'n' => b'\n',
+            't' => b'\t',
+            'r' => b'\r',
+            '0' => 0,
+            '\\' => b'\\',
+            '"' => b'"',
+            'x' => {
+                let hex: String = chars.by_ref().take(2).collect();
+                u8::from_str_radix(&hex, 16).ok()?
+            },
+            _ => return None
+        };
+        bytes.push(escaped);
+    }
+    Some(bytes)
+}
+
+/// Splits a leading `label:` off a line
+fn split_label(line: &str) -> Option<(&str, &str)> {
+    let (head, tail) = split_head(line);
+    let label = head.strip_suffix(':')?;
+    if is_identifier(label) && !label.ends_with(':') {
+        Some((label, tail))
+    } else {
+        None
+    }
+}
+
+/// Splits the first whitespace separated word off a line
+fn split_head(line: &str) -> (&str, &str) {
+    let line = line.trim();
+    match line.find(char::is_whitespace) {
+        Some(index) => (&line[..index], line[index..].trim_start()),
+        None => (line, "")
+    }
+}
+
+/// Removes a `;` comment, unless it is inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '\\' if in_string => {
+                escaped = !escaped;
+                continue;
+            },
+            '"' if !escaped => in_string = !in_string,
+            ';' if !in_string => return &line[..i],
+            _ => {}
+        };
+        escaped = false;
+    }
+    line
+}
+
+/// Splits operands at commas that are not inside a string
+fn split_operands(args: &str) -> Vec<String> {
+    let mut operands = Vec::new();
+    let mut current = String::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in args.chars() {
+        match c {
+            '"' if !escaped => in_string = !in_string,
+            ',' if !in_string => {
+                operands.push(current.trim().to_string());
+                current.clear();
+                continue;
+            },
+            _ => {}
+        };
+        escaped = c == '\\' && in_string && !escaped;
+        current.push(c);
+    }
+    if !current.trim().is_empty() || !operands.is_empty() {
+        operands.push(current.trim().to_string());
+    }
+    operands
+}
@@ -0,0 +1,297 @@
+use super::{
+    operand::{
+        OperandKind,
+        get_operand_kinds,
+        get_instruction_size,
+        get_mnemonic,
+        get_register_name,
+        is_identifier
+    }
+};
+use crate::{
+    vm::{
+        is::Opcode
+    },
+    codegen::{
+        program::Program
+    }
+};
+
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap
+    },
+    convert::{
+        TryInto
+    },
+    fmt::Write,
+    ops::Range
+};
+
+use num_traits::FromPrimitive;
+
+/// A decoded operand value
+#[derive(PartialEq, Debug, Clone)]
+enum Operand {
+    Register(u8),
+    Memory(u8, i16),
+    Int(i64),
+    UInt(u64),
+    Float(f32),
+    Bool(bool),
+    Target(u64),
+    Function(u64)
+}
+
+/// Renders a Program as assembly text, that assembles back into the same Program.
+///
+/// Everything in front of the first function is rendered as the data section.
+/// Bytes that do not decode to a valid instruction are rendered with `.bytes`.
+pub struct Disassembler<'p> {
+    program: &'p Program,
+    labels: HashMap<usize, String>
+}
+
+impl<'p> Disassembler<'p> {
+    pub fn new(program: &'p Program) -> Disassembler<'p> {
+        Disassembler {
+            program,
+            labels: HashMap::new()
+        }
+    }
+
+    /// Adds named labels at code offsets, e.g. the labels of the Builder that compiled the program
+    pub fn with_labels(mut self, labels: HashMap<usize, String>) -> Disassembler<'p> {
+        self.labels = labels;
+        self
+    }
+
+    pub fn disassemble(&self) -> String {
+        let code = &self.program.code;
+        let code_start = self.program.functions.values()
+            .min()
+            .cloned()
+            .unwrap_or(0)
+            .min(code.len());
+
+        let mut fn_names: HashMap<u64, String> = self.program.function_uids.iter()
+            .map(|(name, uid)| (*uid, name.clone()))
+            .collect();
+        for uid in self.program.functions.keys() {
+            fn_names.entry(*uid)
+                .or_insert_with(|| format!("fn_{}", uid));
+        }
+        let mut fn_starts: BTreeMap<usize, Vec<u64>> = BTreeMap::new();
+        for (uid, offset) in self.program.functions.iter() {
+            fn_starts.entry(*offset)
+                .or_default()
+                .push(*uid);
+        }
+        for uids in fn_starts.values_mut() {
+            uids.sort_by_key(|uid| fn_names[uid].clone());
+        }
+
+        // Decode until the first byte that is not a valid instruction
+        let mut instructions = Vec::new();
+        let mut offset = code_start;
+        while offset < code.len() {
+            match decode_instruction(&code[offset..]) {
+                Some((opcode, operands)) => {
+                    let size = get_instruction_size(&opcode);
+                    instructions.push((offset, opcode, operands));
+                    offset += size;
+                },
+                None => break
+            };
+        }
+        let code_end = offset;
+
+        let mut labels: HashMap<u64, String> = HashMap::new();
+        for (offset, label) in self.labels.iter() {
+            let is_instr = instructions.binary_search_by_key(offset, |(o, _, _)| *o).is_ok();
+            if is_instr && is_identifier(label) {
+                labels.insert(*offset as u64, label.clone());
+            }
+        }
+        for (_, _, operands) in instructions.iter() {
+            for operand in operands.iter() {
+                if let Operand::Target(target) = operand {
+                    let target = *target;
+                    let is_instr = instructions.binary_search_by_key(&(target as usize), |(o, _, _)| *o).is_ok();
+                    if !is_instr || labels.contains_key(&target) {
+                        continue;
+                    }
+                    let label = match fn_starts.get(&(target as usize)) {
+                        Some(uids) if is_identifier(&fn_names[&uids[0]]) => fn_names[&uids[0]].clone(),
+                        _ => format!("L{}", target)
+                    };
+                    labels.insert(target, label);
+                }
+            }
+        }
+
+        let mut out = String::new();
+        if code_start > 0 {
+            out.push_str(".data\n");
+            self.write_data(&mut out, 0..code_start);
+            out.push('\n');
+        }
+        out.push_str(".code\n");
+        for (offset, opcode, operands) in instructions.iter() {
+            if let Some(uids) = fn_starts.get(offset) {
+                out.push('\n');
+                for uid in uids.iter() {
+                    let _ = writeln!(out, ".fn {} #{}", fn_names[uid], uid);
+                }
+            }
+            if let Some(label) = labels.get(&(*offset as u64)) {
+                if fn_starts.get(offset).map(|uids| &fn_names[&uids[0]]) != Some(label) {
+                    let _ = writeln!(out, "{}:", label);
+                }
+            }
+
+            let mut comment = format!("{}", offset);
+            let rendered: Vec<String> = operands.iter()
+                .map(|operand| match operand {
+                    Operand::Register(reg) => get_register_name(*reg),
+                    Operand::Memory(reg, offset) => match offset {
+                        0 => format!("[{}]", get_register_name(*reg)),
+                        o if *o < 0 => format!("[{}{}]", get_register_name(*reg), o),
+                        o => format!("[{}+{}]", get_register_name(*reg), o)
+                    },
+                    Operand::Int(value) => value.to_string(),
+                    Operand::UInt(value) => value.to_string(),
+                    Operand::Float(value) => format!("{:?}", value),
+                    Operand::Bool(value) => value.to_string(),
+                    Operand::Target(target) => labels.get(target)
+                        .cloned()
+                        .unwrap_or_else(|| target.to_string()),
+                    Operand::Function(uid) => match fn_names.get(uid) {
+                        Some(name) if is_identifier(name) && self.program.functions.contains_key(uid) => name.clone(),
+                        Some(name) => {
+                            comment = format!("{}, {}", comment, name);
+                            format!("#{}", uid)
+                        },
+                        None => format!("#{}", uid)
+                    }
+                })
+                .collect();
+            let instr = format!("{} {}", get_mnemonic(opcode), rendered.join(", "));
+            write_line(&mut out, instr.trim_end(), &comment);
+        }
+
+        if code_end < code.len() {
+            out.push('\n');
+            write_bytes(&mut out, &code[code_end..], code_end);
+        }
+        out
+    }
+
+    /// Renders the data section, static variables are kept as separate `.static` entries
+    fn write_data(&self, out: &mut String, range: Range<usize>) {
+        let code = &self.program.code;
+        let mut statics: Vec<&Range<usize>> = self.program.static_vars.iter()
+            .filter(|r| r.start >= range.start && r.end <= range.end)
+            .collect();
+        statics.sort_by_key(|r| r.start);
+
+        let mut offset = range.start;
+        for static_range in statics.into_iter() {
+            if static_range.start < offset {
+                continue;
+            }
+            if static_range.start > offset {
+                write_chunk(out, &code[offset..static_range.start], offset);
+            }
+            let bytes = &code[static_range.clone()];
+            let directive = if bytes.chunks_exact(8).remainder().is_empty() {
+                let words: Vec<String> = bytes.chunks(8)
+                    .map(|word| u64::from_le_bytes(word.try_into().unwrap()).to_string())
+                    .collect();
+                format!(".static .u64 {}", words.join(", "))
+            } else {
+                let bytes: Vec<String> = bytes.iter()
+                    .map(|b| b.to_string())
+                    .collect();
+                format!(".static .bytes {}", bytes.join(", "))
+            };
+            write_line(out, &directive, &static_range.start.to_string());
+            offset = static_range.end;
+        }
+        if offset < range.end {
+            write_chunk(out, &code[offset..range.end], offset);
+        }
+    }
+}
+
+/// Renders a chunk of data, as a string if it is text
+fn write_chunk(out: &mut String, bytes: &[u8], offset: usize) {
+    match std::str::from_utf8(bytes) {
+        Ok(text) if text.chars().all(|c| !c.is_control() || c == '\n' || c == '\t') => {
+            let escaped: String = text.chars()
+                .map(|c| match c {
+                    '\n' => String::from("\\n"),
+                    '\t' => String::from("\\t"),
+                    '"' => String::from("\\\""),
+                    '\\' => String::from("\\\\"),
+                    c => c.to_string()
+                })
+                .collect();
+            write_line(out, &format!(".string \"{}\"", escaped), &offset.to_string());
+        },
+        _ => write_bytes(out, bytes, offset)
+    };
+}
+
+/// Renders raw bytes, 16 per line
+fn write_bytes(out: &mut String, bytes: &[u8], offset: usize) {
+    for (i, row) in bytes.chunks(16).enumerate() {
+        let row: Vec<String> = row.iter()
+            .map(|b| b.to_string())
+            .collect();
+        write_line(out, &format!(".bytes {}", row.join(", ")), &(offset + i * 16).to_string());
+    }
+}
+
+/// Writes an indented line, with a comment in a column
+fn write_line(out: &mut String, text: &str, comment: &str) {
+    let _ = writeln!(out, "    {:<39} ; {}", text, comment);
+}
+
+fn decode_instruction(bytes: &[u8]) -> Option<(Opcode, Vec<Operand>)> {
+    let opcode = Opcode::from_u8(*bytes.first()?)?;
+    if bytes.len() < get_instruction_size(&opcode) {
+        return None;
+    }
+    let mut pos = 1;
+    let mut take = |n: usize| {
+        let slice = &bytes[pos..pos + n];
+        pos += n;
+        slice
+    };
+    let mut operands = Vec::new();
+    for kind in get_operand_kinds(&opcode).iter() {
+        let operand = match kind {
+            OperandKind::Register => Operand::Register(take(1)[0]),
+            OperandKind::Memory => {
+                let reg = take(1)[0];
+                Operand::Memory(reg, i16::from_le_bytes(take(2).try_into().ok()?))
+            },
+            OperandKind::Int => Operand::Int(i64::from_le_bytes(take(8).try_into().ok()?)),
+            OperandKind::UInt => Operand::UInt(u64::from_le_bytes(take(8).try_into().ok()?)),
+            OperandKind::Float => Operand::Float(f32::from_le_bytes(take(4).try_into().ok()?)),
+            OperandKind::Bool => match take(1)[0] {
+                0 => Operand::Bool(false),
+                1 => Operand::Bool(true),
+                _ => return None
+            },
+            OperandKind::Byte => Operand::UInt(take(1)[0] as u64),
+            OperandKind::Word => Operand::UInt(u32::from_le_bytes(take(4).try_into().ok()?) as u64),
+            OperandKind::Target => Operand::Target(u64::from_le_bytes(take(8).try_into().ok()?)),
+            OperandKind::Function => Operand::Function(u64::from_le_bytes(take(8).try_into().ok()?))
+        };
+        operands.push(operand);
+    }
+    Some((opcode, operands))
+}
@@ -0,0 +1,8 @@
+/// Contains the operand layouts of the instruction set
+pub mod operand;
+
+/// Contains the assembler, which turns assembly text into a Program
+pub mod assembler;
+
+/// Contains the disassembler, which renders a Program as assembly text
+pub mod disassembler;
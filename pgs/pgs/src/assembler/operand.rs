@@ -0,0 +1,208 @@
+use crate::{
+    vm::{
+        is::Opcode
+    },
+    codegen::{
+        register::Register
+    }
+};
+
+use num_traits::FromPrimitive;
+
+/// The kind of an instruction operand, in the order they are encoded
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum OperandKind {
+    /// A register, e.g. `r1` or `sp`
+    Register,
+    /// A register with a signed offset, e.g. `[sp-8]`
+    Memory,
+    /// A signed 64 bit immediate
+    Int,
+    /// An unsigned 64 bit immediate, or the address of a label
+    UInt,
+    /// A 32 bit float immediate
+    Float,
+    /// A boolean immediate, `true` or `false`
+    Bool,
+    /// An unsigned 8 bit immediate
+    Byte,
+    /// An unsigned 32 bit immediate
+    Word,
+    /// A jump target, either a label or an address
+    Target,
+    /// A function uid, either a function name or `#uid`
+    Function
+}
+
+impl OperandKind {
+    /// Size of the encoded operand in bytes
+    pub fn get_size(&self) -> usize {
+        match self {
+            OperandKind::Register => 1,
+            OperandKind::Memory => 3,
+            OperandKind::Int => 8,
+            OperandKind::UInt => 8,
+            OperandKind::Float => 4,
+            OperandKind::Bool => 1,
+            OperandKind::Byte => 1,
+            OperandKind::Word => 4,
+            OperandKind::Target => 8,
+            OperandKind::Function => 8
+        }
+    }
+}
+
+use self::OperandKind::*;
+
+/// Gets the operand layout of an opcode, as read by the core
+pub fn get_operand_kinds(opcode: &Opcode) -> &'static [OperandKind] {
+    match opcode {
+        Opcode::NOOP |
+        Opcode::RET |
+        Opcode::ENDTRY |
+        Opcode::THROW => &[],
+        Opcode::HALT => &[Byte],
+        Opcode::MOVB |
+        Opcode::MOVF |
+        Opcode::MOVI |
+        Opcode::MOVA |
+        Opcode::NOT |
+        Opcode::NEGI |
+        Opcode::NEGF |
+        Opcode::ALLOC |
+        Opcode::DJMPT |
+        Opcode::DJMPF => &[Register, Register],
+        Opcode::MOVB_A |
+        Opcode::MOVF_A |
+        Opcode::MOVI_A |
+        Opcode::MOVA_A => &[Memory, Memory],
+        Opcode::MOVN_A => &[Memory, Memory, Word],
+        Opcode::MOVB_AR |
+        Opcode::MOVF_AR |
+        Opcode::MOVI_AR |
+        Opcode::MOVA_AR => &[Memory, Register],
+        Opcode::MOVB_RA |
+        Opcode::MOVF_RA |
+        Opcode::MOVI_RA |
+        Opcode::MOVA_RA => &[Register, Memory],
+        Opcode::LDB => &[Bool, Register],
+        Opcode::LDF => &[Float, Register],
+        Opcode::LDI => &[Int, Register],
+        Opcode::LDA |
+        Opcode::ALLOC_I => &[UInt, Register],
+        Opcode::ADDI |
+        Opcode::SUBI |
+        Opcode::MULI |
+        Opcode::DIVI |
+        Opcode::ADDU |
+        Opcode::SUBU |
+        Opcode::MULU |
+        Opcode::DIVU |
+        Opcode::ADDF |
+        Opcode::SUBF |
+        Opcode::MULF |
+        Opcode::DIVF |
+        Opcode::MODI |
+        Opcode::BANDI |
+        Opcode::BORI |
+        Opcode::BXORI |
+        Opcode::SHLI |
+        Opcode::SHRI |
+        Opcode::AND |
+        Opcode::OR |
+        Opcode::EQI |
+        Opcode::NEQI |
+        Opcode::LTI |
+        Opcode::GTI |
+        Opcode::LTEQI |
+        Opcode::GTEQI |
+        Opcode::EQF |
+        Opcode::NEQF |
+        Opcode::LTF |
+        Opcode::GTF |
+        Opcode::LTEQF |
+        Opcode::GTEQF |
+        Opcode::SCONCAT |
+        Opcode::SCMP => &[Register, Register, Register],
+        Opcode::ADDI_I |
+        Opcode::SUBI_I |
+        Opcode::MULI_I |
+        Opcode::DIVI_I |
+        Opcode::MODI_I |
+        Opcode::BANDI_I |
+        Opcode::BORI_I |
+        Opcode::BXORI_I |
+        Opcode::SHLI_I |
+        Opcode::SHRI_I => &[Register, Int, Register],
+        Opcode::ADDU_I |
+        Opcode::SUBU_I |
+        Opcode::MULU_I |
+        Opcode::DIVU_I => &[Register, UInt, Register],
+        Opcode::ADDF_I |
+        Opcode::SUBF_I |
+        Opcode::MULF_I |
+        Opcode::DIVF_I => &[Register, Float, Register],
+        Opcode::JMP |
+        Opcode::TRY => &[Target],
+        Opcode::JMPT |
+        Opcode::JMPF => &[Register, Target],
+        Opcode::DJMP |
+        Opcode::FREE => &[Register],
+        Opcode::CALL => &[Function],
+        Opcode::BNDCHK => &[Register, UInt],
+        Opcode::BNDCHK_R => &[Register, Register],
+        Opcode::SSLICE => &[Register, Register, Register, Register]
+    }
+}
+
+/// Gets the encoded size of an instruction, including the opcode
+pub fn get_instruction_size(opcode: &Opcode) -> usize {
+    get_operand_kinds(opcode).iter()
+        .map(|kind| kind.get_size())
+        .sum::<usize>() + 1
+}
+
+/// Gets the assembly mnemonic of an opcode
+pub fn get_mnemonic(opcode: &Opcode) -> String {
+    format!("{:?}", opcode)
+}
+
+/// Looks up an opcode by its mnemonic, case insensitive
+pub fn parse_mnemonic(mnemonic: &str) -> Option<Opcode> {
+    (0..=u8::MAX)
+        .filter_map(Opcode::from_u8)
+        .find(|opcode| get_mnemonic(opcode).eq_ignore_ascii_case(mnemonic))
+}
+
+/// Gets the assembly name of a register, e.g. `r1` or `sp`
+pub fn get_register_name(reg: u8) -> String {
+    match Register::from_u8(reg) {
+        Some(Register::SP) => String::from("sp"),
+        Some(Register::IP) => String::from("ip"),
+        Some(_) => format!("r{}", reg),
+        None => format!("?{}", reg)
+    }
+}
+
+/// Looks up a register by its assembly name, case insensitive
+pub fn parse_register(name: &str) -> Option<u8> {
+    let name = name.to_ascii_lowercase();
+    match name.as_str() {
+        "sp" => Some(Register::SP.into()),
+        "ip" => Some(Register::IP.into()),
+        _ => {
+            let index: u8 = name.strip_prefix('r')?
+                .parse()
+                .ok()?;
+            Register::from_u8(index)
+                .filter(|reg| *reg != Register::SP && *reg != Register::IP)
+                .map(|reg| reg.into())
+        }
+    }
+}
+
+/// Returns true if the value can be used as a label or function name
+pub fn is_identifier(value: &str) -> bool {
+    value.starts_with(|c: char| c.is_alphabetic() || c == '_') &&
+        value.chars().all(|c| c.is_alphanumeric() || c == '_' || c == ':')
+}
@@ -72,7 +72,8 @@ impl Builder {
     pub fn push_instr(&mut self, instruction: Instruction) {
         if instruction.opcode == Opcode::JMP ||
             instruction.opcode == Opcode::JMPT ||
-            instruction.opcode == Opcode::JMPF {
+            instruction.opcode == Opcode::JMPF ||
+            instruction.opcode == Opcode::TRY {
             self.jmp_instructions.push(self.instructions.len());
         }
         self.instructions.push(instruction);
@@ -100,7 +101,7 @@ impl Builder {
         code
     }
 
-    pub fn get_label_offset(&mut self, label: &String) -> Option<usize> {
+    pub fn get_label_offset(&self, label: &String) -> Option<usize> {
         let mut code_before_size = 0;
         let label_instr_offset = self.labels.get(label)
             .or(None)?;
@@ -0,0 +1,399 @@
+use crate::{
+    api::{
+        function::Function,
+        script_function::ScriptFunction
+    },
+    codegen::{
+        program::Program
+    },
+    parser::{
+        ast::{
+            Type
+        }
+    }
+};
+
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap
+    },
+    convert::{
+        TryInto
+    },
+    error::Error,
+    fmt::{
+        Display,
+        Formatter,
+        Result as FmtResult
+    },
+    ops::Range
+};
+
+/// Magic header of precompiled (.pgc) programs
+pub const PGC_MAGIC: &[u8; 4] = b"PGC\0";
+/// Version of the precompiled program format
+pub const PGC_VERSION: u16 = 2;
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum BytecodeError {
+    /// The data does not start with the magic header
+    InvalidMagic,
+    /// The data was written by an incompatible version of the format
+    UnsupportedVersion(u16),
+    /// The data ended early or contains invalid values
+    Malformed,
+    /// The program calls a foreign function that is not registered
+    MissingForeignFunction(String),
+    /// The registered foreign function has a different signature than the program expects
+    ForeignFunctionMismatch {
+        name: String,
+        expected: String,
+        found: String
+    }
+}
+
+impl Display for BytecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            BytecodeError::InvalidMagic => write!(f, "not a precompiled program"),
+            BytecodeError::UnsupportedVersion(version) => write!(f, "unsupported bytecode version {} (expected {})", version, PGC_VERSION),
+            BytecodeError::Malformed => write!(f, "malformed bytecode"),
+            BytecodeError::MissingForeignFunction(name) => write!(f, "missing foreign function {}", name),
+            BytecodeError::ForeignFunctionMismatch { name, expected, found } => write!(f, "foreign function {} has signature {}, but the program expects {}", name, found, expected)
+        }
+    }
+}
+
+impl Error for BytecodeError {
+}
+
+pub type BytecodeResult<T> = Result<T, BytecodeError>;
+
+/// Returns true if the data starts with the magic header
+pub fn is_bytecode(bytes: &[u8]) -> bool {
+    bytes.starts_with(PGC_MAGIC)
+}
+
+/// Renders the signature of a function, for error messages
+pub fn signature_string(arg_types: &[Type], return_type: &Type) -> String {
+    let args: Vec<String> = arg_types.iter().map(|t| format!("{:?}", t)).collect();
+    format!("({}) ~ {:?}", args.join(", "), return_type)
+}
+
+/// Serializes a program into the precompiled format.
+/// Script functions are stored with their signature, so they can be called after loading.
+/// Foreign functions are stored by name and signature only, they have to be relinked on load
+pub fn write_program(program: &Program) -> BytecodeResult<Vec<u8>> {
+    let mut writer = ByteWriter::new();
+    writer.bytes.extend_from_slice(PGC_MAGIC);
+    writer.bytes.extend_from_slice(&PGC_VERSION.to_le_bytes());
+
+    writer.write_bytes(&program.code);
+
+    // Sort by name, so the same program always produces the same bytes
+    let mut names: Vec<(&String, &u64)> = program.function_uids.iter().collect();
+    names.sort();
+
+    let functions: Vec<(&String, u64, usize)> = names.iter()
+        .filter_map(|(name, uid)| program.functions.get(uid).map(|offset| (*name, **uid, *offset)))
+        .collect();
+    writer.write_usize(functions.len());
+    for (name, uid, offset) in functions {
+        writer.write_str(name);
+        writer.write_u64(uid);
+        writer.write_usize(offset);
+        let function = program.script_functions.get(&uid)
+            .ok_or(BytecodeError::Malformed)?;
+        writer.write_usize(function.arguments.len());
+        for (arg_name, arg_type) in function.arguments.iter() {
+            writer.write_str(arg_name);
+            writer.write_type(arg_type);
+        }
+        writer.write_type(&function.return_type);
+    }
+
+    writer.write_usize(program.static_vars.len());
+    for range in program.static_vars.iter() {
+        writer.write_range(range);
+    }
+
+    writer.write_usize(program.static_pointers.len());
+    for (offset, range) in program.static_pointers.iter() {
+        writer.write_usize(*offset);
+        writer.write_range(range);
+    }
+
+    let foreign_functions: Vec<(&String, u64, &Function)> = names.iter()
+        .filter_map(|(name, uid)| program.foreign_functions.get(uid).map(|function| (*name, **uid, function)))
+        .collect();
+    if foreign_functions.len() != program.foreign_functions.len() {
+        return Err(BytecodeError::Malformed);
+    }
+    writer.write_usize(foreign_functions.len());
+    for (name, uid, function) in foreign_functions {
+        writer.write_str(name);
+        writer.write_u64(uid);
+        writer.write_usize(function.arg_types.len());
+        for arg_type in function.arg_types.iter() {
+            writer.write_type(arg_type);
+        }
+        writer.write_type(&function.return_type);
+    }
+
+    Ok(writer.bytes)
+}
+
+/// Deserializes a program from the precompiled format.
+/// The foreign functions of the returned program only carry their signature
+pub fn read_program(bytes: &[u8]) -> BytecodeResult<Program> {
+    if !is_bytecode(bytes) {
+        return Err(BytecodeError::InvalidMagic);
+    }
+    let mut reader = ByteReader::new(&bytes[PGC_MAGIC.len()..]);
+    let version = reader.read_u16()?;
+    if version != PGC_VERSION {
+        return Err(BytecodeError::UnsupportedVersion(version));
+    }
+
+    let code = reader.read_bytes()?.to_vec();
+
+    let mut function_uids = HashMap::new();
+    let mut functions = HashMap::new();
+    let mut script_functions = HashMap::new();
+    for _ in 0..reader.read_usize()? {
+        let name = reader.read_string()?;
+        let uid = reader.read_u64()?;
+        let offset = reader.read_usize()?;
+        if offset >= code.len() {
+            return Err(BytecodeError::Malformed);
+        }
+        let mut function = ScriptFunction::new(&name);
+        for _ in 0..reader.read_usize()? {
+            let arg_name = reader.read_string()?;
+            function.arguments.push((arg_name, reader.read_type()?));
+        }
+        function.return_type = reader.read_type()?;
+        function_uids.insert(name, uid);
+        functions.insert(uid, offset);
+        script_functions.insert(uid, function);
+    }
+
+    let mut static_vars = Vec::new();
+    for _ in 0..reader.read_usize()? {
+        static_vars.push(reader.read_range(code.len())?);
+    }
+
+    let mut static_pointers = BTreeMap::new();
+    for _ in 0..reader.read_usize()? {
+        let offset = reader.read_usize()?;
+        static_pointers.insert(offset, reader.read_range(code.len())?);
+    }
+
+    let mut foreign_functions = HashMap::new();
+    for _ in 0..reader.read_usize()? {
+        let name = reader.read_string()?;
+        let uid = reader.read_u64()?;
+        let short_name = name.rsplit("::").next()
+            .unwrap_or(&name);
+        let mut function = Function::new(short_name);
+        for _ in 0..reader.read_usize()? {
+            function.arg_types.push(reader.read_type()?);
+        }
+        function.return_type = reader.read_type()?;
+        function_uids.insert(name, uid);
+        foreign_functions.insert(uid, function);
+    }
+
+    if !reader.is_empty() {
+        return Err(BytecodeError::Malformed);
+    }
+
+    let program = Program::new()
+        .with_code(code)
+        .with_functions(functions)
+        .with_foreign_functions(foreign_functions)
+        .with_static_pointers(static_pointers)
+        .with_static_vars(static_vars)
+        .with_function_uids(function_uids)
+        .with_script_functions(script_functions);
+
+    Ok(program)
+}
+
+const TYPE_VOID: u8 = 0;
+const TYPE_INT: u8 = 1;
+const TYPE_STRING: u8 = 2;
+const TYPE_FLOAT: u8 = 3;
+const TYPE_BOOL: u8 = 4;
+const TYPE_AUTO: u8 = 5;
+const TYPE_ARRAY: u8 = 6;
+const TYPE_AUTO_ARRAY: u8 = 7;
+const TYPE_OTHER: u8 = 8;
+const TYPE_TUPLE: u8 = 9;
+const TYPE_REFERENCE: u8 = 10;
+
+struct ByteWriter {
+    bytes: Vec<u8>
+}
+
+impl ByteWriter {
+    fn new() -> ByteWriter {
+        ByteWriter {
+            bytes: Vec::new()
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_usize(bytes.len());
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn write_str(&mut self, value: &str) {
+        self.write_bytes(value.as_bytes());
+    }
+
+    fn write_range(&mut self, range: &Range<usize>) {
+        self.write_usize(range.start);
+        self.write_usize(range.end);
+    }
+
+    fn write_type(&mut self, var_type: &Type) {
+        match var_type {
+            Type::Void => self.bytes.push(TYPE_VOID),
+            Type::Int => self.bytes.push(TYPE_INT),
+            Type::String => self.bytes.push(TYPE_STRING),
+            Type::Float => self.bytes.push(TYPE_FLOAT),
+            Type::Bool => self.bytes.push(TYPE_BOOL),
+            Type::Auto => self.bytes.push(TYPE_AUTO),
+            Type::Array(inner_type, size) => {
+                self.bytes.push(TYPE_ARRAY);
+                self.write_type(inner_type);
+                self.write_usize(*size);
+            },
+            Type::AutoArray(inner_type) => {
+                self.bytes.push(TYPE_AUTO_ARRAY);
+                self.write_type(inner_type);
+            },
+            Type::Other(name) => {
+                self.bytes.push(TYPE_OTHER);
+                self.write_str(name);
+            },
+            Type::Tuple(types) => {
+                self.bytes.push(TYPE_TUPLE);
+                self.write_usize(types.len());
+                for inner_type in types.iter() {
+                    self.write_type(inner_type);
+                }
+            },
+            Type::Reference(inner_type) => {
+                self.bytes.push(TYPE_REFERENCE);
+                self.write_type(inner_type);
+            }
+        }
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8]
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> ByteReader<'a> {
+        ByteReader {
+            bytes
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> BytecodeResult<&'a [u8]> {
+        if len > self.bytes.len() {
+            return Err(BytecodeError::Malformed);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn read_u8(&mut self) -> BytecodeResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u16(&mut self) -> BytecodeResult<u16> {
+        let bytes = self.take(2)?
+            .try_into()
+            .map_err(|_| BytecodeError::Malformed)?;
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    fn read_u64(&mut self) -> BytecodeResult<u64> {
+        let bytes = self.take(8)?
+            .try_into()
+            .map_err(|_| BytecodeError::Malformed)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn read_usize(&mut self) -> BytecodeResult<usize> {
+        self.read_u64()?
+            .try_into()
+            .map_err(|_| BytecodeError::Malformed)
+    }
+
+    fn read_bytes(&mut self) -> BytecodeResult<&'a [u8]> {
+        let len = self.read_usize()?;
+        self.take(len)
+    }
+
+    fn read_string(&mut self) -> BytecodeResult<String> {
+        let bytes = self.read_bytes()?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| BytecodeError::Malformed)
+    }
+
+    fn read_range(&mut self, max: usize) -> BytecodeResult<Range<usize>> {
+        let start = self.read_usize()?;
+        let end = self.read_usize()?;
+        if start > end || end > max {
+            return Err(BytecodeError::Malformed);
+        }
+        Ok(start..end)
+    }
+
+    fn read_type(&mut self) -> BytecodeResult<Type> {
+        let var_type = match self.read_u8()? {
+            TYPE_VOID => Type::Void,
+            TYPE_INT => Type::Int,
+            TYPE_STRING => Type::String,
+            TYPE_FLOAT => Type::Float,
+            TYPE_BOOL => Type::Bool,
+            TYPE_AUTO => Type::Auto,
+            TYPE_ARRAY => {
+                let inner_type = self.read_type()?;
+                Type::Array(Box::new(inner_type), self.read_usize()?)
+            },
+            TYPE_AUTO_ARRAY => Type::AutoArray(Box::new(self.read_type()?)),
+            TYPE_OTHER => Type::Other(self.read_string()?),
+            TYPE_TUPLE => {
+                let mut types = Vec::new();
+                for _ in 0..self.read_usize()? {
+                    types.push(self.read_type()?);
+                }
+                Type::Tuple(types)
+            },
+            TYPE_REFERENCE => Type::Reference(Box::new(self.read_type()?)),
+            _ => return Err(BytecodeError::Malformed)
+        };
+        Ok(var_type)
+    }
+}
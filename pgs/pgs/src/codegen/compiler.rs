@@ -1,7 +1,12 @@
 use crate::{
     api::{
         module::Module,
-        function::Function
+        function::Function,
+        script_function::ScriptFunction,
+        container::{
+            Container,
+            ContainerMember
+        }
     },
     codegen::{
         context::{
@@ -13,7 +18,8 @@ use crate::{
         uid_generator::UIDGenerator,
         def::{
             ContainerDef,
-            FunctionDef
+            FunctionDef,
+            StaticVarDef
         },
         builder::{
             Builder
@@ -35,9 +41,14 @@ use crate::{
         ast::{
             Declaration,
             Statement,
+            Spanned,
+            Span,
             Type,
             Expression,
-            IfStatementArgs
+            IfStatementArgs,
+            ForStatementArgs,
+            TryStatementArgs,
+            VariableDeclArgs
         }
     },
     vm::{
@@ -68,6 +79,9 @@ use std::{
     }
 };
 
+/// Name of the builtin function returning the length of a string or an array
+pub const LEN_INTRINSIC: &str = "len";
+
 #[derive(Debug, Clone)]
 pub enum CompilerError {
     Unknown,
@@ -93,13 +107,77 @@ pub enum CompilerError {
     MemberAccessOnNonContainer,
     TypeMismatch(Type, Type),
     CannotDerefNonPointer,
+    CannotFreeNonPointer(Type),
     CannotDerefSlice,
-    RegisterMapping
+    EmptyArrayLiteral,
+    NotIndexable(Type),
+    NotSliceable(Type),
+    NonConstantStatic(String),
+    /// Native containers can only be created by their functions
+    NativeContainerInstance(String),
+    RegisterMapping,
+    /// An error raised while compiling the declaration, statement or expression at the given span
+    At(Span, Box<CompilerError>),
+    /// Errors of several declarations
+    Multiple(Vec<CompilerError>)
+}
+
+impl CompilerError {
+    /// Attaches a source span to the error, the innermost span is kept if there already is one
+    pub fn at(self, span: Span) -> CompilerError {
+        match self {
+            CompilerError::At(_, _) => self,
+            CompilerError::Multiple(_) => self,
+            _ => CompilerError::At(span, Box::new(self))
+        }
+    }
+
+    /// Combines a list of errors, a single error is returned as is
+    pub fn from_errors(mut errors: Vec<CompilerError>) -> CompilerResult<()> {
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(CompilerError::Multiple(errors))
+        }
+    }
+
+    /// Returns the list of errors contained in this error
+    pub fn into_errors(self) -> Vec<CompilerError> {
+        match self {
+            CompilerError::Multiple(errors) => errors,
+            _ => vec![self]
+        }
+    }
+
+    /// Returns the source span of the error, if it is known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            CompilerError::At(span, _) => Some(span.clone()),
+            _ => None
+        }
+    }
+
+    /// Returns the error without its source span
+    pub fn without_span(self) -> CompilerError {
+        match self {
+            CompilerError::At(_, err) => *err,
+            _ => self
+        }
+    }
 }
 
 impl Display for CompilerError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        write!(f, "{:?}", self)
+        match self {
+            CompilerError::At(_, err) => write!(f, "{}", err),
+            CompilerError::Multiple(errors) => {
+                let messages: Vec<String> = errors.iter()
+                    .map(|err| err.to_string())
+                    .collect();
+                write!(f, "{}", messages.join("\n"))
+            },
+            _ => write!(f, "{:?}", self)
+        }
     }
 }
 
@@ -109,11 +187,14 @@ impl Error for CompilerError {}
 pub type CompilerResult<T> = Result<T, CompilerError>;
 
 /// The compiler
+#[derive(Clone)]
 pub struct Compiler {
     fn_context_stack: VecDeque<FunctionContext>,
     mod_context_stack: VecDeque<ModuleContext>,
     loop_ctx_stack: VecDeque<LoopContext>,
     fn_uid_map: HashMap<String, u64>,
+    /// Spans of the script function declarations, by full function name
+    fn_decl_spans: HashMap<String, Span>,
     foreign_functions: Option<HashMap<u64, Function>>,
     foreign_function_uids: HashSet<u64>,
     uid_generator: UIDGenerator,
@@ -133,6 +214,7 @@ impl Compiler {
             mod_context_stack: mod_context_stack,
             loop_ctx_stack: VecDeque::new(),
             fn_uid_map: HashMap::new(),
+            fn_decl_spans: HashMap::new(),
             foreign_functions: Some(HashMap::new()),
             foreign_function_uids: HashSet::new(),
             uid_generator: UIDGenerator::new(),
@@ -158,7 +240,7 @@ impl Compiler {
             let instr = builder.get_instr(offset)
                 .ok_or(CompilerError::Unknown)?;
             let addr: u64 = match instr.opcode {
-                Opcode::JMP => instr.get_operand(0, 8),
+                Opcode::JMP | Opcode::TRY => instr.get_operand(0, 8),
                 Opcode::JMPF => instr.get_operand(1, 8),
                 Opcode::JMPT => instr.get_operand(1, 8),
                 _ => return Err(CompilerError::Unknown)
@@ -168,6 +250,7 @@ impl Compiler {
         }
 
         let mut functions: HashMap<u64, usize> = HashMap::new();
+        let mut script_functions = HashMap::new();
 
         // correctly set function offsets
         for (fn_name, fn_uid) in self.fn_uid_map.iter() {
@@ -175,14 +258,18 @@ impl Compiler {
                 continue;
             }
             let fn_offset = builder.get_label_offset(fn_name)
-                .ok_or(CompilerError::Unknown)?;
+                .ok_or_else(|| self.at_fn_decl(fn_name, CompilerError::UnknownFunction(fn_name.clone())))?;
             functions.insert(fn_uid.clone(), fn_offset + data_len);
+            let script_function = self.get_script_function(fn_name)
+                .map_err(|err| self.at_fn_decl(fn_name, err))?;
+            script_functions.insert(*fn_uid, script_function);
         }
 
-        let foreign_functions = self.foreign_functions.take()
+        let foreign_functions = self.foreign_functions.clone()
             .ok_or(CompilerError::Unknown)?;
 
 
+        let static_vars = data.get_statics().to_vec();
         let mut code = data.bytes;
         let mut builder_code = builder.build();
         //println!("Data length: {}", code.len());
@@ -191,7 +278,10 @@ impl Compiler {
         let program = Program::new()
             .with_code(code)
             .with_functions(functions)
-            .with_foreign_functions(foreign_functions);
+            .with_foreign_functions(foreign_functions)
+            .with_static_vars(static_vars)
+            .with_function_uids(self.fn_uid_map.clone())
+            .with_script_functions(script_functions);
         
         Ok(program)
     }
@@ -319,6 +409,47 @@ impl Compiler {
             .ok_or(CompilerError::UnknownFunction(name.clone()))
     }
 
+    /// Returns the full names of all declared script functions, sorted
+    pub fn get_function_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.fn_uid_map.iter()
+            .filter(|(_, uid)| !self.foreign_function_uids.contains(uid))
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Gets the signature of a script function by its full name, container types are canonized
+    pub fn get_script_function(&self, name: &str) -> CompilerResult<ScriptFunction> {
+        let mut fn_def = self.resolve_function(&String::from(name))?;
+        for (_, arg_type) in fn_def.arguments.iter_mut() {
+            self.canonize_type(arg_type)?;
+        }
+        self.canonize_type(&mut fn_def.ret_type)?;
+        Ok(ScriptFunction {
+            name: String::from(name),
+            arguments: fn_def.arguments,
+            return_type: fn_def.ret_type
+        })
+    }
+
+    /// Attaches the span of a script function's declaration to an error, if it is known
+    fn at_fn_decl(&self, name: &str, err: CompilerError) -> CompilerError {
+        match self.fn_decl_spans.get(name) {
+            Some(span) => err.at(span.clone()),
+            None => err
+        }
+    }
+
+    /// Gets a registered foreign function by its full name
+    pub fn get_foreign_function(&self, name: &String) -> CompilerResult<Function> {
+        let uid = self.get_function_uid(name)?;
+        self.foreign_functions.as_ref()
+            .and_then(|functions| functions.get(&uid))
+            .cloned()
+            .ok_or(CompilerError::UnknownFunction(name.clone()))
+    }
+
     /// Resolves a function by name to a FunctionDef
     pub fn resolve_function(&self, name: &String) -> CompilerResult<FunctionDef> {
         //println!("Resolving function: {}", name);
@@ -405,7 +536,8 @@ impl Compiler {
             }
 
             for i in start_i..path_fragments.len() - 1 {
-                let mod_ctx = mod_ctx_opt.unwrap();
+                let mod_ctx = mod_ctx_opt
+                    .ok_or(CompilerError::UnknownContainer(name.clone()))?;
                 //println!("Blub");
                 mod_ctx_opt = mod_ctx.modules.get(&path_fragments[i]);
             }
@@ -414,7 +546,8 @@ impl Compiler {
 
             //println!("Resolving function {} for mod_ctx {}", last_path, mod_ctx_opt.as_ref().unwrap().name);
 
-            let mod_ctx = mod_ctx_opt.unwrap();
+            let mod_ctx = mod_ctx_opt
+                .ok_or(CompilerError::UnknownContainer(name.clone()))?;
             return mod_ctx.containers.get(last_path)
                 .cloned()
                 .ok_or(CompilerError::UnknownContainer(name.clone()));
@@ -435,11 +568,49 @@ impl Compiler {
         }
     }
 
+    /// Resolves a static variable by name to a StaticVarDef
+    pub fn resolve_static_var(&self, name: &String) -> CompilerResult<StaticVarDef> {
+        if name.contains("::") {
+            let path_fragments: Vec<String> = name.split("::").map(|s| String::from(s)).collect();
+            let mut start_i = 0;
+            let mut mod_ctx = if path_fragments[0] == "root" {
+                start_i = 1;
+                self.get_root_module()?
+            } else {
+                self.get_current_module()?
+            };
+
+            for i in start_i..path_fragments.len() - 1 {
+                mod_ctx = mod_ctx.modules.get(&path_fragments[i])
+                    .ok_or(CompilerError::UnknownModule(path_fragments[i].clone()))?;
+            }
+
+            let last_path = path_fragments.last().unwrap();
+            mod_ctx.get_static_var(last_path)
+                .cloned()
+                .map_err(|_| CompilerError::UnknownVariable(name.clone()))
+        } else {
+            let mod_ctx = self.get_current_module()?;
+            if mod_ctx.static_vars.contains_key(name) {
+                return mod_ctx.get_static_var(name)
+                    .cloned();
+            }
+            if mod_ctx.imports.contains_key(name) {
+                let import_path = mod_ctx.imports.get(name)
+                    .ok_or(CompilerError::Unknown)?;
+                return self.resolve_static_var(import_path);
+            }
+
+            Err(CompilerError::UnknownVariable(name.clone()))
+        }
+    }
+
     /// Returns the byte size of a given Type
     pub fn get_size_of_type(&self, var_type: &Type) -> CompilerResult<usize> {
         //println!("Getting size of type");
         let size = match var_type {
             Type::String => 16,
+            Type::AutoArray(_) => 16,
             Type::Void => 0,
             Type::Int => 8,
             Type::Reference(inner) => {
@@ -477,9 +648,82 @@ impl Compiler {
                 type_opt = Some(var_type_res.unwrap());
                 break;
             }
+            // Variables of previously compiled functions are not visible
+            if !fn_ctx.weak {
+                break;
+            }
+        }
+
+        match type_opt {
+            Some(var_type) => Ok(var_type),
+            None => {
+                let static_def = self.resolve_static_var(var_name)?;
+                Ok(static_def.var_type)
+            }
+        }
+    }
+
+    /// Checks if a variable is a static variable, local variables shadow static ones
+    pub fn is_static_var(&self, var_name: &String) -> bool {
+        for fn_ctx in self.fn_context_stack.iter() {
+            if fn_ctx.get_var_type(var_name).is_ok() {
+                return false;
+            }
+            if !fn_ctx.weak {
+                break;
+            }
+        }
+        self.resolve_static_var(var_name).is_ok()
+    }
+
+    /// Returns the base register and the offset of a variable.
+    /// Local variables are relative to SP, the address of a static variable is loaded into a new register.
+    pub fn get_var_location(&mut self, var_name: &String) -> CompilerResult<(Register, i64)> {
+        if !self.is_static_var(var_name) {
+            let var_offset = self.get_sp_offset_of_var(var_name)?;
+            return Ok((Register::SP, var_offset));
         }
+        let static_def = self.resolve_static_var(var_name)?;
+        let addr_reg = self.get_next_register()?;
+        let lda_instr = Instruction::new(Opcode::LDA)
+            .with_operand::<u64>(static_def.address)
+            .with_operand::<u8>(addr_reg.clone().into());
+        self.builder.push_instr(lda_instr);
+        Ok((addr_reg, 0))
+    }
+
+    /// Loads the address of a variable into the given register
+    pub fn compile_var_addr(&mut self, var_name: &String, target_reg: Register) -> CompilerResult<()> {
+        let addr_instr = if self.is_static_var(var_name) {
+            let static_def = self.resolve_static_var(var_name)?;
+            Instruction::new(Opcode::LDA)
+                .with_operand::<u64>(static_def.address)
+                .with_operand::<u8>(target_reg.into())
+        } else {
+            let var_offset = self.get_sp_offset_of_var(var_name)?;
+            Instruction::new(Opcode::SUBU_I)
+                .with_operand::<u8>(Register::SP.into())
+                .with_operand::<u64>(var_offset.unsigned_abs())
+                .with_operand::<u8>(target_reg.into())
+        };
+        self.builder.push_instr(addr_instr);
+        Ok(())
+    }
 
-        type_opt.ok_or(CompilerError::UnknownVariable(var_name.clone()))
+    /// Loads the pointer stored in a reference variable into the target register
+    pub fn compile_var_ptr(&mut self, var_name: &String, target_reg: Register) -> CompilerResult<()> {
+        let (base_reg, var_offset) = if self.is_static_var(var_name) {
+            self.compile_var_addr(var_name, target_reg.clone())?;
+            (target_reg.clone(), 0)
+        } else {
+            (Register::SP, self.get_sp_offset_of_var(var_name)?)
+        };
+        let mova_instr = Instruction::new(Opcode::MOVA_AR)
+            .with_operand::<u8>(base_reg.into())
+            .with_operand::<i16>(var_offset as i16)
+            .with_operand::<u8>(target_reg.into());
+        self.builder.push_instr(mova_instr);
+        Ok(())
     }
 
     /// Returns the offset to SP for a given variable
@@ -536,8 +780,15 @@ impl Compiler {
 
         self.push_module_context(mod_ctx);
 
+        // Containers come first, so the signatures of functions can refer to them
+        for (_, container) in module.containers {
+            self.register_foreign_container(container, &path)?;
+        }
+
         for (_, function) in module.functions {
-            self.register_foreign_function(function, &path)?;
+            let fn_def = self.register_foreign_function(function, &path)?;
+            self.get_current_module_mut()?
+                .add_function(fn_def)?;
         }
 
         for (_, module) in module.modules {
@@ -552,7 +803,44 @@ impl Compiler {
         Ok(())
     }
 
-    fn register_foreign_function(&mut self, mut function: Function, path: &String) -> CompilerResult<()> {
+    /// Registers a native container, its instances are foreign pointers.
+    /// Member functions and accessors get the reference to the instance as their first argument.
+    fn register_foreign_container(&mut self, container: Container, path: &str) -> CompilerResult<()> {
+        let canonical_name = String::from(path) + &container.name;
+        let mut cont_def = ContainerDef::new(container.name.clone(), canonical_name.clone());
+        cont_def.is_native = true;
+        self.get_current_module_mut()?
+            .add_container(cont_def)?;
+
+        let cont_path = canonical_name + "::";
+        let this_type = Type::Reference(Box::new(Type::Other(container.name.clone())));
+        for (_, member) in container.members {
+            let (fn_def, var_name) = match member {
+                ContainerMember::Function(mut function) => {
+                    function.arg_types.insert(0, this_type.clone());
+                    (self.register_foreign_function(function, &cont_path)?, None)
+                },
+                ContainerMember::StaticFunction(function) => {
+                    (self.register_foreign_function(function, &cont_path)?, None)
+                },
+                ContainerMember::Variable { name, mut accessor_fn } => {
+                    accessor_fn.arg_types.insert(0, this_type.clone());
+                    (self.register_foreign_function(accessor_fn, &cont_path)?, Some(name))
+                }
+            };
+            let cont_def = self.get_current_module_mut()?
+                .get_container_mut(&container.name)?;
+            if let Some(var_name) = var_name {
+                cont_def.member_accessors.insert(var_name, fn_def.name.clone());
+            }
+            cont_def.add_member_function(fn_def)?;
+        }
+
+        Ok(())
+    }
+
+    /// Registers a foreign function and returns its definition, for the module or container it belongs to
+    fn register_foreign_function(&mut self, mut function: Function, path: &String) -> CompilerResult<FunctionDef> {
         if self.foreign_functions.is_none() {
             self.foreign_functions = Some(HashMap::new());
         }
@@ -566,7 +854,7 @@ impl Compiler {
         let mut arg_offsets = Vec::new();
         arg_sizes.resize(function.arg_types.len(), 0);
         arg_offsets.resize(function.arg_types.len(), 0);
-        let mut i = arg_sizes.len() - 1;
+        let mut i = arg_sizes.len().saturating_sub(1);
         for arg_type in function_clone.arg_types.iter().rev() {
             let arg_size = self.get_size_of_type(&arg_type)?;
             arg_sizes[i] = arg_size;
@@ -581,6 +869,13 @@ impl Compiler {
         function.set_arg_offsets(arg_offsets);
         function.set_arg_sizes(arg_sizes);
 
+        // The module isn't part of the root yet, so sizes have to be known before types are canonized
+        for arg_type in function.arg_types.iter_mut() {
+            self.canonize_type(arg_type)?;
+        }
+        self.canonize_type(&mut function.return_type)?;
+        let function_clone = function.clone();
+
         self.fn_uid_map.insert(full_fn_name, fn_uid);
         self.foreign_function_uids.insert(fn_uid);
         self.foreign_functions.as_mut()
@@ -593,10 +888,7 @@ impl Compiler {
             .with_ret_type(function_clone.return_type)
             .with_uid(fn_uid);
 
-        let front_mod_ctx = self.get_current_module_mut()?;
-        front_mod_ctx.add_function(fn_def)?;
-
-        Ok(())
+        Ok(fn_def)
     }
 
     /// Canonizes (adds module path when necessary) a given Type
@@ -628,16 +920,20 @@ impl Compiler {
     // #region declare functions
 
     /// (Pre-)declares a given declaration list
-    pub fn declare_decl_list(&mut self, decl_list: &[Declaration]) -> CompilerResult<()> {
+    /// Errors are collected per declaration, so every declaration gets declared.
+    pub fn declare_decl_list(&mut self, decl_list: &[Spanned<Declaration>]) -> CompilerResult<()> {
+        let mut errors = Vec::new();
         for decl in decl_list.iter() {
-            self.declare_decl(decl)?;
+            if let Err(err) = self.declare_decl(decl) {
+                errors.append(&mut err.at(decl.span.clone()).into_errors());
+            }
         }
-        Ok(())
+        CompilerError::from_errors(errors)
     }
 
     /// (Pre-)declares a given declaration
-    pub fn declare_decl(&mut self, decl: &Declaration) -> CompilerResult<()> {
-        match decl {
+    pub fn declare_decl(&mut self, decl: &Spanned<Declaration>) -> CompilerResult<()> {
+        match &decl.node {
             Declaration::Module(_, _) => self.declare_mod_decl(decl)?,
             Declaration::Function(_) => self.declare_fn_decl(decl)?,
             Declaration::Container(_) => self.declare_cont_decl(decl)?,
@@ -648,14 +944,100 @@ impl Compiler {
         Ok(())
     }
 
-    /// (Pre-)declares a given static var declaration
+    /// (Pre-)declares a given static var declaration.
+    /// The initial value is written into the data segment.
     pub fn declare_static_var(&mut self, decl: &Declaration) -> CompilerResult<()> {
+        let var_decl_args = match decl {
+            Declaration::StaticVar(var_decl_args) => var_decl_args,
+            _ => return Err(CompilerError::Unknown)
+        };
+
+        let mut assignment_type = self.check_expr_type(&var_decl_args.assignment)?;
+        self.canonize_type(&mut assignment_type)?;
+
+        let mut var_type = var_decl_args.var_type.clone();
+        if var_type == Type::Auto {
+            var_type = assignment_type.clone();
+        }
+        self.canonize_type(&mut var_type)?;
+
+        if var_type != assignment_type {
+            return Err(CompilerError::TypeMismatch(var_type, assignment_type));
+        }
+
+        let bytes = self.get_static_bytes(&var_decl_args.name, &var_decl_args.assignment)?;
+        let address = self.data.add_static(&bytes);
+
+        let static_def = StaticVarDef::new(var_decl_args.name.clone(), var_type, address);
+        let mod_ctx = self.get_current_module_mut()?;
+        mod_ctx.add_static_var(static_def)?;
+
         Ok(())
     }
 
+    /// Declares a zero initialized static variable in the current module, replacing one of the same name.
+    /// Used to declare variables whose value is only known at runtime, e.g. in an interactive session.
+    pub fn redeclare_static_var(&mut self, name: &String, var_type: &Type) -> CompilerResult<StaticVarDef> {
+        let mut var_type = var_type.clone();
+        self.canonize_type(&mut var_type)?;
+        let size = self.get_size_of_type(&var_type)?;
+        let address = self.data.add_static(&vec![0; size]);
+
+        let static_def = StaticVarDef::new(name.clone(), var_type, address);
+        let mod_ctx = self.get_current_module_mut()?;
+        mod_ctx.static_vars.insert(name.clone(), static_def.clone());
+        Ok(static_def)
+    }
+
+    /// Evaluates the initial value of a static variable at compile time.
+    /// Only literals, and arrays or containers of literals are supported.
+    pub fn get_static_bytes(&mut self, var_name: &String, expr: &Expression) -> CompilerResult<Vec<u8>> {
+        let bytes = match expr {
+            Expression::IntLiteral(int) => int.to_le_bytes().to_vec(),
+            Expression::FloatLiteral(float) => float.to_le_bytes().to_vec(),
+            Expression::BoolLiteral(boolean) => vec![*boolean as u8],
+            Expression::StringLiteral(string) => {
+                let string = String::from(&string[1..string.len() - 1]);
+                let (string_size, string_addr) = self.data.get_string_slice(&string);
+                let mut bytes = string_size.to_le_bytes().to_vec();
+                bytes.extend_from_slice(&string_addr.to_le_bytes());
+                bytes
+            },
+            Expression::ArrayLiteral(elements) => {
+                let mut bytes = Vec::new();
+                for element in elements.iter() {
+                    bytes.append(&mut self.get_static_bytes(var_name, element)?);
+                }
+                bytes
+            },
+            Expression::ContainerInstance(cont_name, member_map) => {
+                let cont_def = self.resolve_container(cont_name)?;
+                // Members are laid out in declaration order
+                let mut members: Vec<(&String, &usize)> = cont_def.member_indices.iter().collect();
+                members.sort_by_key(|(_, index)| **index);
+                let mut bytes = Vec::new();
+                for (member_name, _) in members {
+                    let member_expr = member_map.get(member_name)
+                        .ok_or(CompilerError::UnknownMember(member_name.clone()))?;
+                    let mut member_type = cont_def.get_member_type(member_name)?;
+                    let mut member_expr_type = self.check_expr_type(member_expr)?;
+                    self.canonize_type(&mut member_type)?;
+                    self.canonize_type(&mut member_expr_type)?;
+                    if member_type != member_expr_type {
+                        return Err(CompilerError::TypeMismatch(member_type, member_expr_type));
+                    }
+                    bytes.append(&mut self.get_static_bytes(var_name, member_expr)?);
+                }
+                bytes
+            },
+            _ => return Err(CompilerError::NonConstantStatic(var_name.clone()))
+        };
+        Ok(bytes)
+    }
+
     /// (Pre-)declares a given function declaration
-    pub fn declare_fn_decl(&mut self, decl: &Declaration) -> CompilerResult<()> {
-        let fn_decl_args = match decl {
+    pub fn declare_fn_decl(&mut self, decl: &Spanned<Declaration>) -> CompilerResult<()> {
+        let fn_decl_args = match &decl.node {
             Declaration::Function(fn_decl_args) => fn_decl_args,
             _ => return Err(CompilerError::Unknown)
         };
@@ -669,6 +1051,7 @@ impl Compiler {
 
         let uid = self.uid_generator.get_function_uid(&full_fn_name);
         self.fn_uid_map.insert(full_fn_name.clone(), uid.clone());
+        self.fn_decl_spans.insert(full_fn_name.clone(), decl.span.clone());
 
         let mut fn_def = FunctionDef::from(fn_decl_args)
             .with_uid(uid);
@@ -689,6 +1072,18 @@ impl Compiler {
         Ok(())
     }
 
+    /// Removes a function of the root module, e.g. one that was compiled to run only once.
+    /// Its code stays in the builder but is no longer part of the program.
+    pub fn remove_root_function(&mut self, name: &str) -> CompilerResult<()> {
+        let full_fn_name = format!("root::{}", name);
+        self.fn_uid_map.remove(&full_fn_name);
+        self.fn_decl_spans.remove(&full_fn_name);
+        self.get_root_module_mut()?
+            .functions.remove(name)
+            .map(|_| ())
+            .ok_or(CompilerError::UnknownFunction(full_fn_name))
+    }
+
     /// (Pre-)declares a given module declaration
     pub fn declare_mod_decl(&mut self, decl: &Declaration) -> CompilerResult<()> {
         let (mod_name, decl_list) = match decl {
@@ -700,7 +1095,7 @@ impl Compiler {
 
         self.push_module_context(mod_ctx);
 
-        self.declare_decl_list(decl_list)?;
+        let decl_list_res = self.declare_decl_list(decl_list);
 
         mod_ctx = self.pop_module_context()?;
 
@@ -712,7 +1107,7 @@ impl Compiler {
 
         front_mod_ctx.add_module(mod_ctx)?;
 
-        Ok(())
+        decl_list_res
     }
 
     /// (Pre-)declares a given container declaration
@@ -774,13 +1169,12 @@ impl Compiler {
                 mod_ctx.add_container(cont_def)?;
             }
             self.current_cont = Some(impl_type.clone());
-            self.declare_decl_list(decl_list)?;
+            let decl_list_res = self.declare_decl_list(decl_list);
             self.current_cont = None;
+            decl_list_res
         } else {
-            return Err(CompilerError::Unimplemented(format!("Cannot currently compile non-cont impls!")));
+            Err(CompilerError::Unimplemented(format!("Cannot currently compile non-cont impls!")))
         }
-
-        Ok(())
     }
 
     // #endregion
@@ -788,18 +1182,31 @@ impl Compiler {
     // #region compile functions
 
     /// Compiles the decl list for the root module
-    pub fn compile_root(&mut self, decl_list: &[Declaration]) -> CompilerResult<()> {
+    pub fn compile_root(&mut self, decl_list: &[Spanned<Declaration>]) -> CompilerResult<()> {
         self.declare_decl_list(decl_list)?;
         self.compile_decl_list(decl_list)?;
         Ok(())
     }
 
-    /// Compiles a declaration list
-    pub fn compile_decl_list(&mut self, decl_list: &[Declaration]) -> CompilerResult<()> {
+    /// Compiles a declaration list.
+    /// Errors are collected per declaration, so one broken function doesn't hide the errors of the others.
+    pub fn compile_decl_list(&mut self, decl_list: &[Spanned<Declaration>]) -> CompilerResult<()> {
+        let mut errors = Vec::new();
         for decl in decl_list.iter() {
-            self.compile_decl(decl)?;
+            let fn_ctx_count = self.fn_context_stack.len();
+            let loop_ctx_count = self.loop_ctx_stack.len();
+            if let Err(err) = self.compile_decl(decl) {
+                errors.append(&mut err.at(decl.span.clone()).into_errors());
+                // Drop the contexts the failed declaration left behind
+                while self.fn_context_stack.len() > fn_ctx_count {
+                    self.fn_context_stack.pop_front();
+                }
+                while self.loop_ctx_stack.len() > loop_ctx_count {
+                    self.loop_ctx_stack.pop_front();
+                }
+            }
         }
-        Ok(())
+        CompilerError::from_errors(errors)
     }
 
     /// Compiles a declaration
@@ -876,17 +1283,24 @@ impl Compiler {
     /// Compiles the proper SUBU_I instruction for a break statement
     pub fn compile_stack_loop(&mut self) -> CompilerResult<()> {
         let mut pop_size = 0;
+        let mut try_count = 0;
 
         // Pop all values until the first loop context is hit
         for i in 0..self.fn_context_stack.len() {
             let fn_ctx = self.fn_context_stack.get(i)
                 .ok_or(CompilerError::Unknown)?;
             pop_size += fn_ctx.stack_size;
+            if fn_ctx.is_try {
+                try_count += 1;
+            }
             if fn_ctx.is_loop {
                 break;
             }
         }
 
+        // Jumping out of try statements ends them
+        self.compile_end_try(try_count);
+
         //println!("Compiling loop stack cleanup with pop size {}", pop_size);
 
         let stack_instr = Instruction::new_dec_stack(pop_size);
@@ -911,13 +1325,25 @@ impl Compiler {
         Ok(())
     }
 
+    /// Compiles the end of the given amount of try statements
+    pub fn compile_end_try(&mut self, try_count: usize) {
+        for _ in 0..try_count {
+            let end_try_instr = Instruction::new(Opcode::ENDTRY);
+            self.builder.push_instr(end_try_instr);
+        }
+    }
+
     /// Compiles a full stack unwind until the parent function is hit 
     pub fn compile_stack_cleanup_return(&mut self) -> CompilerResult<()> {
         let mut parent_fn_ctx_opt = None;
         let mut stack_size = 0;
+        let mut try_count = 0;
 
         for ctx in self.fn_context_stack.iter() {
             stack_size += ctx.stack_size;
+            if ctx.is_try {
+                try_count += 1;
+            }
             if !ctx.weak {
                 parent_fn_ctx_opt = Some(ctx);
                 break;
@@ -926,6 +1352,9 @@ impl Compiler {
 
         let parent_fn_ctx = parent_fn_ctx_opt.ok_or(CompilerError::Unknown)?;
         let ret_type = parent_fn_ctx.get_ret_type()?;
+
+        // Returning out of try statements ends them
+        self.compile_end_try(try_count);
         let ret_size = self.get_size_of_type(&ret_type)?;
         let mut pop_size = stack_size;
         let stack_begin_offset = -(stack_size as i16);
@@ -946,8 +1375,9 @@ impl Compiler {
 
         if pop_size > 0 {
             //println!("Popping {} off the stack at return.", pop_size);
+            // Only the returning path pops the stack, so the stack size
+            // of the enclosing blocks is left untouched
             let pop_stack_instr = Instruction::new_dec_stack(pop_size);
-            self.dec_stack(pop_size)?;
             self.builder.push_instr(pop_stack_instr);
         }
 
@@ -974,11 +1404,11 @@ impl Compiler {
 
         self.push_module_context(mod_ctx);
 
-        self.compile_decl_list(decl_list)?;
+        let decl_list_res = self.compile_decl_list(decl_list);
 
         self.pop_module_context()?;
 
-        Ok(())
+        decl_list_res
     }
 
     /// Compiles an impl declaration
@@ -992,20 +1422,20 @@ impl Compiler {
 
         if impl_type == impl_for {
             self.current_cont = Some(impl_type.clone());
-            self.compile_decl_list(decl_list)?;
+            let decl_list_res = self.compile_decl_list(decl_list);
             self.current_cont = None;
+            decl_list_res
         } else {
-            return Err(CompilerError::Unimplemented(format!("impl of interfaces not supported yet!")));
+            Err(CompilerError::Unimplemented(format!("impl of interfaces not supported yet!")))
         }
-
-        Ok(())
     }
 
     /// Compiles a statement list
-    pub fn compile_stmt_list(&mut self, stmt_list: &[Statement]) -> CompilerResult<()> {
+    pub fn compile_stmt_list(&mut self, stmt_list: &[Spanned<Statement>]) -> CompilerResult<()> {
         for stmt in stmt_list.iter() {
             //println!("Compiling statement... Stack size: {}", self.get_stack_size()?);
-            self.compile_stmt(stmt)?;
+            self.compile_stmt(stmt)
+                .map_err(|err| err.at(stmt.span.clone()))?;
             //println!("Compiled statement... Stack size: {}", self.get_stack_size()?);
         }
         Ok(())
@@ -1019,8 +1449,13 @@ impl Compiler {
             Statement::Return(_) => self.compile_return_stmt(stmt)?,
             Statement::If(_) => self.compile_if_stmt(stmt)?,
             Statement::While(_, _) => self.compile_while_stmt(stmt)?, 
+            Statement::Loop(_) => self.compile_loop_stmt(stmt)?,
+            Statement::For(_) => self.compile_for_stmt(stmt)?,
             Statement::Continue => self.compile_continue_stmt(stmt)?,
             Statement::Break => self.compile_break_stmt(stmt)?,
+            Statement::Free(_) => self.compile_free_stmt(stmt)?,
+            Statement::Try(_) => self.compile_try_stmt(stmt)?,
+            Statement::Throw(_) => self.compile_throw_stmt(stmt)?,
             _ => return Err(CompilerError::Unimplemented(format!("Compilation of {:?} not implemented!", stmt)))
         };
         Ok(())
@@ -1114,14 +1549,15 @@ impl Compiler {
             _ => return Err(CompilerError::Unknown)
         };
 
-        match stmt_expr {
+        match &stmt_expr.node {
             Expression::Call(_, _) => self.compile_expr(stmt_expr)?,
+            Expression::MemberAccess(_, _) if stmt_expr.is_member_call() => self.compile_expr(stmt_expr)?,
             Expression::Assign(_, _) => self.compile_var_assign_stmt_expr(stmt_expr)?,
             Expression::AddAssign(_, _) => self.compile_var_assign_stmt_expr(stmt_expr)?,
             Expression::SubAssign(_, _) => self.compile_var_assign_stmt_expr(stmt_expr)?,
             Expression::MulAssign(_, _) => self.compile_var_assign_stmt_expr(stmt_expr)?,
             Expression::DivAssign(_, _) => self.compile_var_assign_stmt_expr(stmt_expr)?,
-            _ => return Err(CompilerError::UnsupportedExpression(stmt_expr.clone()))
+            _ => return Err(CompilerError::UnsupportedExpression(stmt_expr.node.clone()))
         };
 
         Ok(())
@@ -1394,6 +1830,193 @@ impl Compiler {
         Ok(())
     }
 
+    /// Compiles a loop statement
+    pub fn compile_loop_stmt(&mut self, stmt: &Statement) -> CompilerResult<()> {
+        let loop_stmt_list = match stmt {
+            Statement::Loop(loop_stmt_list) => loop_stmt_list,
+            _ => return Err(CompilerError::Unknown)
+        };
+
+        let loop_fn_ctx = FunctionContext::new_loop(self.get_current_function()?)?;
+        self.push_function_context(loop_fn_ctx);
+        let loop_start_pos = self.builder.get_current_offset();
+        let tag_end = self.uid_generator.generate();
+        let mut loop_ctx = LoopContext::new(loop_start_pos, tag_end);
+        self.push_loop_context(loop_ctx);
+
+        // Compile the statement list
+        self.compile_stmt_list(loop_stmt_list)?;
+
+        // Jump back to the start of the loop
+        self.compile_continue_stmt(&Statement::Continue)?;
+
+        // This is the end of this loop
+        let loop_end_pos = self.builder.get_current_offset();
+
+        // Pop the loop off the stack
+        loop_ctx = self.pop_loop_context()?;
+
+        // A loop without a break statement never gets tagged
+        if let Some(instr_pos_list) = self.builder.get_tag(&loop_ctx.tag_end) {
+            // Update all break jumps with the correct end position
+            for instr_pos in instr_pos_list {
+                let jmp_instr = self.builder.get_instr(&instr_pos)
+                    .ok_or(CompilerError::Unknown)?;
+                jmp_instr.remove_operand_bytes(8);
+                jmp_instr.append_operand::<u64>(loop_end_pos as u64);
+            }
+        }
+
+        // Pop this loops fn context off the stack
+        self.pop_function_context()?;
+
+        Ok(())
+    }
+
+    /// Compiles a for statement
+    pub fn compile_for_stmt(&mut self, stmt: &Statement) -> CompilerResult<()> {
+        let for_stmt_args: &ForStatementArgs = match stmt {
+            Statement::For(for_stmt_args) => for_stmt_args,
+            _ => return Err(CompilerError::Unknown)
+        };
+
+        // Only integer ranges are allowed
+        let start_type = self.check_expr_type(&for_stmt_args.range_start)?;
+        if start_type != Type::Int {
+            return Err(CompilerError::TypeMismatch(Type::Int, start_type));
+        }
+        let end_type = self.check_expr_type(&for_stmt_args.range_end)?;
+        if end_type != Type::Int {
+            return Err(CompilerError::TypeMismatch(Type::Int, end_type));
+        }
+
+        // Weak context holding the counter and the end of the range
+        let mut for_fn_ctx = FunctionContext::new_weak(self.get_current_function()?)?;
+        self.push_function_context(for_fn_ctx);
+
+        let var_name = for_stmt_args.var_name.clone();
+        // "#" can never be part of an identifier, so this can't clash with user variables
+        let end_var_name = format!("{}#end", var_name);
+
+        let counter_decl = Statement::VariableDecl(VariableDeclArgs {
+            var_type: Type::Int,
+            name: var_name.clone(),
+            assignment: Box::new(for_stmt_args.range_start.clone())
+        });
+        self.compile_var_decl_stmt(&counter_decl)?;
+
+        let end_decl = Statement::VariableDecl(VariableDeclArgs {
+            var_type: Type::Int,
+            name: end_var_name.clone(),
+            assignment: Box::new(for_stmt_args.range_end.clone())
+        });
+        self.compile_var_decl_stmt(&end_decl)?;
+
+        // The first iteration skips the increment
+        let tag_check = self.uid_generator.generate();
+        self.builder.tag(tag_check);
+        let jmp_check_instr = Instruction::new(Opcode::JMP)
+            .with_operand::<u64>(tag_check);
+        self.builder.push_instr(jmp_check_instr);
+
+        let loop_fn_ctx = FunctionContext::new_loop(self.get_current_function()?)?;
+        self.push_function_context(loop_fn_ctx);
+        // Continue statements jump to the increment
+        let for_inc_pos = self.builder.get_current_offset();
+        let tag_end = self.uid_generator.generate();
+        let mut for_loop_ctx = LoopContext::new(for_inc_pos, tag_end);
+        self.push_loop_context(for_loop_ctx);
+
+        // Increment the counter
+        let counter_offset = self.get_sp_offset_of_var(&var_name)?;
+        let counter_reg = {
+            let fn_ctx = self.get_current_function_mut()?;
+            fn_ctx.register_allocator.get_temp_register()?
+        };
+        let movi_ar_instr = Instruction::new(Opcode::MOVI_AR)
+            .with_operand::<u8>(Register::SP.into())
+            .with_operand::<i16>(counter_offset as i16)
+            .with_operand::<u8>(counter_reg.clone().into());
+        let addi_instr = Instruction::new(Opcode::ADDI_I)
+            .with_operand::<u8>(counter_reg.clone().into())
+            .with_operand::<i64>(1)
+            .with_operand::<u8>(counter_reg.clone().into());
+        let movi_ra_instr = Instruction::new(Opcode::MOVI_RA)
+            .with_operand::<u8>(counter_reg.into())
+            .with_operand::<u8>(Register::SP.into())
+            .with_operand::<i16>(counter_offset as i16);
+        self.builder.push_instr(movi_ar_instr);
+        self.builder.push_instr(addi_instr);
+        self.builder.push_instr(movi_ra_instr);
+
+        // This is where the range check starts
+        let for_check_pos = self.builder.get_current_offset();
+        {
+            let jmp_check_pos_list = self.builder.get_tag(&tag_check)
+                .ok_or(CompilerError::Unknown)?;
+            let jmp_check_pos = jmp_check_pos_list.get(0)
+                .ok_or(CompilerError::Unknown)?;
+            let jmp_check_instr = self.builder.get_instr(jmp_check_pos)
+                .ok_or(CompilerError::Unknown)?;
+            jmp_check_instr.remove_operand_bytes(8);
+            jmp_check_instr.append_operand::<u64>(for_check_pos as u64);
+        }
+
+        // The generated check points to the range
+        let range_span = for_stmt_args.range_start.span.start..for_stmt_args.range_end.span.end;
+        let counter_expr = Box::new(Spanned::new(Expression::Variable(var_name.clone()), range_span.clone()));
+        let end_expr = Box::new(Spanned::new(Expression::Variable(end_var_name), range_span.clone()));
+        let check_expr = if for_stmt_args.inclusive {
+            Expression::LessThanEquals(counter_expr, end_expr)
+        } else {
+            Expression::LessThan(counter_expr, end_expr)
+        };
+        self.compile_expr(&Spanned::new(check_expr, range_span))?;
+
+        let last_reg = {
+            self.get_current_function()?
+                .register_allocator
+                .get_last_temp_register()?
+        };
+
+        self.builder.tag(tag_end);
+        let jmpf_instr = Instruction::new(Opcode::JMPF)
+            .with_operand::<u8>(last_reg.into())
+            .with_operand(tag_end);
+        self.builder.push_instr(jmpf_instr);
+
+        // Compile the statement list
+        self.compile_stmt_list(&for_stmt_args.stmt_list)?;
+
+        // Compile a continue statement
+        self.compile_continue_stmt(&Statement::Continue)?;
+
+        // This is the end of this for loop
+        let for_end_pos = self.builder.get_current_offset();
+
+        // Pop the for loop off the stack
+        for_loop_ctx = self.pop_loop_context()?;
+        let instr_pos_list = self.builder.get_tag(&for_loop_ctx.tag_end)
+            .ok_or(CompilerError::Unknown)?;
+
+        // Update with correct end position
+        for instr_pos in instr_pos_list {
+            let jmp_instr = self.builder.get_instr(&instr_pos)
+                .ok_or(CompilerError::Unknown)?;
+            jmp_instr.remove_operand_bytes(8);
+            jmp_instr.append_operand::<u64>(for_end_pos as u64);
+        }
+
+        // Pop this for loops fn context off the stack
+        self.pop_function_context()?;
+
+        // Pop the counter and the range end off the stack
+        for_fn_ctx = self.pop_function_context()?;
+        self.compile_stack_cleanup_block(&for_fn_ctx)?;
+
+        Ok(())
+    }
+
     /// Compiles a break statement
     pub fn compile_break_stmt(&mut self, stmt: &Statement) -> CompilerResult<()> {
         if *stmt != Statement::Break {
@@ -1440,37 +2063,170 @@ impl Compiler {
         Ok(())
     }
 
-    /// Compiles a return statement
-    pub fn compile_return_stmt(&mut self, stmt: &Statement) -> CompilerResult<()> {
-        let return_expr_opt = match stmt {
-            Statement::Return(ret_expr) => ret_expr,
+    /// Compiles a try statement.
+    /// Errors raised after TRY unwind the stack back to it, push their message and jump to the catch block.
+    pub fn compile_try_stmt(&mut self, stmt: &Statement) -> CompilerResult<()> {
+        let try_stmt_args: &TryStatementArgs = match stmt {
+            Statement::Try(try_stmt_args) => try_stmt_args,
             _ => return Err(CompilerError::Unknown)
         };
 
-        let mut return_expr_type = Type::Void;
+        let tag_catch = self.uid_generator.generate();
+        let tag_end = self.uid_generator.generate();
 
-        if return_expr_opt.is_some() {
-            let return_expr_ref = return_expr_opt.as_ref().unwrap();
-            return_expr_type = self.check_expr_type(return_expr_ref)?;
-        }
+        // Register the catch block, its position is filled in below
+        self.builder.tag(tag_catch);
+        let try_instr = Instruction::new(Opcode::TRY)
+            .with_operand::<u64>(tag_catch);
+        self.builder.push_instr(try_instr);
 
-        let fn_ret_type = {
-            let fn_ctx = self.get_parent_function()?;
-            fn_ctx.get_ret_type()?
-        };
+        let mut try_fn_ctx = FunctionContext::new_try(self.get_current_function()?)?;
+        self.push_function_context(try_fn_ctx);
 
-        if fn_ret_type != return_expr_type {
-            return Err(CompilerError::TypeMismatch(fn_ret_type, return_expr_type));
-        }
+        self.compile_stmt_list(&try_stmt_args.try_block)?;
 
-        if return_expr_opt.is_some() {
-            let return_expr = return_expr_opt.as_ref().unwrap();
-            let ret_expr_type = self.check_expr_type(return_expr)?;
-            //println!("Ret expr type: {:?}", ret_expr_type);
-            //println!("Ret expr: {:?}", return_expr);
-            self.compile_expr(return_expr)?;
+        try_fn_ctx = self.pop_function_context()?;
+        self.compile_stack_cleanup_block(&try_fn_ctx)?;
 
-            // Move to R0 register if type is primitive
+        // No error was raised, skip the catch block
+        self.compile_end_try(1);
+        self.builder.tag(tag_end);
+        let jmp_end_instr = Instruction::new(Opcode::JMP)
+            .with_operand::<u64>(tag_end);
+        self.builder.push_instr(jmp_end_instr);
+
+        let catch_pos = self.builder.get_current_offset();
+        {
+            let try_instr_pos_list = self.builder.get_tag(&tag_catch)
+                .ok_or(CompilerError::Unknown)?;
+            let try_instr_pos = try_instr_pos_list.first()
+                .ok_or(CompilerError::Unknown)?;
+            let try_instr = self.builder.get_instr(try_instr_pos)
+                .ok_or(CompilerError::Unknown)?;
+            try_instr.remove_operand_bytes(8);
+            try_instr.append_operand::<u64>(catch_pos as u64);
+        }
+
+        let mut catch_fn_ctx = FunctionContext::new_weak(self.get_current_function()?)?;
+        self.push_function_context(catch_fn_ctx);
+
+        // The message of the caught error is on top of the stack
+        let error_size = self.get_size_of_type(&Type::String)?;
+        self.inc_stack(error_size)?;
+        if let Some(var_name) = &try_stmt_args.catch_var {
+            let fn_ctx = self.get_current_function_mut()?;
+            fn_ctx.set_stack_var((var_name.clone(), Type::String), (fn_ctx.stack_size - error_size) as i64)?;
+        }
+
+        self.compile_stmt_list(&try_stmt_args.catch_block)?;
+
+        catch_fn_ctx = self.pop_function_context()?;
+        self.compile_stack_cleanup_block(&catch_fn_ctx)?;
+
+        let end_pos = self.builder.get_current_offset();
+        {
+            let jmp_end_pos_list = self.builder.get_tag(&tag_end)
+                .ok_or(CompilerError::Unknown)?;
+            let jmp_end_pos = jmp_end_pos_list.first()
+                .ok_or(CompilerError::Unknown)?;
+            let jmp_end_instr = self.builder.get_instr(jmp_end_pos)
+                .ok_or(CompilerError::Unknown)?;
+            jmp_end_instr.remove_operand_bytes(8);
+            jmp_end_instr.append_operand::<u64>(end_pos as u64);
+        }
+
+        Ok(())
+    }
+
+    /// Compiles a throw statement, the message is raised as an error
+    pub fn compile_throw_stmt(&mut self, stmt: &Statement) -> CompilerResult<()> {
+        let throw_expr = match stmt {
+            Statement::Throw(throw_expr) => throw_expr,
+            _ => return Err(CompilerError::Unknown)
+        };
+
+        let expr_type = self.check_expr_type(throw_expr)?;
+        if expr_type != Type::String {
+            return Err(CompilerError::TypeMismatch(Type::String, expr_type));
+        }
+
+        let stack_size = self.get_stack_size()?;
+
+        // The message ends up on top of the stack
+        self.compile_expr(throw_expr)?;
+
+        let throw_instr = Instruction::new(Opcode::THROW);
+        self.builder.push_instr(throw_instr);
+
+        // Execution doesn't continue after THROW, the stack size only has to stay consistent
+        let pop_size = self.get_stack_size()? - stack_size;
+        if pop_size > 0 {
+            let stack_dec_instr = Instruction::new_dec_stack(pop_size);
+            self.dec_stack(pop_size)?;
+            self.builder.push_instr(stack_dec_instr);
+        }
+
+        Ok(())
+    }
+
+    /// Compiles a free statement
+    pub fn compile_free_stmt(&mut self, stmt: &Statement) -> CompilerResult<()> {
+        let free_expr = match stmt {
+            Statement::Free(free_expr) => free_expr,
+            _ => return Err(CompilerError::Unknown)
+        };
+
+        let free_type = self.check_expr_type(free_expr)?;
+        match free_type {
+            Type::Reference(_) => {},
+            _ => return Err(CompilerError::CannotFreeNonPointer(free_type))
+        };
+
+        self.compile_expr(free_expr)?;
+        let ptr_reg = self.get_last_register()?;
+
+        let free_instr = Instruction::new(Opcode::FREE)
+            .with_operand::<u8>(ptr_reg.into());
+        self.builder.push_instr(free_instr);
+        Ok(())
+    }
+
+    /// Compiles a return statement
+    pub fn compile_return_stmt(&mut self, stmt: &Statement) -> CompilerResult<()> {
+        let return_expr_opt = match stmt {
+            Statement::Return(ret_expr) => ret_expr,
+            _ => return Err(CompilerError::Unknown)
+        };
+
+        let mut return_expr_type = Type::Void;
+
+        if return_expr_opt.is_some() {
+            let return_expr_ref = return_expr_opt.as_ref().unwrap();
+            return_expr_type = self.check_expr_type(return_expr_ref)?;
+        }
+
+        let fn_ret_type = {
+            let fn_ctx = self.get_parent_function()?;
+            fn_ctx.get_ret_type()?
+        };
+
+        // Container names may be qualified differently, compare canonical types
+        let mut fn_ret_canon_type = fn_ret_type.clone();
+        let mut return_expr_canon_type = return_expr_type.clone();
+        self.canonize_type(&mut fn_ret_canon_type)?;
+        self.canonize_type(&mut return_expr_canon_type)?;
+        if fn_ret_canon_type != return_expr_canon_type {
+            return Err(CompilerError::TypeMismatch(fn_ret_type, return_expr_type));
+        }
+
+        if return_expr_opt.is_some() {
+            let return_expr = return_expr_opt.as_ref().unwrap();
+            let ret_expr_type = self.check_expr_type(return_expr)?;
+            //println!("Ret expr type: {:?}", ret_expr_type);
+            //println!("Ret expr: {:?}", return_expr);
+            self.compile_expr(return_expr)?;
+
+            // Move to R0 register if type is primitive
             if ret_expr_type.is_primitive() {
                 match fn_ret_type {
                     Type::Int => {
@@ -1533,23 +2289,23 @@ impl Compiler {
     }
 
     /// Compiles a variable assign statement expression
-    pub fn compile_var_assign_stmt_expr(&mut self, assign_expr: &Expression) -> CompilerResult<()> {
-        let (lhs_expr, rhs_expr) = match assign_expr {
+    pub fn compile_var_assign_stmt_expr(&mut self, assign_expr: &Spanned<Expression>) -> CompilerResult<()> {
+        let (lhs_expr, rhs_expr) = match &assign_expr.node {
             Expression::Assign(lhs, rhs) => (lhs.deref().clone(), rhs.deref().clone()),
             Expression::AddAssign(lhs, rhs) => {
-                let rhs_expr = Expression::Addition(lhs.clone(), rhs.clone());
+                let rhs_expr = Spanned::new(Expression::Addition(lhs.clone(), rhs.clone()), assign_expr.span.clone());
                 (lhs.deref().clone(), rhs_expr)
             },
             Expression::SubAssign(lhs, rhs) => {
-                let rhs_expr = Expression::Subtraction(lhs.clone(), rhs.clone());
+                let rhs_expr = Spanned::new(Expression::Subtraction(lhs.clone(), rhs.clone()), assign_expr.span.clone());
                 (lhs.deref().clone(), rhs_expr)
             },
             Expression::DivAssign(lhs, rhs) => {
-                let rhs_expr = Expression::Division(lhs.clone(), rhs.clone());
+                let rhs_expr = Spanned::new(Expression::Division(lhs.clone(), rhs.clone()), assign_expr.span.clone());
                 (lhs.deref().clone(), rhs_expr)
             },
             Expression::MulAssign(lhs, rhs) => {
-                let rhs_expr = Expression::Multiplication(lhs.clone(), rhs.clone());
+                let rhs_expr = Spanned::new(Expression::Multiplication(lhs.clone(), rhs.clone()), assign_expr.span.clone());
                 (lhs.deref().clone(), rhs_expr)
             },
             _ => return Err(CompilerError::Unknown)
@@ -1585,8 +2341,12 @@ impl Compiler {
         // Check the type of the rhs expression
         let rhs_expr_type = self.check_expr_type(&rhs_expr)?;
 
-        // Check for type mismatch
-        if lhs_expr_type != rhs_expr_type {
+        // Check for type mismatch, static variables have canonical container names
+        let mut lhs_canon_type = lhs_expr_type.clone();
+        let mut rhs_canon_type = rhs_expr_type.clone();
+        self.canonize_type(&mut lhs_canon_type)?;
+        self.canonize_type(&mut rhs_canon_type)?;
+        if lhs_canon_type != rhs_canon_type {
             return Err(CompilerError::TypeMismatch(lhs_expr_type, rhs_expr_type));
         }
 
@@ -1675,45 +2435,32 @@ impl Compiler {
     pub fn compile_lhs_assign_expr(&mut self, expr: &Expression) -> CompilerResult<Type> {
         let expr_type = match expr {
             Expression::Variable(var_name) => {
-                let stack_offset = self.get_sp_offset_of_var(var_name)?.abs() as u64;
                 let target_reg = {
                     let fn_ctx = self.get_current_function_mut()?;
                     fn_ctx.register_allocator.get_temp_register()?
                 };
                 // Instruction for assign
-                let stack_offset_instr = Instruction::new(Opcode::SUBU_I)
-                    .with_operand::<u8>(Register::SP.into())
-                    .with_operand::<u64>(stack_offset)
-                    .with_operand::<u8>(target_reg.into());
-                self.builder.push_instr(stack_offset_instr);
+                self.compile_var_addr(var_name, target_reg)?;
                 self.get_type_of_var(var_name)?
             },
             Expression::MemberAccess(lhs_expr, rhs_expr) => {
-                let var_name = match lhs_expr.deref() {
+                let var_name = match &lhs_expr.node {
                     Expression::Variable(var_name) => var_name,
-                    _ => return Err(CompilerError::UnsupportedExpression(lhs_expr.deref().clone()))
+                    _ => return Err(CompilerError::UnsupportedExpression(lhs_expr.node.clone()))
                 };
-                let var_offset = self.get_sp_offset_of_var(var_name)?;
                 let var_type = self.get_type_of_var(var_name)?;
-                let lhs_ptr_reg = self.get_next_register()?;
 
                 let cont_def = match var_type {
                     Type::Other(cont_name) => {
-                        let subui_instr = Instruction::new(Opcode::SUBU_I)
-                            .with_operand::<u8>(Register::SP.into())
-                            .with_operand::<u64>(var_offset.abs() as u64)
-                            .with_operand::<u8>(lhs_ptr_reg.into());
-                        self.builder.push_instr(subui_instr);
+                        let lhs_ptr_reg = self.get_next_register()?;
+                        self.compile_var_addr(var_name, lhs_ptr_reg)?;
                         self.resolve_container(&cont_name)?
                     },
                     Type::Reference(inner_type) => {
                         match inner_type.deref() {
                             Type::Other(cont_name) => {
-                                let mova_instr = Instruction::new(Opcode::MOVA_AR)
-                                    .with_operand::<u8>(Register::SP.into())
-                                    .with_operand::<i16>(var_offset as i16)
-                                    .with_operand::<u8>(lhs_ptr_reg.into());
-                                self.builder.push_instr(mova_instr);
+                                let lhs_ptr_reg = self.get_next_register()?;
+                                self.compile_var_ptr(var_name, lhs_ptr_reg)?;
                                 self.resolve_container(cont_name)?
                             },
                             _ => return Err(CompilerError::Unknown)
@@ -1724,6 +2471,19 @@ impl Compiler {
 
                 self.compile_lhs_assign_member_expr(rhs_expr, &cont_def)?
             },
+            Expression::Index(_, _) => {
+                self.compile_index_addr_expr(expr)?
+            },
+            Expression::Deref(op_expr) => {
+                // The pointer itself is the assignment target
+                match self.check_expr_type(op_expr)? {
+                    Type::Reference(inner_type) => {
+                        self.compile_expr(op_expr)?;
+                        inner_type.deref().clone()
+                    },
+                    _ => return Err(CompilerError::CannotDerefNonPointer)
+                }
+            },
             _ => return Err(CompilerError::UnsupportedExpression(expr.clone()))
         };
         Ok(expr_type)
@@ -1747,10 +2507,10 @@ impl Compiler {
             },
             Expression::MemberAccess(lhs_expr, rhs_expr) => {
                 let var_name;
-                if let Expression::Variable(name) = lhs_expr.deref() {
+                if let Expression::Variable(name) = &lhs_expr.node {
                     var_name = name;
                 } else {
-                    return Err(CompilerError::UnsupportedExpression(lhs_expr.deref().clone()));
+                    return Err(CompilerError::UnsupportedExpression(lhs_expr.node.clone()));
                 }
 
                 let member_offset = cont_def.get_member_offset(self, var_name)?;
@@ -1790,13 +2550,18 @@ impl Compiler {
         }
     }
 
-    /// Compiles an expression
-    pub fn compile_expr(&mut self, expr: &Expression) -> CompilerResult<()> {
+    /// Compiles an expression, errors point to the innermost expression they were raised at
+    pub fn compile_expr(&mut self, expr: &Spanned<Expression>) -> CompilerResult<()> {
+        self.compile_expr_node(expr)
+            .map_err(|err| err.at(expr.span.clone()))
+    }
+
+    fn compile_expr_node(&mut self, expr: &Spanned<Expression>) -> CompilerResult<()> {
         let expr_type = self.check_expr_type(expr)?;
         let expr_size = self.get_size_of_type(&expr_type)?;
         //println!("Expr size: {}", expr_size);
         let before_stack_size = self.get_stack_size()?;
-        match expr {
+        match &expr.node {
             Expression::IntLiteral(int) => {
                 let reg = {
                     let fn_ctx = self.get_current_function_mut()?;
@@ -1866,12 +2631,21 @@ impl Compiler {
             Expression::ContainerInstance(_, _) => {
                 self.compile_cont_instance_expr(expr)?;
             },
+            Expression::ArrayLiteral(_) => {
+                self.compile_array_literal_expr(expr)?;
+            },
+            Expression::Index(_, _) => {
+                self.compile_index_expr(expr)?;
+            },
             Expression::Variable(_) => {
                 self.compile_var_expr(expr)?;
             },
             Expression::Ref(op_expr) => {
                 self.compile_lhs_assign_expr(op_expr)?;
             },
+            Expression::New(_) => {
+                self.compile_new_expr(expr)?;
+            },
             Expression::Deref(op_expr) => {
                 let expr_type = self.check_expr_type(op_expr)?;
                 self.compile_expr(op_expr)?;
@@ -1916,6 +2690,9 @@ impl Compiler {
                 }
             },
             Expression::MemberAccess(_, _) => {
+                if let Some(lowered_expr) = self.lower_native_member_access(expr) {
+                    return self.compile_expr(&lowered_expr);
+                }
                 //println!("Stack size before member access: {}", self.get_stack_size()?);
                 let expr_type = self.check_expr_type(expr)?;
                 self.compile_member_access_expr(expr, None)?;
@@ -1941,16 +2718,30 @@ impl Compiler {
                             self.builder.push_instr(movf_instr);
                         },
                         Type::Bool => {
-                        
+                            let movb_instr = Instruction::new(Opcode::MOVB_AR)
+                                .with_operand::<u8>(last_reg.into())
+                                .with_operand::<i16>(0)
+                                .with_operand::<u8>(next_reg.into());
+                            self.builder.push_instr(movb_instr);
                         },
                         Type::Reference(_) => {
-
+                            let mova_instr = Instruction::new(Opcode::MOVA_AR)
+                                .with_operand::<u8>(last_reg.into())
+                                .with_operand::<i16>(0)
+                                .with_operand::<u8>(next_reg.into());
+                            self.builder.push_instr(mova_instr);
                         },
                         _ => {}
                     };
                 }
                 //println!("Stack size after member access: {}", self.get_stack_size()?);
             },
+            Expression::Call(fn_name, _) if fn_name == LEN_INTRINSIC => {
+                self.compile_len_expr(expr)?;
+            },
+            Expression::Slice(_, _, _) => {
+                self.compile_slice_expr(expr)?;
+            },
             Expression::Call(fn_name, _) => {
                 //println!("Stack size before call expr: {}", self.get_stack_size()?);
                 self.compile_call_expr(expr)?;
@@ -2002,7 +2793,10 @@ impl Compiler {
                             .with_operand::<u8>(res_reg.into());
                         self.builder.push_instr(addf_instr);
                     },
-                    _ => return Err(CompilerError::UnsupportedExpression(lhs.deref().clone()))
+                    Type::String => {
+                        self.compile_string_concat_expr()?;
+                    },
+                    _ => return Err(CompilerError::UnsupportedExpression(lhs.node.clone()))
                 };
             },
             Expression::Subtraction(lhs, rhs) => {
@@ -2040,7 +2834,7 @@ impl Compiler {
                             .with_operand::<u8>(res_reg.into());
                         self.builder.push_instr(subf_instr);
                     },
-                    _ => return Err(CompilerError::UnsupportedExpression(lhs.deref().clone()))
+                    _ => return Err(CompilerError::UnsupportedExpression(lhs.node.clone()))
                 };
             },
             Expression::Multiplication(lhs, rhs) => {
@@ -2078,7 +2872,7 @@ impl Compiler {
                             .with_operand::<u8>(res_reg.into());
                         self.builder.push_instr(mulf_instr);
                     },
-                    _ => return Err(CompilerError::UnsupportedExpression(lhs.deref().clone()))
+                    _ => return Err(CompilerError::UnsupportedExpression(lhs.node.clone()))
                 };
             },
             Expression::Division(lhs, rhs) => {
@@ -2116,9 +2910,27 @@ impl Compiler {
                             .with_operand::<u8>(res_reg.into());
                         self.builder.push_instr(divf_instr);
                     },
-                    _ => return Err(CompilerError::UnsupportedExpression(lhs.deref().clone()))
+                    _ => return Err(CompilerError::UnsupportedExpression(lhs.node.clone()))
                 };
             },
+            Expression::Modulo(lhs, rhs) => {
+                self.compile_int_op_expr(lhs, rhs, Opcode::MODI, Opcode::MODI_I)?;
+            },
+            Expression::BitAnd(lhs, rhs) => {
+                self.compile_int_op_expr(lhs, rhs, Opcode::BANDI, Opcode::BANDI_I)?;
+            },
+            Expression::BitOr(lhs, rhs) => {
+                self.compile_int_op_expr(lhs, rhs, Opcode::BORI, Opcode::BORI_I)?;
+            },
+            Expression::BitXor(lhs, rhs) => {
+                self.compile_int_op_expr(lhs, rhs, Opcode::BXORI, Opcode::BXORI_I)?;
+            },
+            Expression::ShiftLeft(lhs, rhs) => {
+                self.compile_int_op_expr(lhs, rhs, Opcode::SHLI, Opcode::SHLI_I)?;
+            },
+            Expression::ShiftRight(lhs, rhs) => {
+                self.compile_int_op_expr(lhs, rhs, Opcode::SHRI, Opcode::SHRI_I)?;
+            },
             Expression::LessThan(lhs, rhs) => {
                 let expr_type = self.check_expr_type(lhs)?;
                 self.compile_expr(lhs)?;
@@ -2154,7 +2966,10 @@ impl Compiler {
                             .with_operand::<u8>(res_reg.into());
                         self.builder.push_instr(ltf_instr);
                     },
-                    _ => return Err(CompilerError::UnsupportedExpression(lhs.deref().clone()))
+                    Type::String => {
+                        self.compile_string_cmp_expr(Opcode::LTI)?;
+                    },
+                    _ => return Err(CompilerError::UnsupportedExpression(lhs.node.clone()))
                 };
             },
 
@@ -2193,7 +3008,10 @@ impl Compiler {
                             .with_operand::<u8>(res_reg.into());
                         self.builder.push_instr(gtf_instr);
                     },
-                    _ => return Err(CompilerError::UnsupportedExpression(lhs.deref().clone()))
+                    Type::String => {
+                        self.compile_string_cmp_expr(Opcode::GTI)?;
+                    },
+                    _ => return Err(CompilerError::UnsupportedExpression(lhs.node.clone()))
                 };
             },
 
@@ -2232,7 +3050,10 @@ impl Compiler {
                             .with_operand::<u8>(res_reg.into());
                         self.builder.push_instr(lteqf_instr);
                     },
-                    _ => return Err(CompilerError::UnsupportedExpression(lhs.deref().clone()))
+                    Type::String => {
+                        self.compile_string_cmp_expr(Opcode::LTEQI)?;
+                    },
+                    _ => return Err(CompilerError::UnsupportedExpression(lhs.node.clone()))
                 };
             },
 
@@ -2271,7 +3092,10 @@ impl Compiler {
                             .with_operand::<u8>(res_reg.into());
                         self.builder.push_instr(gteqf_instr);
                     },
-                    _ => return Err(CompilerError::UnsupportedExpression(lhs.deref().clone()))
+                    Type::String => {
+                        self.compile_string_cmp_expr(Opcode::GTEQI)?;
+                    },
+                    _ => return Err(CompilerError::UnsupportedExpression(lhs.node.clone()))
                 };
             },
 
@@ -2310,7 +3134,10 @@ impl Compiler {
                             .with_operand::<u8>(res_reg.into());
                         self.builder.push_instr(eqf_instr);
                     },
-                    _ => return Err(CompilerError::UnsupportedExpression(lhs.deref().clone()))
+                    Type::String => {
+                        self.compile_string_cmp_expr(Opcode::EQI)?;
+                    },
+                    _ => return Err(CompilerError::UnsupportedExpression(lhs.node.clone()))
                 };
             },
             Expression::NotEquals(lhs, rhs) => {
@@ -2348,7 +3175,10 @@ impl Compiler {
                             .with_operand::<u8>(res_reg.into());
                         self.builder.push_instr(neqf_instr);
                     },
-                    _ => return Err(CompilerError::UnsupportedExpression(lhs.deref().clone()))
+                    Type::String => {
+                        self.compile_string_cmp_expr(Opcode::NEQI)?;
+                    },
+                    _ => return Err(CompilerError::UnsupportedExpression(lhs.node.clone()))
                 };
             },
             Expression::Not(op) => {
@@ -2364,6 +3194,24 @@ impl Compiler {
                     .with_operand::<u8>(target_reg.into());
                 self.builder.push_instr(not_instr);
             },
+            Expression::Negate(op) => {
+                self.compile_expr(op)?;
+                let (op_reg, target_reg) = {
+                    let fn_ctx = self.get_current_function_mut()?;
+                    let op_reg = fn_ctx.register_allocator.get_last_temp_register()?;
+                    let target_reg = fn_ctx.register_allocator.get_temp_register()?;
+                    (op_reg, target_reg)
+                };
+                let neg_opcode = match expr_type {
+                    Type::Int => Opcode::NEGI,
+                    Type::Float => Opcode::NEGF,
+                    _ => return Err(CompilerError::UnsupportedExpression(op.node.clone()))
+                };
+                let neg_instr = Instruction::new(neg_opcode)
+                    .with_operand::<u8>(op_reg.into())
+                    .with_operand::<u8>(target_reg.into());
+                self.builder.push_instr(neg_instr);
+            },
             Expression::And(lhs, rhs) => {
                 self.compile_expr(lhs)?;
                 let lhs_reg = self.get_last_register()?;
@@ -2388,7 +3236,7 @@ impl Compiler {
                     .with_operand::<u8>(target_reg.into());
                 self.builder.push_instr(or_instr);
             },
-            _ => return Err(CompilerError::UnsupportedExpression(expr.clone()))
+            _ => return Err(CompilerError::UnsupportedExpression(expr.node.clone()))
         };
 
         let after_stack_size = self.get_stack_size()?;
@@ -2405,7 +3253,7 @@ impl Compiler {
                     .with_operand::<i16>(-(expr_size as i16))
                     .with_operand::<u8>(Register::SP.into())
                     .with_operand::<i16>(-(stack_diff as i16))
-                    .with_operand::<u32>(16);
+                    .with_operand::<u32>(expr_size as u32);
                 self.builder.push_instr(mov_stack_instr);
             }
         }
@@ -2419,6 +3267,244 @@ impl Compiler {
         //Err(CompilerError::Unimplemented(format!("Expr compilation not implemented!")))
     }
 
+    /// Compiles a new expression, which moves its operand into a fresh heap allocation.
+    /// The last register used contains the heap pointer afterwards.
+    pub fn compile_new_expr(&mut self, expr: &Expression) -> CompilerResult<()> {
+        let op_expr = match expr {
+            Expression::New(op_expr) => op_expr.deref(),
+            _ => return Err(CompilerError::Unknown)
+        };
+
+        let op_type = self.check_expr_type(op_expr)?;
+        let op_size = self.get_size_of_type(&op_type)?;
+
+        self.compile_expr(op_expr)?;
+        let value_reg = self.get_last_register()?;
+        let ptr_reg = self.get_next_register()?;
+
+        let alloc_instr = Instruction::new(Opcode::ALLOC_I)
+            .with_operand::<u64>(op_size as u64)
+            .with_operand::<u8>(ptr_reg.clone().into());
+        self.builder.push_instr(alloc_instr);
+
+        // Primitives live in the last register, everything else on top of the stack
+        let mov_instr = match op_type {
+            Type::Int => {
+                Instruction::new(Opcode::MOVI_RA)
+                    .with_operand::<u8>(value_reg.into())
+                    .with_operand::<u8>(ptr_reg.into())
+                    .with_operand::<i16>(0)
+            },
+            Type::Float => {
+                Instruction::new(Opcode::MOVF_RA)
+                    .with_operand::<u8>(value_reg.into())
+                    .with_operand::<u8>(ptr_reg.into())
+                    .with_operand::<i16>(0)
+            },
+            Type::Bool => {
+                Instruction::new(Opcode::MOVB_RA)
+                    .with_operand::<u8>(value_reg.into())
+                    .with_operand::<u8>(ptr_reg.into())
+                    .with_operand::<i16>(0)
+            },
+            Type::Reference(_) => {
+                Instruction::new(Opcode::MOVA_RA)
+                    .with_operand::<u8>(value_reg.into())
+                    .with_operand::<u8>(ptr_reg.into())
+                    .with_operand::<i16>(0)
+            },
+            _ => {
+                // The temporary copy on the stack is popped by compile_expr()
+                Instruction::new(Opcode::MOVN_A)
+                    .with_operand::<u8>(Register::SP.into())
+                    .with_operand::<i16>(-(op_size as i16))
+                    .with_operand::<u8>(ptr_reg.into())
+                    .with_operand::<i16>(0)
+                    .with_operand::<u32>(op_size as u32)
+            }
+        };
+        self.builder.push_instr(mov_instr);
+        Ok(())
+    }
+
+    /// Compiles an integer only binary operator.
+    /// Int literals on the rhs are encoded as an immediate operand.
+    pub fn compile_int_op_expr(&mut self, lhs: &Spanned<Expression>, rhs: &Spanned<Expression>, opcode: Opcode, imm_opcode: Opcode) -> CompilerResult<()> {
+        self.compile_expr(lhs)?;
+        let lhs_reg = self.get_last_register()?;
+        let op_instr = match &rhs.node {
+            Expression::IntLiteral(int) => {
+                let target_reg = self.get_next_register()?;
+                Instruction::new(imm_opcode)
+                    .with_operand::<u8>(lhs_reg.into())
+                    .with_operand::<i64>(*int)
+                    .with_operand::<u8>(target_reg.into())
+            },
+            _ => {
+                self.compile_expr(rhs)?;
+                let rhs_reg = self.get_last_register()?;
+                let target_reg = self.get_next_register()?;
+                Instruction::new(opcode)
+                    .with_operand::<u8>(lhs_reg.into())
+                    .with_operand::<u8>(rhs_reg.into())
+                    .with_operand::<u8>(target_reg.into())
+            }
+        };
+        self.builder.push_instr(op_instr);
+        Ok(())
+    }
+
+    /// Concatenates the two strings on top of the stack into a new heap string.
+    /// The result replaces both operands on the stack.
+    pub fn compile_string_concat_expr(&mut self) -> CompilerResult<()> {
+        let lhs_ptr_reg = self.get_next_register()?;
+        let rhs_ptr_reg = self.get_next_register()?;
+
+        let lhs_ptr_instr = Instruction::new(Opcode::SUBU_I)
+            .with_operand::<u8>(Register::SP.into())
+            .with_operand::<u64>(32)
+            .with_operand::<u8>(lhs_ptr_reg.clone().into());
+        let rhs_ptr_instr = Instruction::new(Opcode::SUBU_I)
+            .with_operand::<u8>(Register::SP.into())
+            .with_operand::<u64>(16)
+            .with_operand::<u8>(rhs_ptr_reg.clone().into());
+        // The result overwrites the lhs string
+        let sconcat_instr = Instruction::new(Opcode::SCONCAT)
+            .with_operand::<u8>(lhs_ptr_reg.clone().into())
+            .with_operand::<u8>(rhs_ptr_reg.into())
+            .with_operand::<u8>(lhs_ptr_reg.into());
+        let pop_stack_instr = Instruction::new_dec_stack(16);
+        self.dec_stack(16)?;
+
+        self.builder.push_instr(lhs_ptr_instr);
+        self.builder.push_instr(rhs_ptr_instr);
+        self.builder.push_instr(sconcat_instr);
+        self.builder.push_instr(pop_stack_instr);
+        Ok(())
+    }
+
+    /// Compares the two strings on top of the stack.
+    /// The ordering is compared against 0 with the given int comparison opcode.
+    pub fn compile_string_cmp_expr(&mut self, cmp_opcode: Opcode) -> CompilerResult<()> {
+        let lhs_ptr_reg = self.get_next_register()?;
+        let rhs_ptr_reg = self.get_next_register()?;
+        let ordering_reg = self.get_next_register()?;
+        let zero_reg = self.get_next_register()?;
+        let res_reg = self.get_next_register()?;
+
+        let lhs_ptr_instr = Instruction::new(Opcode::SUBU_I)
+            .with_operand::<u8>(Register::SP.into())
+            .with_operand::<u64>(32)
+            .with_operand::<u8>(lhs_ptr_reg.clone().into());
+        let rhs_ptr_instr = Instruction::new(Opcode::SUBU_I)
+            .with_operand::<u8>(Register::SP.into())
+            .with_operand::<u64>(16)
+            .with_operand::<u8>(rhs_ptr_reg.clone().into());
+        let scmp_instr = Instruction::new(Opcode::SCMP)
+            .with_operand::<u8>(lhs_ptr_reg.into())
+            .with_operand::<u8>(rhs_ptr_reg.into())
+            .with_operand::<u8>(ordering_reg.clone().into());
+        let ldi_instr = Instruction::new(Opcode::LDI)
+            .with_operand::<i64>(0)
+            .with_operand::<u8>(zero_reg.clone().into());
+        let cmp_instr = Instruction::new(cmp_opcode)
+            .with_operand::<u8>(ordering_reg.into())
+            .with_operand::<u8>(zero_reg.into())
+            .with_operand::<u8>(res_reg.into());
+
+        self.builder.push_instr(lhs_ptr_instr);
+        self.builder.push_instr(rhs_ptr_instr);
+        self.builder.push_instr(scmp_instr);
+        self.builder.push_instr(ldi_instr);
+        self.builder.push_instr(cmp_instr);
+        Ok(())
+    }
+
+    /// Compiles a slice expression. Only strings can be sliced for now,
+    /// the slice shares its data with the sliced string.
+    pub fn compile_slice_expr(&mut self, expr: &Expression) -> CompilerResult<()> {
+        let (sliced_expr, start_expr, end_expr) = match expr {
+            Expression::Slice(sliced, start, end) => (sliced.deref(), start.deref(), end.deref()),
+            _ => return Err(CompilerError::Unknown)
+        };
+
+        self.compile_expr(sliced_expr)?;
+        self.compile_expr(start_expr)?;
+        let start_reg = self.get_last_register()?;
+        self.compile_expr(end_expr)?;
+        let end_reg = self.get_last_register()?;
+        let string_ptr_reg = self.get_next_register()?;
+
+        let string_ptr_instr = Instruction::new(Opcode::SUBU_I)
+            .with_operand::<u8>(Register::SP.into())
+            .with_operand::<u64>(16)
+            .with_operand::<u8>(string_ptr_reg.clone().into());
+        let sslice_instr = Instruction::new(Opcode::SSLICE)
+            .with_operand::<u8>(string_ptr_reg.clone().into())
+            .with_operand::<u8>(start_reg.into())
+            .with_operand::<u8>(end_reg.into())
+            .with_operand::<u8>(string_ptr_reg.into());
+
+        self.builder.push_instr(string_ptr_instr);
+        self.builder.push_instr(sslice_instr);
+        Ok(())
+    }
+
+    /// Compiles the len() intrinsic, which returns the length of a string or an array
+    pub fn compile_len_expr(&mut self, expr: &Expression) -> CompilerResult<()> {
+        let arg_expr = match expr {
+            Expression::Call(_, args) if args.len() == 1 => &args[0],
+            _ => return Err(CompilerError::ArgumentMismatch(String::from(LEN_INTRINSIC)))
+        };
+
+        let arg_type = self.check_expr_type(arg_expr)?;
+        match self.get_len_arg_type(&arg_type)? {
+            Type::Array(_, size) => {
+                let reg = self.get_next_register()?;
+                let ldi_instr = Instruction::new(Opcode::LDI)
+                    .with_operand::<i64>(size as i64)
+                    .with_operand::<u8>(reg.into());
+                self.builder.push_instr(ldi_instr);
+            },
+            _ => {
+                self.compile_expr(arg_expr)?;
+                let last_reg = self.get_last_register()?;
+                let reg = self.get_next_register()?;
+                // References to strings have to be followed first
+                let movi_instr = if matches!(arg_type, Type::String | Type::AutoArray(_)) {
+                    Instruction::new(Opcode::MOVI_AR)
+                        .with_operand::<u8>(Register::SP.into())
+                        .with_operand::<i16>(-16)
+                        .with_operand::<u8>(reg.into())
+                } else {
+                    Instruction::new(Opcode::MOVI_AR)
+                        .with_operand::<u8>(last_reg.into())
+                        .with_operand::<i16>(0)
+                        .with_operand::<u8>(reg.into())
+                };
+                self.builder.push_instr(movi_instr);
+            }
+        };
+        Ok(())
+    }
+
+    /// Returns the type len() operates on for an argument of the given type
+    fn get_len_arg_type(&self, arg_type: &Type) -> CompilerResult<Type> {
+        match arg_type {
+            Type::String |
+            Type::Array(_, _) |
+            Type::AutoArray(_) => Ok(arg_type.clone()),
+            Type::Reference(inner_type) => {
+                match inner_type.deref() {
+                    Type::String |
+                    Type::Array(_, _) => Ok(inner_type.deref().clone()),
+                    _ => Err(CompilerError::ArgumentMismatch(String::from(LEN_INTRINSIC)))
+                }
+            },
+            _ => Err(CompilerError::ArgumentMismatch(String::from(LEN_INTRINSIC)))
+        }
+    }
+
     /// Compiles a member access expression
     pub fn compile_member_access_expr(&mut self, expr: &Expression, cont_def: Option<&ContainerDef>) -> CompilerResult<()> {
         //println!("Line 2374");
@@ -2430,11 +3516,10 @@ impl Compiler {
         let last_reg = self.get_last_register()?;
         let lhs_reg = self.get_next_register()?;
 
-        let var_type = match lhs_expr {
+        let var_type = match &lhs_expr.node {
             Expression::Variable(var_name) => {
                 // If variable is on stack
                 if cont_def.is_none() {
-                    let var_offset = self.get_sp_offset_of_var(var_name)?;
                     //println!("Member access of stack variable {}. Saving [SP]-{} into register {:?}.", var_name, var_offset.abs(), lhs_reg);
                     let var_type = self.get_type_of_var(var_name)?;
                     //println!("Compiling member access for var {}:{:?} at offset {}", var_name, var_type, var_offset);
@@ -2442,22 +3527,14 @@ impl Compiler {
                         Type::Other(cont_name) => {
                             //println!("Doing this by subtracting {} from SP.", var_offset.abs());
                             //println!("Converting [SP]-8 to pointer in register {:?}", lhs_reg);
-                            let subui_instr = Instruction::new(Opcode::SUBU_I)
-                                .with_operand::<u8>(Register::SP.into())
-                                .with_operand::<u64>(var_offset.abs() as u64)
-                                .with_operand::<u8>(lhs_reg.clone().into());
-                            self.builder.push_instr(subui_instr);
+                            self.compile_var_addr(var_name, lhs_reg.clone())?;
                         },
                         Type::Reference(inner_type) => {
                             match inner_type.deref() {
                                 Type::Other(cont_name) => {
                                     //println!("Doing this by moving pointer at [SP]-{}.", var_offset.abs());
                                     //println!("Saving pointer at [SP]-8 to register {:?}", lhs_reg);
-                                    let mova_instr = Instruction::new(Opcode::MOVA_AR)
-                                        .with_operand::<u8>(Register::SP.into())
-                                        .with_operand::<i16>(var_offset as i16)
-                                        .with_operand::<u8>(lhs_reg.clone().into());
-                                    self.builder.push_instr(mova_instr);
+                                    self.compile_var_ptr(var_name, lhs_reg.clone())?;
                                     //println!("Is reference. moving pointer into register {:?}", lhs_reg);
                                 },
                                 _ => return Err(CompilerError::MemberAccessOnNonContainer)
@@ -2498,9 +3575,51 @@ impl Compiler {
                     member_type
                 }
             },
+            Expression::Call(fn_name, _) => {
+                let ret_type = match cont_def {
+                    // Method call on the container whose address is in last_reg
+                    Some(cont_def) => {
+                        let mova_instr = Instruction::new(Opcode::MOVA)
+                            .with_operand::<u8>(last_reg.into())
+                            .with_operand::<u8>(lhs_reg.into());
+                        self.builder.push_instr(mova_instr);
+                        self.compile_member_call_expr(lhs_expr, cont_def)?;
+                        let fn_def = cont_def.get_member_function(fn_name)?;
+                        fn_def.ret_type.clone()
+                    },
+                    None => {
+                        self.compile_call_expr(lhs_expr)?;
+                        let fn_def = self.resolve_function(fn_name)?;
+                        fn_def.ret_type.clone()
+                    }
+                };
+                // Load the address of the returned container
+                let addr_reg = self.get_next_register()?;
+                match &ret_type {
+                    Type::Other(_) => {
+                        let ret_size = self.get_size_of_type(&ret_type)?;
+                        let subui_instr = Instruction::new(Opcode::SUBU_I)
+                            .with_operand::<u8>(Register::SP.into())
+                            .with_operand::<u64>(ret_size as u64)
+                            .with_operand::<u8>(addr_reg.into());
+                        self.builder.push_instr(subui_instr);
+                    },
+                    Type::Reference(_) => {
+                        let mova_instr = Instruction::new(Opcode::MOVA)
+                            .with_operand::<u8>(Register::R0.into())
+                            .with_operand::<u8>(addr_reg.into());
+                        self.builder.push_instr(mova_instr);
+                    },
+                    _ => return Err(CompilerError::MemberAccessOnNonContainer)
+                };
+                ret_type
+            },
             _ => return Err(CompilerError::UnsupportedExpression(lhs_expr.deref().clone()))
         };
 
+        // Register that contains the address of the lhs container
+        let lhs_reg = self.get_last_register()?;
+
         let cont_name = match &var_type {
             Type::Other(cont_name) => cont_name,
             Type::Reference(inner_type) => {
@@ -2513,7 +3632,7 @@ impl Compiler {
         };
         let cont_def = self.resolve_container(cont_name)?;
 
-        match rhs_expr {
+        match &rhs_expr.node {
             Expression::Variable(member_name) => {
                 //println!("Accessing member {} of container {}", member_name, cont_def.canonical_name);
                 let rhs_reg = self.get_next_register()?;
@@ -2543,36 +3662,64 @@ impl Compiler {
                 }
                 //println!("Stack size after member call expr: {}", self.get_stack_size()?);
             },
-            Expression::MemberAccess(member_expr, _) => {
+            Expression::MemberAccess(_, _) => {
+                // The lhs of the rhs expression is a member or method of this container
                 let rhs_reg = self.get_next_register()?;
-                let member_name = match member_expr.deref() {
-                    Expression::Variable(var_name) => var_name,
-                    _ => return Err(CompilerError::UnsupportedExpression(member_expr.deref().clone()))
-                };
-                let member_type = cont_def.get_member_type(member_name)?;
-                let cont_name = match &member_type {
-                    Type::Other(cont_name) => cont_name,
-                    Type::Reference(inner_type) => {
-                        match inner_type.deref() {
-                            Type::Other(cont_name) => cont_name,
-                            _ => return Err(CompilerError::MemberAccessOnNonContainer)
-                        }
-                    },
-                    _ => return Err(CompilerError::MemberAccessOnNonContainer)
-                };
-                let inner_cont_def = self.resolve_container(cont_name)?;
                 let mova_instr = Instruction::new(Opcode::MOVA)
                     .with_operand::<u8>(lhs_reg.into())
                     .with_operand::<u8>(rhs_reg.into());
                 self.builder.push_instr(mova_instr);
-                self.compile_member_access_expr(rhs_expr, Some(&inner_cont_def))?;
+                self.compile_member_access_expr(rhs_expr, Some(&cont_def))?;
             },
-            _ => return Err(CompilerError::UnsupportedExpression(rhs_expr.clone()))
+            _ => return Err(CompilerError::UnsupportedExpression(rhs_expr.node.clone()))
         };
 
         Ok(())
     }
 
+    /// Lowers reading a member variable of a native container, e.g. `counter.count`,
+    /// into a call of its accessor function. Returns None for every other expression.
+    fn lower_native_member_access(&self, expr: &Spanned<Expression>) -> Option<Spanned<Expression>> {
+        let (lhs_expr, rhs_expr) = match &expr.node {
+            Expression::MemberAccess(lhs, rhs) => (lhs.deref(), rhs.deref()),
+            _ => return None
+        };
+        let var_name = match &lhs_expr.node {
+            Expression::Variable(var_name) => var_name,
+            _ => return None
+        };
+        let cont_name = match self.get_type_of_var(var_name).ok()? {
+            Type::Other(cont_name) => cont_name,
+            Type::Reference(inner_type) => {
+                match inner_type.deref() {
+                    Type::Other(cont_name) => cont_name.clone(),
+                    _ => return None
+                }
+            },
+            _ => return None
+        };
+        let cont_def = self.resolve_container(&cont_name).ok()?;
+
+        // The member may be followed by further member accesses, e.g. `counter.name.len`
+        let (member_name, member_span, rest_expr) = match &rhs_expr.node {
+            Expression::Variable(member_name) => (member_name, rhs_expr.span.clone(), None),
+            Expression::MemberAccess(inner_lhs, inner_rhs) => {
+                match &inner_lhs.node {
+                    Expression::Variable(member_name) => (member_name, inner_lhs.span.clone(), Some(inner_rhs)),
+                    _ => return None
+                }
+            },
+            _ => return None
+        };
+        let accessor_name = cont_def.member_accessors.get(member_name)?;
+        let call_expr = Spanned::new(Expression::Call(accessor_name.clone(), Vec::new()), member_span);
+        let rhs_expr = match rest_expr {
+            Some(rest_expr) => Spanned::new(Expression::MemberAccess(Box::new(call_expr), rest_expr.clone()), rhs_expr.span.clone()),
+            None => call_expr
+        };
+        Some(Spanned::new(Expression::MemberAccess(Box::new(lhs_expr.clone()), Box::new(rhs_expr)), expr.span.clone()))
+    }
+
     /// Compiles a member call expression
     pub fn compile_member_call_expr(&mut self, expr: &Expression, cont_def: &ContainerDef) -> CompilerResult<()> {
         //println!("Line 2718");
@@ -2691,6 +3838,8 @@ impl Compiler {
                         .with_operand::<i16>(-(size as i16)))
                 },
                 Type::String => None,
+                Type::Array(_, _) => None,
+                Type::AutoArray(_) => None,
                 Type::Reference(inner_type) => {
                     match inner_type.deref() {
                         Type::AutoArray(_) => None,
@@ -2762,6 +3911,9 @@ impl Compiler {
 
         // Resolve the container definition
         let cont_def = self.resolve_container(cont_name)?;
+        if cont_def.is_native {
+            return Err(CompilerError::NativeContainerInstance(cont_def.canonical_name));
+        }
 
         // Insert the expressions at the correct position
         for (name, expr) in cont_memper_map.iter() {
@@ -2829,6 +3981,202 @@ impl Compiler {
         Ok(())
     }
 
+    /// Compiles an array literal expression
+    pub fn compile_array_literal_expr(&mut self, expr: &Expression) -> CompilerResult<()> {
+        let elements = match expr {
+            Expression::ArrayLiteral(elements) => elements,
+            _ => return Err(CompilerError::Unknown)
+        };
+
+        // The elements are laid out on the stack in order
+        for element in elements.iter() {
+            let element_type = self.check_expr_type(element)?;
+            self.compile_expr(element)?;
+            if element_type.is_primitive() {
+                let last_reg = self.get_last_register()?;
+                let size = self.get_size_of_type(&element_type)?;
+                let mov_instr = match element_type {
+                    Type::Int => {
+                        Instruction::new(Opcode::MOVI_RA)
+                            .with_operand::<u8>(last_reg.into())
+                            .with_operand::<u8>(Register::SP.into())
+                            .with_operand::<i16>(-(size as i16))
+                    },
+                    Type::Float => {
+                        Instruction::new(Opcode::MOVF_RA)
+                            .with_operand::<u8>(last_reg.into())
+                            .with_operand::<u8>(Register::SP.into())
+                            .with_operand::<i16>(-(size as i16))
+                    },
+                    Type::Bool => {
+                        Instruction::new(Opcode::MOVB_RA)
+                            .with_operand::<u8>(last_reg.into())
+                            .with_operand::<u8>(Register::SP.into())
+                            .with_operand::<i16>(-(size as i16))
+                    },
+                    Type::Reference(_) => {
+                        Instruction::new(Opcode::MOVA_RA)
+                            .with_operand::<u8>(last_reg.into())
+                            .with_operand::<u8>(Register::SP.into())
+                            .with_operand::<i16>(-(size as i16))
+                    },
+                    _ => return Err(CompilerError::UnknownType(element_type))
+                };
+                let stack_inc_instr = Instruction::new_inc_stack(size);
+                self.inc_stack(size)?;
+                self.builder.push_instr(stack_inc_instr);
+                self.builder.push_instr(mov_instr);
+            }
+            // Non-primitive elements are already on top of the stack
+        }
+
+        Ok(())
+    }
+
+    /// Compiles an index expression, reading the element
+    pub fn compile_index_expr(&mut self, expr: &Expression) -> CompilerResult<()> {
+        let element_type = self.compile_index_addr_expr(expr)?;
+        // Register that contains the address of the element
+        let addr_reg = self.get_last_register()?;
+
+        if element_type.is_primitive() {
+            let target_reg = self.get_next_register()?;
+            let opcode = match element_type {
+                Type::Int => Opcode::MOVI_AR,
+                Type::Float => Opcode::MOVF_AR,
+                Type::Bool => Opcode::MOVB_AR,
+                Type::Reference(_) => Opcode::MOVA_AR,
+                _ => return Err(CompilerError::UnknownType(element_type))
+            };
+            let mov_instr = Instruction::new(opcode)
+                .with_operand::<u8>(addr_reg.into())
+                .with_operand::<i16>(0)
+                .with_operand::<u8>(target_reg.into());
+            self.builder.push_instr(mov_instr);
+        } else {
+            // Copy the element on top of the stack
+            let size = self.get_size_of_type(&element_type)?;
+            let stack_inc_instr = Instruction::new_inc_stack(size);
+            self.inc_stack(size)?;
+            let movn_instr = Instruction::new(Opcode::MOVN_A)
+                .with_operand::<u8>(addr_reg.into())
+                .with_operand::<i16>(0)
+                .with_operand::<u8>(Register::SP.into())
+                .with_operand::<i16>(-(size as i16))
+                .with_operand::<u32>(size as u32);
+            self.builder.push_instr(stack_inc_instr);
+            self.builder.push_instr(movn_instr);
+        }
+
+        Ok(())
+    }
+
+    /// Compiles the address calculation of an index expression.
+    /// The address of the element is saved in the last temp register.
+    pub fn compile_index_addr_expr(&mut self, expr: &Expression) -> CompilerResult<Type> {
+        let (indexed_expr, index_expr) = match expr {
+            Expression::Index(indexed_expr, index_expr) => (indexed_expr.deref(), index_expr.deref()),
+            _ => return Err(CompilerError::Unknown)
+        };
+
+        let indexed_type = self.check_expr_type(indexed_expr)?;
+        let (element_type, array_size) = self.get_array_type(&indexed_type)?;
+        let element_size = self.get_size_of_type(&element_type)?;
+
+        let index_type = self.check_expr_type(index_expr)?;
+        if index_type != Type::Int {
+            return Err(CompilerError::TypeMismatch(Type::Int, index_type));
+        }
+
+        // Compile the index first, so the base address can't be overwritten
+        self.compile_expr(index_expr)?;
+        let index_reg = self.get_last_register()?;
+
+        if let Some(array_size) = array_size {
+            let bndchk_instr = Instruction::new(Opcode::BNDCHK)
+                .with_operand::<u8>(index_reg.clone().into())
+                .with_operand::<u64>(array_size as u64);
+            self.builder.push_instr(bndchk_instr);
+        }
+
+        // Address of the array itself, or of the reference to it
+        match &indexed_expr.node {
+            Expression::Variable(var_name) => {
+                let base_reg = self.get_next_register()?;
+                match indexed_type {
+                    Type::Reference(_) => self.compile_var_ptr(var_name, base_reg)?,
+                    _ => self.compile_var_addr(var_name, base_reg)?
+                };
+            },
+            Expression::Index(_, _) => {
+                self.compile_index_addr_expr(indexed_expr)?;
+                if let Type::Reference(_) = indexed_type {
+                    let last_reg = self.get_last_register()?;
+                    let base_reg = self.get_next_register()?;
+                    let mova_instr = Instruction::new(Opcode::MOVA_AR)
+                        .with_operand::<u8>(last_reg.into())
+                        .with_operand::<i16>(0)
+                        .with_operand::<u8>(base_reg.into());
+                    self.builder.push_instr(mova_instr);
+                }
+            },
+            _ => return Err(CompilerError::UnsupportedExpression(indexed_expr.node.clone()).at(indexed_expr.span.clone()))
+        };
+
+        if array_size.is_none() {
+            // Auto arrays have a header with the length at offset 0 and the address of the elements at offset 8
+            let header_reg = self.get_last_register()?;
+            let len_reg = self.get_next_register()?;
+            let movi_instr = Instruction::new(Opcode::MOVI_AR)
+                .with_operand::<u8>(header_reg.clone().into())
+                .with_operand::<i16>(0)
+                .with_operand::<u8>(len_reg.clone().into());
+            let bndchk_instr = Instruction::new(Opcode::BNDCHK_R)
+                .with_operand::<u8>(index_reg.clone().into())
+                .with_operand::<u8>(len_reg.into());
+            let elements_reg = self.get_next_register()?;
+            let mova_instr = Instruction::new(Opcode::MOVA_AR)
+                .with_operand::<u8>(header_reg.into())
+                .with_operand::<i16>(8)
+                .with_operand::<u8>(elements_reg.into());
+            self.builder.push_instr(movi_instr);
+            self.builder.push_instr(bndchk_instr);
+            self.builder.push_instr(mova_instr);
+        }
+
+        let base_reg = self.get_last_register()?;
+        let offset_reg = self.get_next_register()?;
+        let muli_instr = Instruction::new(Opcode::MULI_I)
+            .with_operand::<u8>(index_reg.into())
+            .with_operand::<i64>(element_size as i64)
+            .with_operand::<u8>(offset_reg.clone().into());
+        let addr_reg = self.get_next_register()?;
+        let addu_instr = Instruction::new(Opcode::ADDU)
+            .with_operand::<u8>(base_reg.into())
+            .with_operand::<u8>(offset_reg.into())
+            .with_operand::<u8>(addr_reg.into());
+        self.builder.push_instr(muli_instr);
+        self.builder.push_instr(addu_instr);
+
+        Ok(element_type)
+    }
+
+    /// Returns the element type and size of an array, or of a reference to an array.
+    /// The size of an auto array is only known at runtime and returned as None.
+    pub fn get_array_type(&self, array_type: &Type) -> CompilerResult<(Type, Option<usize>)> {
+        match array_type {
+            Type::Array(element_type, size) => Ok((element_type.deref().clone(), Some(*size))),
+            Type::AutoArray(element_type) => Ok((element_type.deref().clone(), None)),
+            Type::Reference(inner_type) => {
+                match inner_type.deref() {
+                    Type::Array(element_type, size) => Ok((element_type.deref().clone(), Some(*size))),
+                    _ => Err(CompilerError::NotIndexable(array_type.clone()))
+                }
+            },
+            _ => Err(CompilerError::NotIndexable(array_type.clone()))
+        }
+    }
+
     /// Compiles a call expresion
     pub fn compile_call_expr(&mut self, expr: &Expression) -> CompilerResult<()> {
         //println!("Line 2718");
@@ -2926,7 +4274,7 @@ impl Compiler {
                         .with_operand::<u8>(Register::SP.into())
                         .with_operand::<i16>(-(size as i16)))
                 },
-                Type::String => None,
+                Type::String | Type::AutoArray(_) | Type::Other(_) => None,
                 Type::Reference(inner_type) => {
                     match inner_type.deref() {
                         Type::AutoArray(_) => None,
@@ -2992,7 +4340,9 @@ impl Compiler {
         //println!("Compiling var expr");
 
         let var_type = self.get_type_of_var(var_name)?;
-        let mut var_offset = self.get_sp_offset_of_var(var_name)?;
+        let (base_reg, mut var_offset) = self.get_var_location(var_name)?;
+        // Offsets to SP change when the stack grows, static addresses do not
+        let is_sp_relative = base_reg == Register::SP;
         match var_type {
             Type::Int => {
                 let reg = {
@@ -3000,7 +4350,7 @@ impl Compiler {
                     fn_ctx.register_allocator.get_temp_register()?
                 };
                 let movi_instr = Instruction::new(Opcode::MOVI_AR)
-                    .with_operand::<u8>(Register::SP.into())
+                    .with_operand::<u8>(base_reg.into())
                     .with_operand::<i16>(var_offset as i16)
                     .with_operand::<u8>(reg.into());
                 self.builder.push_instr(movi_instr);
@@ -3011,7 +4361,7 @@ impl Compiler {
                     fn_ctx.register_allocator.get_temp_register()?
                 };
                 let movf_instr = Instruction::new(Opcode::MOVF_AR)
-                    .with_operand::<u8>(Register::SP.into())
+                    .with_operand::<u8>(base_reg.into())
                     .with_operand::<i16>(var_offset as i16)
                     .with_operand::<u8>(reg.into());
                 self.builder.push_instr(movf_instr);
@@ -3022,7 +4372,7 @@ impl Compiler {
                     fn_ctx.register_allocator.get_temp_register()?
                 };
                 let movb_instr = Instruction::new(Opcode::MOVB_AR)
-                    .with_operand::<u8>(Register::SP.into())
+                    .with_operand::<u8>(base_reg.into())
                     .with_operand::<i16>(var_offset as i16)
                     .with_operand::<u8>(reg.into());
                 self.builder.push_instr(movb_instr);
@@ -3032,9 +4382,11 @@ impl Compiler {
                     Type::AutoArray(_) => {
                         let stack_inc_instr = Instruction::new_inc_stack(16);
                         self.inc_stack(16)?;
-                        var_offset -= 16;
+                        if is_sp_relative {
+                            var_offset -= 16;
+                        }
                         let movn_instr = Instruction::new(Opcode::MOVN_A)
-                            .with_operand::<u8>(Register::SP.into())
+                            .with_operand::<u8>(base_reg.into())
                             .with_operand::<i16>(var_offset as i16)
                             .with_operand::<u8>(Register::SP.into())
                             .with_operand::<i16>(-16)
@@ -3048,7 +4400,7 @@ impl Compiler {
                             fn_ctx.register_allocator.get_temp_register()?
                         };
                         let mova_instr = Instruction::new(Opcode::MOVA_AR)
-                            .with_operand::<u8>(Register::SP.into())
+                            .with_operand::<u8>(base_reg.into())
                             .with_operand::<i16>(var_offset as i16)
                             .with_operand::<u8>(reg.into());
                         self.builder.push_instr(mova_instr);
@@ -3062,10 +4414,12 @@ impl Compiler {
                 let stack_inc_instr = Instruction::new_inc_stack(size);
                 self.inc_stack(size)?;
 
-                var_offset -= size as i64;
+                if is_sp_relative {
+                    var_offset -= size as i64;
+                }
 
                 let movn_instr = Instruction::new(Opcode::MOVN_A)
-                    .with_operand::<u8>(Register::SP.into())
+                    .with_operand::<u8>(base_reg.into())
                     .with_operand::<i16>(var_offset as i16)
                     .with_operand::<u8>(Register::SP.into())
                     .with_operand::<i16>(-(size as i16))
@@ -3074,6 +4428,26 @@ impl Compiler {
                 self.builder.push_instr(stack_inc_instr);
                 self.builder.push_instr(movn_instr);
             },
+            Type::Array(_, _) | Type::AutoArray(_) | Type::String => {
+                let size = self.get_size_of_type(&var_type)?;
+
+                let stack_inc_instr = Instruction::new_inc_stack(size);
+                self.inc_stack(size)?;
+
+                if is_sp_relative {
+                    var_offset -= size as i64;
+                }
+
+                let movn_instr = Instruction::new(Opcode::MOVN_A)
+                    .with_operand::<u8>(base_reg.into())
+                    .with_operand::<i16>(var_offset as i16)
+                    .with_operand::<u8>(Register::SP.into())
+                    .with_operand::<i16>(-(size as i16))
+                    .with_operand::<u32>(size as u32);
+
+                self.builder.push_instr(stack_inc_instr);
+                self.builder.push_instr(movn_instr);
+            },
             _ => {
                 //println!("Errors in compile_var_expr()");
                 return Err(CompilerError::UnknownType(var_type));
@@ -3083,10 +4457,15 @@ impl Compiler {
         Ok(())
     }
 
-    /// Returns the type of an expression and checks for type mismatches
-    pub fn check_expr_type(&self, expr: &Expression) -> CompilerResult<Type> {
+    /// Returns the type of an expression and checks for type mismatches, errors point to the innermost expression
+    pub fn check_expr_type(&self, expr: &Spanned<Expression>) -> CompilerResult<Type> {
+        self.check_expr_node_type(expr)
+            .map_err(|err| err.at(expr.span.clone()))
+    }
+
+    fn check_expr_node_type(&self, expr: &Spanned<Expression>) -> CompilerResult<Type> {
         //println!("Checking type of expr: {:?}", expr);
-        let expr_type = match expr {
+        let expr_type = match &expr.node {
             Expression::IntLiteral(_) => Type::Int,
             Expression::FloatLiteral(_) => Type::Float,
             Expression::BoolLiteral(_) => Type::Bool,
@@ -3095,6 +4474,13 @@ impl Compiler {
                 let expr_type = self.check_expr_type(expr)?;
                 Type::Reference(Box::new(expr_type))
             },
+            Expression::New(expr) => {
+                let expr_type = self.check_expr_type(expr)?;
+                if let Type::AutoArray(_) = expr_type {
+                    return Err(CompilerError::UnsupportedExpression(expr.node.clone()));
+                }
+                Type::Reference(Box::new(expr_type))
+            },
             Expression::Deref(expr) => {
                 let expr_type = self.check_expr_type(expr)?;
                 match expr_type {
@@ -3107,6 +4493,27 @@ impl Compiler {
                     _ => return Err(CompilerError::CannotDerefNonPointer)
                 };
             },
+            Expression::Call(fn_name, args) if fn_name == LEN_INTRINSIC => {
+                if args.len() != 1 {
+                    return Err(CompilerError::ArgumentMismatch(String::from(LEN_INTRINSIC)));
+                }
+                let arg_type = self.check_expr_type(&args[0])?;
+                self.get_len_arg_type(&arg_type)?;
+                Type::Int
+            },
+            Expression::Slice(sliced, start, end) => {
+                let sliced_type = self.check_expr_type(sliced)?;
+                if sliced_type != Type::String {
+                    return Err(CompilerError::NotSliceable(sliced_type));
+                }
+                for bound in [start, end].iter() {
+                    let bound_type = self.check_expr_type(bound)?;
+                    if bound_type != Type::Int {
+                        return Err(CompilerError::TypeMismatch(Type::Int, bound_type));
+                    }
+                }
+                Type::String
+            },
             Expression::Call(fn_name, _) => {
                 let fn_def = self.resolve_function(fn_name)?;
                 fn_def.ret_type
@@ -3115,11 +4522,35 @@ impl Compiler {
                 self.get_type_of_var(var_name)?
             },
             Expression::MemberAccess(_, _) => {
-                self.check_member_access_expr_type(expr, None)?
+                match self.lower_native_member_access(expr) {
+                    Some(lowered_expr) => self.check_expr_type(&lowered_expr)?,
+                    None => self.check_member_access_expr_type(expr, None)?
+                }
             },
             Expression::ContainerInstance(cont_name, _) => {
                 Type::Other(cont_name.clone())
             },
+            Expression::ArrayLiteral(elements) => {
+                let first_type = match elements.get(0) {
+                    Some(first) => self.check_expr_type(first)?,
+                    None => return Err(CompilerError::EmptyArrayLiteral)
+                };
+                for element in elements.iter().skip(1) {
+                    let element_type = self.check_expr_type(element)?;
+                    if element_type != first_type {
+                        return Err(CompilerError::TypeMismatch(first_type, element_type));
+                    }
+                }
+                Type::Array(Box::new(first_type), elements.len())
+            },
+            Expression::Index(indexed_expr, index_expr) => {
+                let index_type = self.check_expr_type(index_expr)?;
+                if index_type != Type::Int {
+                    return Err(CompilerError::TypeMismatch(Type::Int, index_type));
+                }
+                let indexed_type = self.check_expr_type(indexed_expr)?;
+                self.get_array_type(&indexed_type)?.0
+            },
             Expression::Assign(lhs, rhs) => {
                 let lhs_type = self.check_expr_type(lhs)?;
                 let rhs_type = self.check_expr_type(rhs)?;
@@ -3160,6 +4591,22 @@ impl Compiler {
                 }
                 lhs_type
             },
+            Expression::Modulo(lhs, rhs) |
+            Expression::BitAnd(lhs, rhs) |
+            Expression::BitOr(lhs, rhs) |
+            Expression::BitXor(lhs, rhs) |
+            Expression::ShiftLeft(lhs, rhs) |
+            Expression::ShiftRight(lhs, rhs) => {
+                let lhs_type = self.check_expr_type(lhs)?;
+                let rhs_type = self.check_expr_type(rhs)?;
+                if lhs_type != Type::Int {
+                    return Err(CompilerError::TypeMismatch(Type::Int, lhs_type));
+                }
+                if rhs_type != Type::Int {
+                    return Err(CompilerError::TypeMismatch(Type::Int, rhs_type));
+                }
+                Type::Int
+            },
             Expression::LessThan(lhs, rhs) => {
                 let lhs_type = self.check_expr_type(lhs)?;
                 let rhs_type = self.check_expr_type(rhs)?;
@@ -3215,6 +4662,14 @@ impl Compiler {
                 }
                 Type::Bool
             },
+            Expression::Negate(op) => {
+                let op_type = self.check_expr_type(op)?;
+                match op_type {
+                    Type::Int |
+                    Type::Float => op_type,
+                    _ => return Err(CompilerError::TypeMismatch(Type::Int, op_type))
+                }
+            },
             Expression::And(lhs, rhs) => {
                 let lhs_type = self.check_expr_type(lhs)?;
                 let rhs_type = self.check_expr_type(rhs)?;
@@ -3231,7 +4686,7 @@ impl Compiler {
                 }
                 Type::Bool
             },
-            _ => return Err(CompilerError::UnsupportedExpression(expr.clone()))
+            _ => return Err(CompilerError::UnsupportedExpression(expr.node.clone()))
         };
         Ok(expr_type)
         //Err(CompilerError::Unimplemented(format!("Expr type checking not implemented!")))
@@ -3243,7 +4698,7 @@ impl Compiler {
             _ => return Err(CompilerError::Unknown)
         };
 
-        let lhs_type = match lhs_expr {
+        let lhs_type = match &lhs_expr.node {
             Expression::Variable(var_name) => {
                 // If this is a stack variable
                 if cont_def.is_none() {
@@ -3255,7 +4710,13 @@ impl Compiler {
                     cont_def.get_member_type(var_name)?
                 }
             },
-            _ => return Err(CompilerError::UnsupportedExpression(lhs_expr.clone()))
+            Expression::Call(fn_name, _) => {
+                match cont_def {
+                    Some(cont_def) => cont_def.get_member_function(fn_name)?.ret_type.clone(),
+                    None => self.resolve_function(fn_name)?.ret_type.clone()
+                }
+            },
+            _ => return Err(CompilerError::UnsupportedExpression(lhs_expr.node.clone()))
         };
 
         let cont_name = match &lhs_type {
@@ -3271,7 +4732,7 @@ impl Compiler {
 
         let cont_def = self.resolve_container(cont_name)?;
 
-        match &rhs_expr {
+        match &rhs_expr.node {
             Expression::Variable(var_name) => {
                 cont_def.get_member_type(var_name)
             },
@@ -3279,24 +4740,8 @@ impl Compiler {
                 let fn_def = cont_def.get_member_function(fn_name)?;
                 Ok(fn_def.ret_type.clone())
             },
-            Expression::MemberAccess(member_expr, _) => {
-                let member_name = match member_expr.deref() {
-                    Expression::Variable(var_name) => var_name,
-                    _ => return Err(CompilerError::UnsupportedExpression(member_expr.deref().clone()))
-                };
-                let member_type = cont_def.get_member_type(member_name)?;
-                let child_cont_name = match &member_type {
-                    Type::Other(cont_name) => cont_name,
-                    Type::Reference(inner_type) => {
-                        match inner_type.deref() {
-                            Type::Other(cont_name) => cont_name,
-                            _ => return Err(CompilerError::MemberAccessOnNonContainer)
-                        }
-                    },
-                    _ => return Err(CompilerError::MemberAccessOnNonContainer)
-                };
-                let child_cont_def = self.resolve_container(child_cont_name)?;
-                self.check_member_access_expr_type(rhs_expr, Some(&child_cont_def))
+            Expression::MemberAccess(_, _) => {
+                self.check_member_access_expr_type(rhs_expr, Some(&cont_def))
             },
             _ => return Err(CompilerError::MemberAccessOnNonContainer)
         }
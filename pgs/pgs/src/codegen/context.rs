@@ -2,7 +2,8 @@ use crate::{
     codegen::{
         def::{
             ContainerDef,
-            FunctionDef
+            FunctionDef,
+            StaticVarDef
         },
         register::{
             Register,
@@ -27,12 +28,13 @@ use std::{
     }
 };
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ModuleContext {
     pub name: String,
     pub modules: HashMap<String, ModuleContext>,
     pub functions: HashMap<String, FunctionDef>,
     pub containers: HashMap<String, ContainerDef>,
+    pub static_vars: HashMap<String, StaticVarDef>,
     pub imports: HashMap<String, String>
 }
 
@@ -44,6 +46,7 @@ impl ModuleContext {
             modules: HashMap::new(),
             functions: HashMap::new(),
             containers: HashMap::new(),
+            static_vars: HashMap::new(),
             imports: HashMap::new()
         }
     }
@@ -81,6 +84,17 @@ impl ModuleContext {
         Ok(())
     }
 
+    /// Adds a static variable definition to a module context.
+    /// Throws a DuplicateVariableError if a static variable with the
+    /// same name already exists.
+    pub fn add_static_var(&mut self, def: StaticVarDef) -> CompilerResult<()> {
+        if self.static_vars.contains_key(&def.name) {
+            return Err(CompilerError::DuplicateVariable(def.name));
+        }
+        self.static_vars.insert(def.name.clone(), def);
+        Ok(())
+    }
+
     /// Adds an import declaration to a module context
     /// Throws a DuplicateImportError if an import with the same
     /// "import_as" name already exists.
@@ -104,6 +118,12 @@ impl ModuleContext {
             .ok_or(CompilerError::UnknownContainer(name.clone()))
     }
 
+    /// Gets a reference to a static variable definition, given the name
+    pub fn get_static_var(&self, name: &String) -> CompilerResult<&StaticVarDef> {
+        self.static_vars.get(name)
+            .ok_or(CompilerError::UnknownVariable(name.clone()))
+    }
+
     /// Gets a reference to the function definition, given the name
     pub fn get_function(&self, name: &String) -> CompilerResult<&FunctionDef> {
         self.functions.get(name)
@@ -117,11 +137,13 @@ pub enum VariableLocation {
     Register(Register)
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct FunctionContext {
     pub def: Option<FunctionDef>,
     pub weak: bool,
     pub is_loop: bool,
+    /// Whether this is the body of a try statement, leaving it has to end the try
+    pub is_try: bool,
     pub stack_size: usize,
     variable_types: HashMap<String, Type>,
     variable_positions: HashMap<String, i64>,
@@ -151,6 +173,7 @@ impl FunctionContext {
                 def: Some(def),
                 weak: false,
                 is_loop: false,
+                is_try: false,
                 stack_size: 0,
                 variable_types: variable_types,
                 variable_positions: variable_positions,
@@ -177,6 +200,7 @@ impl FunctionContext {
                 def: None,
                 weak: true,
                 is_loop: false,
+                is_try: false,
                 stack_size: 0,
                 variable_types: fn_ctx.variable_types.clone(),
                 variable_positions: variable_positions,
@@ -203,6 +227,7 @@ impl FunctionContext {
                 def: None,
                 weak: true,
                 is_loop: true,
+                is_try: false,
                 stack_size: 0,
                 variable_types: fn_ctx.variable_types.clone(),
                 variable_positions: variable_positions,
@@ -211,6 +236,12 @@ impl FunctionContext {
         )
     }
 
+    pub fn new_try(fn_ctx: &FunctionContext) -> CompilerResult<FunctionContext> {
+        let mut try_fn_ctx = FunctionContext::new_weak(fn_ctx)?;
+        try_fn_ctx.is_try = true;
+        Ok(try_fn_ctx)
+    }
+
     pub fn set_stack_var(&mut self, (var_name, var_type): (String, Type), stack_pos: i64) -> CompilerResult<()> {
         if self.variable_types.contains_key(&var_name) {
             return Err(CompilerError::DuplicateVariable(var_name));
@@ -255,6 +286,7 @@ impl FunctionContext {
     }
 }
 
+#[derive(Clone)]
 pub struct LoopContext {
     pub pos_start: usize,
     pub tag_end: u64
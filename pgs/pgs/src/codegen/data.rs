@@ -1,14 +1,16 @@
 use std::{
     collections::{
         HashMap
-    }
+    },
+    ops::Range
 };
 
 /// Manager struct for static data
 #[derive(Clone)]
 pub struct Data {
     pub bytes: Vec<u8>,
-    strings: HashMap<String, usize>
+    strings: HashMap<String, usize>,
+    statics: Vec<Range<usize>>
 }
 
 impl Data {
@@ -16,7 +18,8 @@ impl Data {
     pub fn new() -> Data {
         Self {
             bytes: Vec::new(),
-            strings: HashMap::new()
+            strings: HashMap::new(),
+            statics: Vec::new()
         }
     }
 
@@ -33,4 +36,17 @@ impl Data {
         self.strings.insert(string.clone(), addr);
         (byte_len, addr as u64)
     }
+
+    /// Stores the initial value of a static variable, returns its address
+    pub fn add_static(&mut self, bytes: &[u8]) -> u64 {
+        let addr = self.bytes.len();
+        self.bytes.extend_from_slice(bytes);
+        self.statics.push(addr..self.bytes.len());
+        addr as u64
+    }
+
+    /// Returns the address ranges of all static variables
+    pub fn get_statics(&self) -> &[Range<usize>] {
+        &self.statics
+    }
 }
\ No newline at end of file
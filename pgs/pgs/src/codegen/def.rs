@@ -86,7 +86,11 @@ pub struct ContainerDef {
     /// Map of member variable indices
     pub member_indices: BTreeMap<String, usize>,
     /// Map of member functions
-    pub member_functions: HashMap<String, FunctionDef>
+    pub member_functions: HashMap<String, FunctionDef>,
+    /// Map of member variables of native containers to the functions that read them
+    pub member_accessors: HashMap<String, String>,
+    /// Whether instances are handles to Rust values, see `api::container::Container`
+    pub is_native: bool
 }
 
 impl ContainerDef {
@@ -97,7 +101,9 @@ impl ContainerDef {
             canonical_name: canon_name,
             member_indices: BTreeMap::new(),
             member_functions: HashMap::new(),
-            member_variables: HashMap::new()
+            member_variables: HashMap::new(),
+            member_accessors: HashMap::new(),
+            is_native: false
         }
     }
 
@@ -125,13 +131,12 @@ impl ContainerDef {
     pub fn get_member_offset(&self, compiler: &Compiler, var_name: &String) -> CompilerResult<usize> {
         let target_index = self.get_member_index(var_name)?;
         let mut offset = 0;
+        // Members are laid out in declaration order, not in name order
         for (member_name, member_index) in self.member_indices.iter() {
-            let member_type = self.get_member_type(member_name)?;
-            let member_size = compiler.get_size_of_type(&member_type)?;
-            if *member_index == target_index {
-                break;
+            if *member_index < target_index {
+                let member_type = self.get_member_type(member_name)?;
+                offset += compiler.get_size_of_type(&member_type)?;
             }
-            offset += member_size;
         }
         Ok(offset)
     }
@@ -145,6 +150,10 @@ impl ContainerDef {
 
     /// Returns the byte size of this container
     pub fn get_size(&self, compiler: &Compiler) -> CompilerResult<usize> {
+        // Instances of native containers are foreign pointers
+        if self.is_native {
+            return Ok(8);
+        }
         let mut size = 0;
         for (_, var_type) in self.member_variables.iter() {
             size += compiler.get_size_of_type(var_type)?;
@@ -178,4 +187,25 @@ impl ContainerDef {
         def.merge_cont_decl(item);
         def
     }
-}
\ No newline at end of file
+}
+/// A static variable definition
+#[derive(Clone, PartialEq, Debug)]
+pub struct StaticVarDef {
+    /// Name of the static variable
+    pub name: String,
+    /// Type of the static variable
+    pub var_type: Type,
+    /// Address of the static variable in the data segment
+    pub address: u64
+}
+
+impl StaticVarDef {
+    /// Creates a new static variable definition
+    pub fn new(name: String, var_type: Type, address: u64) -> StaticVarDef {
+        StaticVarDef {
+            name: name,
+            var_type: var_type,
+            address: address
+        }
+    }
+}
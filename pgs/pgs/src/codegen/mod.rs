@@ -1,5 +1,7 @@
 pub mod compiler;
 
+pub mod bytecode;
+
 pub mod builder;
 
 pub mod instruction;
@@ -1,6 +1,7 @@
 use crate::{
     api::{
-        function::Function
+        function::Function,
+        script_function::ScriptFunction
     },
 };
 
@@ -17,7 +18,11 @@ pub struct Program {
     pub code: Vec<u8>,
     pub functions: HashMap<u64, usize>,
     pub foreign_functions: HashMap<u64, Function>,
-    pub static_pointers: BTreeMap<usize, Range<usize>> 
+    pub static_pointers: BTreeMap<usize, Range<usize>>,
+    pub static_vars: Vec<Range<usize>>,
+    pub function_uids: HashMap<String, u64>,
+    /// Signatures of the script functions, by uid
+    pub script_functions: HashMap<u64, ScriptFunction>
 }
 
 impl Program {
@@ -26,7 +31,10 @@ impl Program {
             code: Vec::new(),
             functions: HashMap::new(),
             foreign_functions: HashMap::new(),
-            static_pointers: BTreeMap::new() 
+            static_pointers: BTreeMap::new(),
+            static_vars: Vec::new(),
+            function_uids: HashMap::new(),
+            script_functions: HashMap::new()
         }
     }
 
@@ -50,6 +58,33 @@ impl Program {
         self
     }
 
+    pub fn with_static_vars(mut self, static_vars: Vec<Range<usize>>) -> Program {
+        self.static_vars = static_vars;
+        self
+    }
+
+    pub fn with_function_uids(mut self, function_uids: HashMap<String, u64>) -> Program {
+        self.function_uids = function_uids;
+        self
+    }
+
+    pub fn with_script_functions(mut self, script_functions: HashMap<u64, ScriptFunction>) -> Program {
+        self.script_functions = script_functions;
+        self
+    }
+
+    /// Gets the uid of a function by its full name
+    pub fn get_function_uid(&self, name: &str) -> Option<u64> {
+        self.function_uids.get(name)
+            .cloned()
+    }
+
+    /// Gets the signature of a script function by its full name
+    pub fn get_script_function(&self, name: &str) -> Option<&ScriptFunction> {
+        self.get_function_uid(name)
+            .and_then(|uid| self.script_functions.get(&uid))
+    }
+
     pub fn get_size(&self) -> usize {
         self.code.len()
     }
@@ -56,7 +56,7 @@ impl Into<u8> for Register {
     }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct RegisterAllocator {
     register_queue: VecDeque<Register>,
     blocked_registers: HashSet<Register>,
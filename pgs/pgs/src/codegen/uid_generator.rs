@@ -11,6 +11,7 @@ use rand::{
 };
 
 /// Convenience struct for generating unique u64s
+#[derive(Clone)]
 pub struct UIDGenerator {
     uid_set: HashSet<u64>,
     functions: HashMap<String, u64>,
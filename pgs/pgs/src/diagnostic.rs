@@ -0,0 +1,114 @@
+use std::{
+    fmt::{
+        Display,
+        Formatter,
+        Result as FmtResult
+    },
+    ops::Range
+};
+
+/// A position in a source file, lines and columns start at 1
+#[derive(PartialEq, Debug, Clone)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize
+}
+
+impl SourceLocation {
+    /// Computes the line and column of a byte offset into the given code
+    pub fn from_offset(file: &str, code: &str, offset: usize) -> SourceLocation {
+        let offset = floor_char_boundary(code, offset);
+        let before = &code[..offset];
+        let line_start = line_start(code, offset);
+        SourceLocation {
+            file: String::from(file),
+            line: before.matches('\n').count() + 1,
+            column: code[line_start..offset].chars().count() + 1
+        }
+    }
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// An error message pointing at a span of source code
+#[derive(PartialEq, Debug, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    pub location: SourceLocation,
+    pub span: Range<usize>,
+    source_line: String,
+    underline: Range<usize>
+}
+
+impl Diagnostic {
+    pub fn new(message: String, file: &str, code: &str, span: Range<usize>) -> Diagnostic {
+        let start = floor_char_boundary(code, span.start);
+        let line_start = line_start(code, start);
+        let line_end = code[start..].find('\n')
+            .map(|i| start + i)
+            .unwrap_or(code.len());
+        let source_line = code[line_start..line_end].trim_end_matches('\r');
+
+        // The underline is clipped to the first line of the span
+        let end = floor_char_boundary(code, span.end.min(line_end).max(start));
+        let underline_start = code[line_start..start].chars().count();
+        let underline_len = code[start..end].chars().count().max(1);
+
+        Diagnostic {
+            message,
+            location: SourceLocation::from_offset(file, code, start),
+            span,
+            source_line: String::from(source_line),
+            underline: underline_start..underline_start + underline_len
+        }
+    }
+
+    /// Renders the message together with the offending source line and a caret underline
+    pub fn render(&self) -> String {
+        let line_nr = self.location.line.to_string();
+        let gutter = " ".repeat(line_nr.len());
+
+        // Keep tabs in the indentation, so the carets line up with the source line
+        let indent: String = self.source_line.chars()
+            .take(self.underline.start)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = "^".repeat(self.underline.end - self.underline.start);
+
+        format!(
+            "error: {}\n{}--> {}\n{} |\n{} | {}\n{} | {}{}",
+            self.message,
+            gutter, self.location,
+            gutter,
+            line_nr, self.source_line,
+            gutter, indent, carets
+        )
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.render())
+    }
+}
+
+/// Returns the byte offset of the start of the line containing the offset
+fn line_start(code: &str, offset: usize) -> usize {
+    code[..offset].rfind('\n')
+        .map(|i| i + 1)
+        .unwrap_or(0)
+}
+
+/// Clamps an offset into the code and moves it back to the nearest char boundary
+fn floor_char_boundary(code: &str, offset: usize) -> usize {
+    let mut offset = offset.min(code.len());
+    while !code.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
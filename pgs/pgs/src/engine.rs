@@ -4,6 +4,11 @@ use crate::{
             Core,
             CoreError
         },
+        address::{
+            Address,
+            AddressType
+        },
+        heap::HeapStats,
         register::{
             RegisterAccess,
             Register as RegisterUnion
@@ -16,7 +21,15 @@ use crate::{
         },
         ast::{
             Declaration,
-            Statement
+            Expression,
+            FunctionDeclArgs,
+            Spanned,
+            Statement,
+            Type
+        },
+        printer::{
+            self,
+            type_name
         }
     },
     codegen::{
@@ -24,14 +37,39 @@ use crate::{
             Compiler,
             CompilerError
         },
+        bytecode::{
+            self,
+            BytecodeError
+        },
+        def::StaticVarDef,
+        program::Program,
         register::Register
     },
     api::{
-        module::Module
-    }
+        module::Module,
+        script_function::ScriptFunction,
+        value::{
+            ScriptArgs,
+            ScriptValue,
+            Value,
+            ValueType,
+            ValueReader,
+            ValueWriter
+        }
+    },
+    assembler::{
+        assembler::AssemblerError,
+        disassembler::Disassembler
+    },
+    diagnostic::Diagnostic
 };
 
 use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        Mutex
+    },
     io::{
         Read
     },
@@ -50,14 +88,11 @@ use std::{
     }
 };
 
-use serde::{
-    de::DeserializeOwned,
-    Serialize
-};
-
 pub struct Engine {
     core: Core,
-    pub compiler: Compiler
+    compiler: Compiler,
+    /// Static variables holding the results of `eval`, one per result type
+    eval_results: Vec<StaticVarDef>
 }
 
 pub type EngineResult<T> = Result<T, Box<EngineError>>;
@@ -68,11 +103,96 @@ pub enum EngineError {
     CoreError(CoreError),
     ParseError(ParseError),
     CompileError(CompilerError),
+    BytecodeError(BytecodeError),
+    AssemblerError(AssemblerError),
+    /// Values of the type can't be passed to or returned from a call from the outside
+    UnsupportedType(ValueType),
+    /// The arguments or the return type of a call don't match the signature of the function
+    SignatureMismatch {
+        name: String,
+        expected: String,
+        found: String
+    },
+    /// An error that points to a location in the source code
+    Located(Box<EngineError>, Diagnostic),
+    /// Every error found while loading code, in source order
+    Multiple(Vec<EngineError>)
+}
+
+impl EngineError {
+    /// Returns the diagnostic if the error points to a location in the source code
+    pub fn diagnostic(&self) -> Option<&Diagnostic> {
+        match self {
+            EngineError::Located(_, diagnostic) => Some(diagnostic),
+            _ => None
+        }
+    }
+
+    /// Returns the diagnostics of all errors that point to a location in the source code
+    pub fn diagnostics(&self) -> Vec<&Diagnostic> {
+        match self {
+            EngineError::Multiple(errors) => {
+                errors.iter()
+                    .filter_map(|err| err.diagnostic())
+                    .collect()
+            },
+            _ => self.diagnostic().into_iter().collect()
+        }
+    }
+
+    /// Combines a list of errors, a single error is returned as is
+    fn from_errors(mut errors: Vec<EngineError>) -> Box<EngineError> {
+        if errors.len() == 1 {
+            Box::new(errors.remove(0))
+        } else {
+            Box::new(EngineError::Multiple(errors))
+        }
+    }
+
+    fn from_parse_error(err: ParseError, file: &str, code: &str) -> EngineError {
+        let diagnostic = Diagnostic::new(format!("{}", err), file, code, err.token_pos.clone());
+        EngineError::Located(Box::new(EngineError::ParseError(err)), diagnostic)
+    }
+
+    fn from_compiler_errors(err: CompilerError, file: &str, code: &str) -> Box<EngineError> {
+        EngineError::from_errors(
+            err.into_errors().into_iter()
+                .map(|c| EngineError::from_compiler_error(c, file, code))
+                .collect()
+        )
+    }
+
+    fn from_compiler_error(err: CompilerError, file: &str, code: &str) -> EngineError {
+        match err.span() {
+            Some(span) => {
+                let err = err.without_span();
+                let diagnostic = Diagnostic::new(format!("{}", err), file, code, span);
+                EngineError::Located(Box::new(EngineError::CompileError(err)), diagnostic)
+            },
+            None => EngineError::CompileError(err)
+        }
+    }
 }
 
 impl Display for EngineError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        write!(f, "{:?}", self)
+        match self {
+            EngineError::Located(_, diagnostic) => write!(f, "{}", diagnostic),
+            EngineError::BytecodeError(err) => write!(f, "error: {}", err),
+            EngineError::AssemblerError(err) => write!(f, "error: {}", err),
+            EngineError::CoreError(err @ CoreError::ForeignFunction { .. }) |
+            EngineError::CoreError(err @ CoreError::Thrown(_)) => write!(f, "error: {}", err),
+            EngineError::SignatureMismatch { name, expected, found } => {
+                write!(f, "error: signature mismatch for {}: expected {}, found {}", name, expected, found)
+            },
+            EngineError::Multiple(errors) => {
+                let messages: Vec<String> = errors.iter()
+                    .map(|err| err.to_string())
+                    .collect();
+                write!(f, "{}", messages.join("\n\n"))
+            },
+            _ => write!(f, "{:?}", self)
+        }
     }
 }
 
@@ -84,7 +204,8 @@ impl Engine {
         let mut compiler = Compiler::new();
         Engine {
             core: Core::new(stack_size),
-            compiler: compiler
+            compiler: compiler,
+            eval_results: Vec::new()
         }
     }
 
@@ -94,55 +215,651 @@ impl Engine {
     }
 
     pub fn load_code(&mut self, code: &str) -> EngineResult<()> {
-        let parser = Parser::new(String::from(code));
-        let decl_list = parser.parse_root_decl_list()
-            .map_err(|p| {
-                let mut offset = 0;
-                let token_range = p.token_pos.clone();
-                let mut line_nr = 0;
-                for line in code.lines() {
-                    if offset <= token_range.start && offset + line.len() >= token_range.end {
-                        println!("Parse error in line #{} at offset {}", line_nr, token_range.start - offset);
-                    }
-                    offset += line.len();
-                    line_nr += 1;
-                }
-                Box::new(EngineError::ParseError(p))
-            })?;
-        self.compiler.compile_root(&decl_list)
-            .map_err(|c| Box::new(EngineError::CompileError(c)))?;
-        let program = self.compiler.get_program()
-            .map_err(|c| Box::new(EngineError::CompileError(c)))?;
+        self.load_source("<input>", code)
+    }
+
+    /// Loads code read from the given file, errors point to locations in that file
+    pub fn load_source(&mut self, file: &str, code: &str) -> EngineResult<()> {
+        let program = self.compile_source(file, code)?;
+        self.core.load_program(program);
+        Ok(())
+    }
+
+    /// Compiles code into the precompiled (.pgc) format, without loading it
+    pub fn precompile(&mut self, file: &str, code: &str) -> EngineResult<Vec<u8>> {
+        let program = self.compile_source(file, code)?;
+        bytecode::write_program(&program)
+            .map_err(|b| Box::new(EngineError::BytecodeError(b)))
+    }
+
+    /// Loads a precompiled program, its foreign functions are linked against the registered modules
+    pub fn load_bytecode(&mut self, bytes: &[u8]) -> EngineResult<()> {
+        let mut program = bytecode::read_program(bytes)
+            .map_err(|b| Box::new(EngineError::BytecodeError(b)))?;
+        self.link_foreign_functions(&mut program)
+            .map_err(|b| Box::new(EngineError::BytecodeError(b)))?;
+        self.core.load_program(program);
+        Ok(())
+    }
+
+    /// Loads a program written in the textual assembly format, e.g. to test the VM without the compiler
+    #[cfg(test)]
+    pub(crate) fn load_assembly(&mut self, code: &str) -> EngineResult<()> {
+        let program = crate::assembler::assembler::Assembler::new(String::from(code)).assemble()
+            .map_err(|a| Box::new(EngineError::AssemblerError(a)))?;
         self.core.load_program(program);
         Ok(())
     }
 
+    /// Replaces the foreign function signatures of a program with the registered functions of the same name
+    fn link_foreign_functions(&self, program: &mut Program) -> Result<(), BytecodeError> {
+        for (name, uid) in program.function_uids.iter() {
+            let expected = match program.foreign_functions.get(uid) {
+                Some(function) => function,
+                None => continue
+            };
+            let registered = self.compiler.get_foreign_function(name)
+                .map_err(|_| BytecodeError::MissingForeignFunction(name.clone()))?;
+            if registered.arg_types != expected.arg_types || registered.return_type != expected.return_type {
+                return Err(BytecodeError::ForeignFunctionMismatch {
+                    name: name.clone(),
+                    expected: bytecode::signature_string(&expected.arg_types, &expected.return_type),
+                    found: bytecode::signature_string(&registered.arg_types, &registered.return_type)
+                });
+            }
+            program.foreign_functions.insert(*uid, registered);
+        }
+        Ok(())
+    }
+
+    /// Checks if the bytes are a precompiled program rather than source code
+    pub fn is_bytecode(bytes: &[u8]) -> bool {
+        bytecode::is_bytecode(bytes)
+    }
+
+    /// Renders the tokens of the code, one per line
+    pub fn print_tokens(code: &str) -> String {
+        printer::print_tokens(code)
+    }
+
+    /// Renders the syntax tree of the code, errors point to locations in the given file
+    pub fn print_ast(&self, file: &str, code: &str) -> EngineResult<String> {
+        let decl_list = self.parse_source(file, code)?;
+        Ok(printer::print_decl_list(&decl_list))
+    }
+
+    /// Compiles the code without loading it and disassembles it, with the labels of the instructions
+    pub fn disassemble(&mut self, file: &str, code: &str) -> EngineResult<String> {
+        let program = self.compile_source(file, code)?;
+        let labels = self.get_code_labels(&program);
+        Ok(Disassembler::new(&program).with_labels(labels).disassemble())
+    }
+
+    /// Disassembles a precompiled program
+    pub fn disassemble_bytecode(bytes: &[u8]) -> EngineResult<String> {
+        let program = bytecode::read_program(bytes)
+            .map_err(|b| Box::new(EngineError::BytecodeError(b)))?;
+        Ok(Disassembler::new(&program).disassemble())
+    }
+
+    /// Parses code without compiling it, errors point to locations in the given file
+    pub(crate) fn parse_source(&self, file: &str, code: &str) -> EngineResult<Vec<Spanned<Declaration>>> {
+        let parser = Parser::new(String::from(code));
+        parser.parse_root()
+            .map_err(|errors| {
+                EngineError::from_errors(
+                    errors.into_iter()
+                        .map(|p| EngineError::from_parse_error(p, file, code))
+                        .collect()
+                )
+            })
+    }
+
+    /// Compiles code into a program without loading it, errors point to locations in the given file
+    pub(crate) fn compile_source(&mut self, file: &str, code: &str) -> EngineResult<Program> {
+        let decl_list = self.parse_source(file, code)?;
+        // Declarations of failed code must not leak into later compilations
+        let compiler_backup = self.compiler.clone();
+        let program = self.compiler.compile_root(&decl_list)
+            .and_then(|_| self.compiler.get_program())
+            .map_err(|c| EngineError::from_compiler_errors(c, file, code));
+        if program.is_err() {
+            self.compiler = compiler_backup;
+        }
+        program
+    }
+
+    /// Maps the code offsets of the last compiled program to the labels of its instructions, for disassembly
+    pub(crate) fn get_code_labels(&self, program: &Program) -> HashMap<usize, String> {
+        let builder = self.compiler.get_builder();
+        // The data section comes before the instructions
+        let data_len = program.code.len() - builder.get_current_offset();
+        builder.labels.keys()
+            .filter_map(|label| Some((builder.get_label_offset(label)? + data_len, label.clone())))
+            .collect()
+    }
+
     pub fn run_file(&mut self, path: &Path) -> EngineResult<()> {
+        self.load_file(path)?;
+        self.run_fn("root::main")
+    }
+
+    /// Loads a script or a precompiled program from a file, without running it
+    pub fn load_file(&mut self, path: &Path) -> EngineResult<()> {
         let mut file = File::open(path)
             .map_err(|_| Box::new(EngineError::Unknown))?;
+        self.load_readable(&path.to_string_lossy(), &mut file)
+    }
 
-        let mut file_content = String::new();
-        file.read_to_string(&mut file_content)
-            .map_err(|_| Box::new(EngineError::Unknown))?;
+    /// Evaluates one input of an interactive session on top of the code loaded so far.
+    ///
+    /// Declarations are added to the root module. Statements are compiled into a function that is run right away and dropped afterwards,
+    /// the variables they declare at the top level become static variables so later inputs can use them.
+    /// If the input ends with an expression, its formatted value is returned.
+    /// The compiler state is restored if the input fails to compile.
+    pub fn eval(&mut self, code: &str) -> EngineResult<Option<String>> {
+        let compiler_backup = self.compiler.clone();
+        let eval_res = self.eval_input(code);
+        if let Err(err) = &eval_res {
+            if !matches!(err.as_ref(), EngineError::CoreError(_)) {
+                self.compiler = compiler_backup;
+            }
+        }
+        eval_res
+    }
+
+    fn eval_input(&mut self, code: &str) -> EngineResult<Option<String>> {
+        let file = "<repl>";
+        let parser = Parser::new(String::from(code));
+        if parser.is_decl_list() {
+            let program = self.compile_source(file, code)?;
+            self.core.reload_program(program);
+            return Ok(None);
+        }
+
+        // The last statement may omit its ";"
+        let mut code = String::from(code.trim_end());
+        if !code.ends_with(';') && !code.ends_with('}') {
+            code.push(';');
+        }
+        let code = code.as_str();
+        let to_engine_error = |c: CompilerError, span| {
+            Box::new(EngineError::from_compiler_error(c.at(span), file, code))
+        };
 
-        self.run_code(&file_content)
+        let stmt_list = Parser::new(String::from(code)).parse_root_stmt_list()
+            .map_err(|errors| {
+                EngineError::from_errors(
+                    errors.into_iter()
+                        .map(|p| EngineError::from_parse_error(p, file, code))
+                        .collect()
+                )
+            })?;
+
+        let fn_name = String::from("__eval");
+        let stmt_count = stmt_list.len();
+        let mut result = None;
+        let mut code_block = Vec::new();
+        for (i, stmt) in stmt_list.into_iter().enumerate() {
+            let Spanned { node, span } = stmt;
+            let node = match node {
+                Statement::VariableDecl(var_decl_args) => {
+                    let mut var_type = var_decl_args.var_type.clone();
+                    if var_type == Type::Auto {
+                        var_type = self.compiler.check_expr_type(&var_decl_args.assignment)
+                            .map_err(|c| to_engine_error(c, span.clone()))?;
+                    }
+                    self.compiler.redeclare_static_var(&var_decl_args.name, &var_type)
+                        .map_err(|c| to_engine_error(c, span.clone()))?;
+                    Self::assign_stmt(&var_decl_args.name, var_decl_args.assignment)
+                },
+                Statement::Expression(expr) if i == stmt_count - 1 && !expr.is_assignment() => {
+                    let expr_type = self.compiler.check_expr_type(&expr)
+                        .map_err(|c| to_engine_error(c, span.clone()))?;
+                    if expr_type == Type::Void {
+                        Statement::Expression(expr)
+                    } else {
+                        let static_def = match self.eval_results.iter().find(|def| def.var_type == expr_type) {
+                            Some(static_def) => static_def.clone(),
+                            None => {
+                                let result_name = format!("__eval_result_{}", self.eval_results.len());
+                                self.compiler.redeclare_static_var(&result_name, &expr_type)
+                                    .map_err(|c| to_engine_error(c, span.clone()))?
+                            }
+                        };
+                        let assign = Self::assign_stmt(&static_def.name, Box::new(expr));
+                        result = Some(static_def);
+                        assign
+                    }
+                },
+                node => node
+            };
+            code_block.push(Spanned::new(node, span));
+        }
+
+        let fn_decl = Declaration::Function(FunctionDeclArgs {
+            name: fn_name.clone(),
+            arguments: Vec::new(),
+            returns: Type::Void,
+            code_block: Some(code_block)
+        });
+        let program = self.compiler.compile_root(&[Spanned::new(fn_decl, 0..code.len())])
+            .and_then(|_| self.compiler.get_program())
+            .map_err(|c| EngineError::from_compiler_errors(c, file, code))?;
+        if let Some(static_def) = &result {
+            if !self.eval_results.contains(static_def) {
+                self.eval_results.push(static_def.clone());
+            }
+        }
+        self.core.reload_program(program);
+
+        // A failed run leaves the frames of the function on the stack
+        let stack_size = self.core.get_stack_size();
+        let run_res = self.run_fn(format!("root::{}", fn_name));
+        self.compiler.remove_root_function(&fn_name)
+            .map_err(|c| Box::new(EngineError::CompileError(c)))?;
+        if run_res.is_err() {
+            self.core.set_stack_size(stack_size)
+                .map_err(|c| Box::new(EngineError::CoreError(c)))?;
+        }
+        run_res?;
+
+        match result {
+            Some(static_def) => self.format_value(static_def.address, &static_def.var_type).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    /// Creates the statement `name = expr;`
+    fn assign_stmt(name: &str, expr: Box<Spanned<Expression>>) -> Statement {
+        let span = expr.span.clone();
+        Statement::Expression(
+            Spanned::new(
+                Expression::Assign(
+                    Box::new(Spanned::new(Expression::Variable(String::from(name)), span.clone())),
+                    expr
+                ),
+                span
+            )
+        )
+    }
+
+    /// Formats the value of the given type stored at an address, e.g. `[1, 2]` or `Point { x: 1, y: 2 }`
+    pub(crate) fn format_value(&self, addr: u64, var_type: &Type) -> EngineResult<String> {
+        let core_err = |c| Box::new(EngineError::CoreError(c));
+        let compile_err = |c| Box::new(EngineError::CompileError(c));
+        let value = match var_type {
+            Type::Int => self.core.mem_get::<i64>((addr, 0)).map_err(core_err)?.to_string(),
+            Type::Float => format!("{:?}", self.core.mem_get::<f32>((addr, 0)).map_err(core_err)?),
+            Type::Bool => (self.core.mem_get::<u8>((addr, 0)).map_err(core_err)? != 0).to_string(),
+            Type::String => format!("{:?}", self.core.mem_get_string(addr).map_err(core_err)?),
+            Type::Array(inner_type, size) => {
+                let inner_size = self.compiler.get_size_of_type(inner_type)
+                    .map_err(compile_err)?;
+                let elements = (0..*size)
+                    .map(|i| self.format_value(addr + (i * inner_size) as u64, inner_type))
+                    .collect::<EngineResult<Vec<String>>>()?;
+                format!("[{}]", elements.join(", "))
+            },
+            Type::Other(cont_name) => {
+                let cont_def = self.compiler.resolve_container(cont_name)
+                    .map_err(compile_err)?;
+                if cont_def.is_native {
+                    let ptr = self.core.mem_get::<u64>((addr, 0)).map_err(core_err)?;
+                    return Ok(format!("{}({:#x})", cont_def.name, ptr));
+                }
+                let mut members: Vec<(&String, &usize)> = cont_def.member_indices.iter().collect();
+                members.sort_by_key(|(_, index)| **index);
+                let mut values = Vec::new();
+                for (member_name, _) in members {
+                    let member_type = cont_def.get_member_type(member_name)
+                        .map_err(compile_err)?;
+                    let member_offset = cont_def.get_member_offset(&self.compiler, member_name)
+                        .map_err(compile_err)?;
+                    let value = self.format_value(addr + member_offset as u64, &member_type)?;
+                    values.push(format!("{}: {}", member_name, value));
+                }
+                format!("{} {{ {} }}", cont_def.name, values.join(", "))
+            },
+            Type::Reference(_) => format!("{:#x}", self.core.mem_get::<u64>((addr, 0)).map_err(core_err)?),
+            _ => format!("<{}>", type_name(var_type))
+        };
+        Ok(value)
     }
 
+    /// Reads code or a precompiled program from a reader until it ends, e.g. a script piped on stdin, and runs it
     pub fn run_stream(&mut self, readable: Box<dyn Read>) -> EngineResult<()> {
-        Err(Box::new(EngineError::Unknown))
+        self.load_stream(readable)?;
+        self.run_fn("root::main")
     }
 
-    pub fn push_stack<T: Serialize>(&mut self, item: T) -> EngineResult<()> {
-        self.core.push_stack(item)
-            .map_err(|c| Box::new(EngineError::CoreError(c)))
+    /// Loads code or a precompiled program from a reader until it ends, without running it
+    pub fn load_stream(&mut self, mut readable: Box<dyn Read>) -> EngineResult<()> {
+        self.load_readable("<stdin>", &mut readable)
     }
 
-    pub fn pop_stack<T: DeserializeOwned>(&mut self) -> EngineResult<T> {
-        self.core.pop_stack()
-            .map_err(|c| Box::new(EngineError::CoreError(c)))
+    fn load_readable<R: Read + ?Sized>(&mut self, file: &str, readable: &mut R) -> EngineResult<()> {
+        let mut content = Vec::new();
+        readable.read_to_end(&mut content)
+            .map_err(|_| Box::new(EngineError::Unknown))?;
+
+        if bytecode::is_bytecode(&content) {
+            self.load_bytecode(&content)?;
+        } else {
+            let code = String::from_utf8(content)
+                .map_err(|_| Box::new(EngineError::Unknown))?;
+            self.load_source(file, &code)?;
+        }
+        Ok(())
+    }
+
+    /// Gets the signature of a compiled script function by its full name, e.g. `root::main`.
+    /// Functions of the loaded program are preferred, the same way `run_fn` looks them up.
+    pub fn get_function(&self, name: &str) -> EngineResult<ScriptFunction> {
+        if let Some(function) = self.core.get_script_function(name) {
+            return Ok(function.clone());
+        }
+        self.compiler.get_script_function(name)
+            .map_err(|c| Box::new(EngineError::CompileError(c)))
+    }
+
+    /// Gets the signatures of all compiled script functions, sorted by name
+    pub fn get_functions(&self) -> Vec<ScriptFunction> {
+        let mut names = self.compiler.get_function_names();
+        names.extend(self.core.get_script_function_names());
+        names.sort();
+        names.dedup();
+        names.iter()
+            // Functions generated by eval are not part of the program
+            .filter(|name| !name.rsplit("::").next().unwrap_or(name).starts_with("__"))
+            .filter_map(|name| self.get_function(name).ok())
+            .collect()
+    }
+
+    /// Calls a compiled function with typed arguments and returns its typed result, e.g.
+    /// `engine.call::<_, i64>("root::add", (1i64, 2i64))`.
+    /// The argument and return types are checked against the declared signature of the function first.
+    pub fn call<A: ScriptArgs, R: ScriptValue>(&mut self, name: &str, args: A) -> EngineResult<R> {
+        let function = self.get_function(name)?;
+        let found_args: Vec<Type> = A::arg_types().iter()
+            .map(Type::from)
+            .collect();
+        let found_ret = Type::from(&R::script_type());
+        let mismatch = || {
+            Box::new(EngineError::SignatureMismatch {
+                name: String::from(name),
+                expected: function.signature(),
+                found: bytecode::signature_string(&found_args, &found_ret)
+            })
+        };
+        let values = args.to_values();
+        // Containers are only checked by name, their members have to match as well
+        if function.arguments.len() != found_args.len() ||
+            !function.arguments.iter().zip(found_args.iter()).all(|((_, lhs), rhs)| self.types_match(lhs, rhs)) ||
+            !function.arguments.iter().zip(values.iter()).all(|((_, arg_type), value)| self.value_matches(value, arg_type)) ||
+            !self.types_match(&function.return_type, &found_ret) {
+            return Err(mismatch());
+        }
+
+        let mut writer = ValueWriter::new(&mut self.core);
+        for value in values.iter() {
+            writer.write_value(value)
+                .map_err(|c| Box::new(EngineError::CoreError(c)))?;
+        }
+        let bytes = writer.into_bytes();
+
+        let ret_bytes = self.call_with_bytes(name, &bytes, &function.return_type)?;
+        if function.return_type == Type::Void {
+            return R::from_void().ok_or_else(mismatch);
+        }
+        let value = self.read_value(&mut ValueReader::new(&self.core, &ret_bytes), &function.return_type)?;
+        R::from_value(value).ok_or_else(mismatch)
+    }
+
+    /// Calls a script function with runtime typed arguments, e.g. values parsed from the command line.
+    /// The values are checked against the signature of the function, void functions return `None`.
+    pub fn call_function(&mut self, function: &ScriptFunction, args: &[Value]) -> EngineResult<Option<Value>> {
+        let matches = function.arguments.len() == args.len() &&
+            function.arguments.iter().zip(args.iter()).all(|((_, arg_type), value)| self.value_matches(value, arg_type));
+        if !matches {
+            let found_args: Vec<Type> = args.iter()
+                .map(|value| Type::from(&value.value_type()))
+                .collect();
+            return Err(Box::new(EngineError::SignatureMismatch {
+                name: function.name.clone(),
+                expected: function.signature(),
+                found: bytecode::signature_string(&found_args, &function.return_type)
+            }));
+        }
+
+        let mut writer = ValueWriter::new(&mut self.core);
+        for value in args.iter() {
+            writer.write_value(value)
+                .map_err(|c| Box::new(EngineError::CoreError(c)))?;
+        }
+        let bytes = writer.into_bytes();
+
+        let ret_bytes = self.call_with_bytes(&function.name, &bytes, &function.return_type)?;
+        if function.return_type == Type::Void {
+            return Ok(None);
+        }
+        let mut reader = ValueReader::new(&self.core, &ret_bytes);
+        let value = self.read_value(&mut reader, &function.return_type)?;
+        // Handles are kept alive while the caller holds them
+        self.set_foreign_refs_pinned(&value, true)?;
+        Ok(Some(value))
+    }
+
+    /// Hands a Rust value to the engine, scripts can pass the returned handle around.
+    /// The value is kept alive until the handle is released with `release_foreign`.
+    pub fn insert_foreign<T: Send + 'static>(&mut self, item: Arc<Mutex<T>>) -> EngineResult<Value> {
+        let value = self.core.insert_foreign_ptr(item)
+            .map(Value::ForeignRef)
+            .map_err(|c| Box::new(EngineError::CoreError(c)))?;
+        self.set_foreign_refs_pinned(&value, true)?;
+        Ok(value)
+    }
+
+    /// Releases the handles in a value that were inserted or returned by `call_function`.
+    /// Their Rust values are dropped by the garbage collector once scripts don't refer to them anymore.
+    pub fn release_foreign(&mut self, value: &Value) -> EngineResult<()> {
+        self.set_foreign_refs_pinned(value, false)
+    }
+
+    fn set_foreign_refs_pinned(&mut self, value: &Value, pinned: bool) -> EngineResult<()> {
+        match value {
+            Value::ForeignRef(ptr) => self.core.set_foreign_ptr_pinned(*ptr, pinned)
+                .map_err(|c| Box::new(EngineError::CoreError(c))),
+            Value::Array(elements) |
+            Value::AutoArray(elements) => {
+                elements.iter()
+                    .try_for_each(|element| self.set_foreign_refs_pinned(element, pinned))
+            },
+            Value::Container(_, members) => {
+                members.iter()
+                    .try_for_each(|(_, member)| self.set_foreign_refs_pinned(member, pinned))
+            },
+            _ => Ok(())
+        }
+    }
+
+    /// Gets the Rust value behind a handle.
+    /// Fails with `CoreError::ForeignTypeMismatch` if the type is not the one it was inserted with.
+    pub fn get_foreign<T: Send + 'static>(&self, value: &Value) -> EngineResult<Arc<Mutex<T>>> {
+        match value {
+            Value::ForeignRef(ptr) => self.core.get_foreign_ptr(*ptr)
+                .map_err(|c| Box::new(EngineError::CoreError(c))),
+            _ => Err(Box::new(EngineError::UnsupportedType(value.value_type())))
+        }
+    }
+
+    /// Checks if two types are the same, container names are compared by their canonical names
+    fn types_match(&self, lhs: &Type, rhs: &Type) -> bool {
+        let mut lhs = lhs.clone();
+        let mut rhs = rhs.clone();
+        self.compiler.canonize_type(&mut lhs).is_ok() &&
+            self.compiler.canonize_type(&mut rhs).is_ok() &&
+            lhs == rhs
+    }
+
+    /// Checks if a runtime typed value has the layout of a type, containers have to list their members in declaration order
+    fn value_matches(&self, value: &Value, value_type: &Type) -> bool {
+        match (value, value_type) {
+            (Value::Int(_), Type::Int) |
+            (Value::Float(_), Type::Float) |
+            (Value::Bool(_), Type::Bool) |
+            (Value::String(_), Type::String) |
+            (Value::ForeignRef(_), Type::Reference(_)) => true,
+            (Value::Array(elements), Type::Array(inner_type, size)) => {
+                elements.len() == *size &&
+                    elements.iter().all(|element| self.value_matches(element, inner_type))
+            },
+            (Value::AutoArray(elements), Type::AutoArray(inner_type)) => {
+                elements.iter().all(|element| self.value_matches(element, inner_type))
+            },
+            // Instances of native containers are handles to Rust values, created for that container
+            (Value::ForeignRef(ptr), Type::Other(_)) => {
+                let mut cont_type = value_type.clone();
+                match (self.core.get_foreign_container(*ptr), self.compiler.canonize_type(&mut cont_type)) {
+                    (Some(container), Ok(())) => {
+                        self.is_native_container(value_type) && cont_type == Type::Other(String::from(container))
+                    },
+                    _ => false
+                }
+            },
+            (Value::Container(name, members), Type::Other(_)) => {
+                if !self.types_match(&Type::Other(name.clone()), value_type) || self.is_native_container(value_type) {
+                    return false;
+                }
+                match self.get_container_members(value_type) {
+                    Ok(cont_members) => {
+                        cont_members.len() == members.len() &&
+                            cont_members.iter().zip(members.iter()).all(|((cont_name, member_type), (name, member))| {
+                                cont_name == name && self.value_matches(member, member_type)
+                            })
+                    },
+                    Err(_) => false
+                }
+            },
+            _ => false
+        }
+    }
+
+    /// Checks if a type is a container whose instances are handles to Rust values
+    fn is_native_container(&self, cont_type: &Type) -> bool {
+        match cont_type {
+            Type::Other(cont_name) => {
+                self.compiler.resolve_container(cont_name)
+                    .map(|cont_def| cont_def.is_native)
+                    .unwrap_or(false)
+            },
+            _ => false
+        }
+    }
+
+    /// Returns the members of a container type in declaration order
+    fn get_container_members(&self, cont_type: &Type) -> EngineResult<Vec<(String, Type)>> {
+        let cont_name = match cont_type {
+            Type::Other(cont_name) => cont_name,
+            _ => return Err(Box::new(EngineError::UnsupportedType(ValueType::from(cont_type))))
+        };
+        let cont_def = self.compiler.resolve_container(cont_name)
+            .map_err(|c| Box::new(EngineError::CompileError(c)))?;
+        let mut members: Vec<(&String, &usize)> = cont_def.member_indices.iter().collect();
+        members.sort_by_key(|(_, index)| **index);
+        members.into_iter()
+            .map(|(member_name, _)| {
+                cont_def.get_member_type(member_name)
+                    .map(|member_type| (member_name.clone(), member_type))
+                    .map_err(|c| Box::new(EngineError::CompileError(c)))
+            })
+            .collect()
+    }
+
+    /// Reads a runtime typed value in the layout of the given type
+    fn read_value(&self, reader: &mut ValueReader, value_type: &Type) -> EngineResult<Value> {
+        let core_err = |c| Box::new(EngineError::CoreError(c));
+        let value = match value_type {
+            Type::Int => Value::Int(reader.read_int().map_err(core_err)?),
+            Type::Float => Value::Float(reader.read_float().map_err(core_err)?),
+            Type::Bool => Value::Bool(reader.read_bool().map_err(core_err)?),
+            Type::String => Value::String(reader.read_string().map_err(core_err)?),
+            Type::Array(inner_type, size) => {
+                let elements = (0..*size)
+                    .map(|_| self.read_value(reader, inner_type))
+                    .collect::<EngineResult<Vec<Value>>>()?;
+                Value::Array(elements)
+            },
+            Type::AutoArray(inner_type) => {
+                let size = reader.read_u64().map_err(core_err)? as usize;
+                let addr = reader.read_u64().map_err(core_err)?;
+                let element_size = self.compiler.get_size_of_type(inner_type)
+                    .map_err(|c| Box::new(EngineError::CompileError(c)))?;
+                let bytes = match size {
+                    0 => Vec::new(),
+                    size => self.core.mem_get_n((addr, 0), size * element_size).map_err(core_err)?
+                };
+                let mut element_reader = ValueReader::new(&self.core, &bytes);
+                let elements = (0..size)
+                    .map(|_| self.read_value(&mut element_reader, inner_type))
+                    .collect::<EngineResult<Vec<Value>>>()?;
+                Value::AutoArray(elements)
+            },
+            Type::Other(_) if self.is_native_container(value_type) => {
+                Value::ForeignRef(reader.read_u64().map_err(core_err)?)
+            },
+            Type::Other(cont_name) => {
+                let members = self.get_container_members(value_type)?.into_iter()
+                    .map(|(member_name, member_type)| {
+                        self.read_value(reader, &member_type)
+                            .map(|member| (member_name, member))
+                    })
+                    .collect::<EngineResult<Vec<(String, Value)>>>()?;
+                Value::Container(cont_name.clone(), members)
+            },
+            Type::Reference(_) => {
+                let ptr = reader.read_u64().map_err(core_err)?;
+                // Only references to Rust values are handed out, script memory stays inside the engine
+                if Address::from(ptr).address_type != AddressType::Foreign {
+                    return Err(Box::new(EngineError::UnsupportedType(ValueType::from(value_type))));
+                }
+                Value::ForeignRef(ptr)
+            },
+            _ => return Err(Box::new(EngineError::UnsupportedType(ValueType::from(value_type))))
+        };
+        Ok(value)
     }
 
-    pub fn get_register_value<T>(&mut self, reg: Register) -> EngineResult<T>
+    /// Runs a function with arguments that are already in their memory layout and returns the bytes of its result.
+    /// Primitives are returned in R0, everything else on top of the stack.
+    fn call_with_bytes(&mut self, name: &str, args: &[u8], ret_type: &Type) -> EngineResult<Vec<u8>> {
+        let stack_size = self.core.get_stack_size();
+        self.core.push_stack_bytes(args)
+            .map_err(|c| Box::new(EngineError::CoreError(c)))?;
+
+        let call_res = self.run_fn(name)
+            .and_then(|_| {
+                let bytes = match ret_type {
+                    Type::Void => Vec::new(),
+                    Type::Int => self.get_register_value::<i64>(Register::R0)?.to_le_bytes().to_vec(),
+                    Type::Float => self.get_register_value::<f32>(Register::R0)?.to_le_bytes().to_vec(),
+                    Type::Bool => (self.get_register_value::<bool>(Register::R0)? as u32).to_le_bytes().to_vec(),
+                    _ => {
+                        let size = self.compiler.get_size_of_type(ret_type)
+                            .map_err(|c| Box::new(EngineError::CompileError(c)))?;
+                        self.core.pop_stack_bytes(size)
+                            .map_err(|c| Box::new(EngineError::CoreError(c)))?
+                    }
+                };
+                Ok(bytes)
+            });
+        // The arguments are left on the stack by the function
+        self.core.set_stack_size(stack_size)
+            .map_err(|c| Box::new(EngineError::CoreError(c)))?;
+        call_res
+    }
+
+    fn get_register_value<T>(&mut self, reg: Register) -> EngineResult<T>
         where RegisterUnion: RegisterAccess<T> {
         let val = self.core.reg(reg.into())
             .map_err(|ce| EngineError::CoreError(ce))?
@@ -154,11 +871,29 @@ impl Engine {
         self.core.get_stack_size()
     }
 
-    pub fn run_fn<T>(&mut self, name: T) -> EngineResult<()>
+    /// Frees all unreachable heap allocations and returns the amount of freed bytes
+    pub fn collect_garbage(&mut self) -> usize {
+        self.core.collect_garbage()
+    }
+
+    /// Sets the amount of heap bytes allocated in between automatic garbage collections, 0 disables them
+    pub fn set_gc_threshold(&mut self, threshold: usize) {
+        self.core.set_gc_threshold(threshold);
+    }
+
+    pub fn get_heap_stats(&self) -> HeapStats {
+        self.core.get_heap_stats()
+    }
+
+    pub(crate) fn run_fn<T>(&mut self, name: T) -> EngineResult<()>
         where String: From<T> {
         let name = String::from(name);
-        let fn_uid = self.compiler.get_function_uid(&name)
-            .map_err(|ce| EngineError::CompileError(ce))?;
+        // Precompiled programs carry their own function table
+        let fn_uid = match self.core.get_function_uid(&name) {
+            Some(fn_uid) => fn_uid,
+            None => self.compiler.get_function_uid(&name)
+                .map_err(|ce| EngineError::CompileError(ce))?
+        };
         self.core.run_fn(fn_uid)
             .map_err(|c| Box::new(EngineError::CoreError(c)))
     }
@@ -5,13 +5,29 @@ extern crate rand;
 #[macro_use] extern crate memoffset;
 extern crate enum_primitive_derive as epd;
 extern crate num_traits;
+#[cfg(feature = "derive")]
+extern crate pgs_derive as derive;
 
-pub mod parser;
+// Parts of the internals are only used by the tests of the crate
+#[allow(dead_code)]
+pub(crate) mod parser;
 
-pub mod vm;
+#[allow(dead_code)]
+pub(crate) mod vm;
 
-pub mod codegen;
+#[allow(dead_code)]
+pub(crate) mod codegen;
 
-pub mod engine;
+pub(crate) mod engine;
 
-pub mod api;
\ No newline at end of file
+pub mod prelude;
+
+pub(crate) mod api;
+
+pub(crate) mod diagnostic;
+
+#[allow(dead_code)]
+pub(crate) mod assembler;
+
+#[cfg(test)]
+mod tests;
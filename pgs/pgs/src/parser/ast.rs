@@ -3,9 +3,46 @@ use std::{
         HashMap,
         BTreeMap
     },
-    ops::Deref
+    ops::{
+        Deref,
+        Range
+    }
 };
 
+/// Byte range of a node in the source code
+pub type Span = Range<usize>;
+
+/// A node of the syntax tree together with the span of source code it was parsed from
+#[derive(Debug, Clone)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Spanned<T> {
+        Spanned {
+            node,
+            span
+        }
+    }
+}
+
+/// Nodes are compared without their spans, the same code is equal wherever it was parsed from
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, rhs: &Spanned<T>) -> bool {
+        self.node == rhs.node
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum Expression {
     IntLiteral(i64),
@@ -13,29 +50,40 @@ pub enum Expression {
     StringLiteral(String),
     BoolLiteral(bool),
     Variable(String),
-    ContainerInstance(String, HashMap<String, Expression>),
-    MemberAccess(Box<Expression>, Box<Expression>),
-    Deref(Box<Expression>),
-    Ref(Box<Expression>),
-    Call(String, Vec<Expression>),
-    Addition(Box<Expression>, Box<Expression>),
-    Subtraction(Box<Expression>, Box<Expression>),
-    Multiplication(Box<Expression>, Box<Expression>),
-    Division(Box<Expression>, Box<Expression>),
-    Not(Box<Expression>),
-    And(Box<Expression>, Box<Expression>),
-    Or(Box<Expression>, Box<Expression>),
-    Equals(Box<Expression>, Box<Expression>),
-    NotEquals(Box<Expression>, Box<Expression>),
-    GreaterThan(Box<Expression>, Box<Expression>),
-    LessThan(Box<Expression>, Box<Expression>),
-    GreaterThanEquals(Box<Expression>, Box<Expression>),
-    LessThanEquals(Box<Expression>, Box<Expression>),
-    Assign(Box<Expression>, Box<Expression>),
-    AddAssign(Box<Expression>, Box<Expression>),
-    SubAssign(Box<Expression>, Box<Expression>),
-    MulAssign(Box<Expression>, Box<Expression>),
-    DivAssign(Box<Expression>, Box<Expression>),
+    ContainerInstance(String, HashMap<String, Spanned<Expression>>),
+    ArrayLiteral(Vec<Spanned<Expression>>),
+    Index(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    Slice(Box<Spanned<Expression>>, Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    MemberAccess(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    Deref(Box<Spanned<Expression>>),
+    Ref(Box<Spanned<Expression>>),
+    New(Box<Spanned<Expression>>),
+    Negate(Box<Spanned<Expression>>),
+    Call(String, Vec<Spanned<Expression>>),
+    Addition(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    Subtraction(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    Multiplication(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    Division(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    Modulo(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    BitAnd(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    BitOr(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    BitXor(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    ShiftLeft(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    ShiftRight(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    Not(Box<Spanned<Expression>>),
+    And(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    Or(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    Equals(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    NotEquals(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    GreaterThan(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    LessThan(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    GreaterThanEquals(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    LessThanEquals(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    Assign(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    AddAssign(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    SubAssign(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    MulAssign(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    DivAssign(Box<Spanned<Expression>>, Box<Spanned<Expression>>),
 }
 
 impl Expression {
@@ -131,11 +179,25 @@ impl Expression {
     pub fn is_member_call(&self) -> bool {
         match self {
             Expression::MemberAccess(_, rhs) => {
-                rhs.is_member_call()
+                match &rhs.node {
+                    Expression::Call(_, _) => true,
+                    _ => rhs.is_member_call()
+                }
             },
             _ => false
         }
     }
+
+    /// Checks if an expression is an assignment, e.g. `a = b` or `a += b`
+    pub fn is_assignment(&self) -> bool {
+        matches!(self,
+            Expression::Assign(_, _) |
+            Expression::AddAssign(_, _) |
+            Expression::SubAssign(_, _) |
+            Expression::MulAssign(_, _) |
+            Expression::DivAssign(_, _)
+        )
+    }
 }
 
 #[derive(PartialEq, Debug)]
@@ -160,7 +222,7 @@ pub struct FunctionDeclArgs {
     pub name: String,
     pub arguments: Vec<(String, Type)>,
     pub returns: Type,
-    pub code_block: Option<Vec<Statement>>
+    pub code_block: Option<Vec<Spanned<Statement>>>
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -172,10 +234,10 @@ pub struct ContainerDeclArgs {
 #[derive(PartialEq, Debug)]
 pub enum Declaration {
     Function(FunctionDeclArgs),
-    Module(String, Vec<Declaration>),
+    Module(String, Vec<Spanned<Declaration>>),
     Container(ContainerDeclArgs),
     Import(String, String),
-    Impl(String, String, Vec<Declaration>),
+    Impl(String, String, Vec<Spanned<Declaration>>),
     StaticVar(VariableDeclArgs)
 }
 
@@ -183,30 +245,51 @@ pub enum Declaration {
 pub struct VariableDeclArgs {
     pub var_type: Type,
     pub name: String,
-    pub assignment: Box<Expression>
+    pub assignment: Box<Spanned<Expression>>
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct IfStatementArgs {
-    pub if_expr: Expression,
-    pub if_block: Vec<Statement>,
-    pub else_block: Option<Vec<Statement>>,
-    pub else_if_list: Option<Vec<(Expression, Vec<Statement>)>>
+    pub if_expr: Spanned<Expression>,
+    pub if_block: Vec<Spanned<Statement>>,
+    pub else_block: Option<Vec<Spanned<Statement>>>,
+    pub else_if_list: Option<Vec<(Spanned<Expression>, Vec<Spanned<Statement>>)>>
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct TryStatementArgs {
+    pub try_block: Vec<Spanned<Statement>>,
+    /// Name of the string variable holding the error message in the catch block
+    pub catch_var: Option<String>,
+    pub catch_block: Vec<Spanned<Statement>>
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ForStatementArgs {
+    pub var_name: String,
+    pub range_start: Spanned<Expression>,
+    pub range_end: Spanned<Expression>,
+    pub inclusive: bool,
+    pub stmt_list: Vec<Spanned<Statement>>
 }
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Statement {
     VariableDecl(VariableDeclArgs),
-    Assignment(String, Box<Expression>),
-    Call(String, Vec<Expression>),
-    Return(Option<Expression>),
-    CodeBlock(Vec<Statement>),
-    Loop(Vec<Statement>),
-    While(Box<Expression>, Vec<Statement>),
+    Assignment(String, Box<Spanned<Expression>>),
+    Call(String, Vec<Spanned<Expression>>),
+    Return(Option<Spanned<Expression>>),
+    CodeBlock(Vec<Spanned<Statement>>),
+    Loop(Vec<Spanned<Statement>>),
+    While(Box<Spanned<Expression>>, Vec<Spanned<Statement>>),
+    For(ForStatementArgs),
     Break,
     Continue,
-    Expression(Expression),
-    If(IfStatementArgs)
+    Free(Spanned<Expression>),
+    Expression(Spanned<Expression>),
+    If(IfStatementArgs),
+    Try(TryStatementArgs),
+    Throw(Spanned<Expression>)
 }
 
 #[derive(PartialEq, Debug, Clone)]
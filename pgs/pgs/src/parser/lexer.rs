@@ -52,6 +52,10 @@ pub enum Token {
     #[prio = 1]
     For,
 
+    #[token = "in"]
+    #[prio = 1]
+    In,
+
     #[token = "loop"]
     #[prio = 1]
     Loop,
@@ -160,12 +164,18 @@ pub enum Token {
     #[token = "-"]
     Minus,
 
+    /// Unary minus, the parser turns a "-" in front of an operand into this
+    Negate,
+
     #[token = "*"]
     Times,
 
     #[token = "/"]
     Divide,
 
+    #[token = "%"]
+    Percent,
+
     #[token = "=="]
     Equals,
 
@@ -190,16 +200,54 @@ pub enum Token {
     #[token = "&"]
     And,
 
+    /// Binary and, the parser turns a "&" after an operand into this
+    BitAnd,
+
+    #[token = "|"]
+    Pipe,
+
+    #[token = "^"]
+    Caret,
+
+    #[token = "<<"]
+    ShiftLeft,
+
+    #[token = ">>"]
+    ShiftRight,
+
     #[token = "."]
     Dot,
 
     #[token = ".."]
     DoubleDot,
 
+    #[token = "..="]
+    DoubleDotEquals,
+
     #[token = "return"]
     #[prio = 1]
     Return,
 
+    #[token = "new"]
+    #[prio = 1]
+    New,
+
+    #[token = "free"]
+    #[prio = 1]
+    Free,
+
+    #[token = "try"]
+    #[prio = 1]
+    Try,
+
+    #[token = "catch"]
+    #[prio = 1]
+    Catch,
+
+    #[token = "throw"]
+    #[prio = 1]
+    Throw,
+
     #[end]
     End,
 
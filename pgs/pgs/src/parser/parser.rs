@@ -49,6 +49,9 @@ pub enum ParseErrorType {
     UnknownStatement,
     ExpectedVarName,
     ExpectedWhile,
+    ExpectedFor,
+    ExpectedIn,
+    ExpectedRange,
     ExpectedAssignment,
     ExpectedSemicolon,
     UnsupportedExpression,
@@ -62,6 +65,7 @@ pub enum ParseErrorType {
     ExpectedMemberName,
     ExpectedContainerName,
     ExpectedArraySize,
+    ExpectedOpenBracket,
     ExpectedCloseBracket,
     InvalidTypename(String),
     InvalidTokenInTypename(Token),
@@ -74,7 +78,11 @@ pub enum ParseErrorType {
     ExpectedImplType,
     ExpectedThis,
     ThisOnlyAllowedInImpls,
-    MalformedImport
+    MalformedImport,
+    ExpectedOperand,
+    ExpectedTry,
+    ExpectedCatch,
+    ExpectedThrow
 }
 
 #[derive(Debug)]
@@ -94,7 +102,7 @@ impl ParseError {
 
 impl Display for ParseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        write!(f, "{:?}", self)
+        write!(f, "{:?}", self.error_type)
     }
 }
 
@@ -108,15 +116,42 @@ macro_rules! make_parse_error {
 
 pub type ParseResult<T> = Result<T, ParseError>;
 
+/// Tokens that start a declaration, error recovery stops in front of them
+const DECL_START_TOKENS: &[Token] = &[
+    Token::Fn,
+    Token::Container,
+    Token::Import,
+    Token::Mod,
+    Token::Impl,
+    Token::Var
+];
+
+/// Tokens that start a statement, error recovery stops in front of them
+const STMT_START_TOKENS: &[Token] = &[
+    Token::Var,
+    Token::Return,
+    Token::If,
+    Token::Continue,
+    Token::Break,
+    Token::While,
+    Token::Loop,
+    Token::For,
+    Token::Free,
+    Token::Try,
+    Token::Throw
+];
+
 pub struct Parser {
     code: String,
-    current_cont: RefCell<String>
+    current_cont: RefCell<String>,
+    errors: RefCell<Vec<ParseError>>
 }
 
 fn is_op(token: &Token) -> bool {
     match token {
         Token::Times => true,
         Token::Divide => true,
+        Token::Percent => true,
         Token::Plus => true,
         Token::Minus => true,
         Token::Equals => true,
@@ -126,8 +161,10 @@ fn is_op(token: &Token) -> bool {
         Token::LessThan => true,
         Token::LessThanEquals => true,
         Token::Not => true,
+        Token::Negate => true,
         Token::Tilde => true,
         Token::And => true,
+        Token::New => true,
         Token::Dot => true,
         Token::Assign => true,
         Token::AddAssign => true,
@@ -137,26 +174,39 @@ fn is_op(token: &Token) -> bool {
         Token::DoubleDot => true,
         Token::Or => true,
         Token::DoubleAnd => true,
+        Token::BitAnd => true,
+        Token::Pipe => true,
+        Token::Caret => true,
+        Token::ShiftLeft => true,
+        Token::ShiftRight => true,
         _ => false
     }
 }
 
 fn op_prec(token: &Token) -> i8 {
     match token {
-        Token::Times => 3,
-        Token::Divide => 3,
-        Token::Plus => 2,
-        Token::Minus => 2,
+        Token::Times => 7,
+        Token::Divide => 7,
+        Token::Percent => 7,
+        Token::Plus => 6,
+        Token::Minus => 6,
+        Token::ShiftLeft => 5,
+        Token::ShiftRight => 5,
+        Token::BitAnd => 4,
+        Token::Caret => 3,
+        Token::Pipe => 2,
         Token::Equals => 1,
         Token::NotEquals => 1,
         Token::GreaterThan => 1,
         Token::GreaterThanEquals => 1,
         Token::LessThan => 1,
         Token::LessThanEquals => 1,
-        Token::Not => 4,
-        Token::And => 2,
-        Token::Tilde => 2,
-        Token::Dot => 5,
+        Token::Not => 8,
+        Token::Negate => 8,
+        Token::And => 6,
+        Token::Tilde => 6,
+        Token::New => 6,
+        Token::Dot => 9,
         Token::Assign => 0,
         Token::AddAssign => 0,
         Token::MulAssign => 0,
@@ -173,8 +223,9 @@ fn op_prec(token: &Token) -> i8 {
 
 fn is_op_right_assoc(token: &Token) -> bool {
     match token {
-        Token::Times => true,
+        Token::Times => false,
         Token::Divide => false,
+        Token::Percent => false,
         Token::Plus => false,
         Token::Minus => false,
         Token::Equals => false,
@@ -184,8 +235,10 @@ fn is_op_right_assoc(token: &Token) -> bool {
         Token::LessThan => false,
         Token::LessThanEquals => false,
         Token::Not => true,
+        Token::Negate => true,
         Token::Tilde => true,
         Token::And => true,
+        Token::New => true,
         Token::Dot => true,
         Token::Assign => true,
         Token::AddAssign => true,
@@ -195,6 +248,11 @@ fn is_op_right_assoc(token: &Token) -> bool {
         Token::DoubleDot => false,
         Token::Or => false,
         Token::DoubleAnd => false,
+        Token::BitAnd => false,
+        Token::Pipe => false,
+        Token::Caret => false,
+        Token::ShiftLeft => false,
+        Token::ShiftRight => false,
         _ => {
             panic!("ERROR! Not an operator");
         }
@@ -205,35 +263,104 @@ impl Parser {
     pub fn new(code: String) -> Self {
         Parser {
             code: code,
-            current_cont: RefCell::new(String::new())
+            current_cont: RefCell::new(String::new()),
+            errors: RefCell::new(Vec::new())
         }
     }
 
-    pub fn parse_decl_list(&self, lexer: &mut Lexer, delims: &[Token]) -> ParseResult<Vec<Declaration>> {
+    /// Takes the errors the declaration and statement lists recovered from
+    pub fn take_errors(&self) -> Vec<ParseError> {
+        self.errors.replace(Vec::new())
+    }
+
+    /// Records an error and skips to the end of the current declaration or statement.
+    /// Parsing resumes after the next ";" or balanced "}", in front of a "}" closing the current block
+    /// or in front of one of the stop tokens. Blocks opened before the error are balanced as well.
+    fn recover(&self, lexer: &mut Lexer, err: ParseError, start: usize, stop_tokens: &[Token]) {
+        self.errors.borrow_mut().push(err);
+
+        // The failed parser might have rewound the lexer, the first token is always skipped to make progress.
+        // It can't end the list, otherwise the list would not have been parsed any further.
+        let mut moved = lexer.range().start > start;
+        // Blocks the failed parser opened but didn't close yet, e.g. the body of a broken container
+        let mut depth = 0;
+        if moved {
+            let mut consumed = Token::lexer(&self.code[start..lexer.range().start]);
+            while consumed.token != Token::End && consumed.token != Token::Error {
+                match consumed.token {
+                    Token::OpenBlock => depth += 1,
+                    Token::CloseBlock => depth -= 1,
+                    _ => {}
+                };
+                consumed.advance();
+            }
+            depth = depth.max(0);
+        }
+        loop {
+            match lexer.token {
+                Token::End | Token::Error => return,
+                Token::Semicolon if depth == 0 => {
+                    lexer.advance();
+                    return;
+                },
+                Token::OpenBlock => depth += 1,
+                Token::CloseBlock => {
+                    if depth == 0 && moved {
+                        return;
+                    }
+                    depth -= 1;
+                    if depth <= 0 {
+                        lexer.advance();
+                        return;
+                    }
+                },
+                _ => {
+                    if depth == 0 && moved && stop_tokens.contains(&lexer.token) {
+                        return;
+                    }
+                }
+            };
+            lexer.advance();
+            moved = true;
+        }
+    }
+
+    /// Returns the span from the given start offset to the end of the last consumed token
+    fn span_from(&self, start: usize, lexer: &Lexer) -> Span {
+        let end = lexer.range().start.max(start);
+        let consumed = &self.code[start..end];
+        start..start + consumed.trim_end().len()
+    }
+
+    pub fn parse_decl_list(&self, lexer: &mut Lexer, delims: &[Token]) -> ParseResult<Vec<Spanned<Declaration>>> {
         let mut ret = Vec::new();
         
         while !delims.contains(&lexer.token) &&
             lexer.token != Token::End &&
             lexer.token != Token::Error {
-            match lexer.token {
-                Token::Fn => {
-                    ret.push(self.parse_fn_decl(lexer)?);
-                },
-                Token::Container => {
-                    ret.push(self.parse_container_decl(lexer)?);
-                },
-                Token::Import => {
-                    let mut import_decls = self.parse_import_decl(lexer)?;
-                    ret.append(&mut import_decls);
-                },
-                Token::Mod => {
-                    ret.push(self.parse_mod_decl(lexer)?);
-                },
-                Token::Impl => {
-                    ret.push(self.parse_impl_decl(lexer)?);
+            let start = lexer.range().start;
+            let decls_res = match lexer.token {
+                Token::Fn => self.parse_fn_decl(lexer).map(|decl| vec![decl]),
+                Token::Container => self.parse_container_decl(lexer).map(|decl| vec![decl]),
+                Token::Import => self.parse_import_decl(lexer),
+                Token::Mod => self.parse_mod_decl(lexer).map(|decl| vec![decl]),
+                Token::Impl => self.parse_impl_decl(lexer).map(|decl| vec![decl]),
+                Token::Var => self.parse_static_var_decl(lexer).map(|decl| vec![decl]),
+                _ => make_parse_error!(lexer, ParseErrorType::ExpectedMod)
+            };
+            match decls_res {
+                Ok(decls) => {
+                    let span = self.span_from(start, lexer);
+                    for decl in decls {
+                        ret.push(Spanned::new(decl, span.clone()));
+                    }
                 },
-                _ => {
-                    return Err(ParseError::new(ParseErrorType::ExpectedMod, lexer.range()));
+                Err(err) => {
+                    let stop_tokens: Vec<Token> = DECL_START_TOKENS.iter()
+                        .chain(delims.iter())
+                        .cloned()
+                        .collect();
+                    self.recover(lexer, err, start, &stop_tokens);
                 }
             };
         }
@@ -291,9 +418,51 @@ impl Parser {
         )
     }
 
-    pub fn parse_root_decl_list(&self) -> ParseResult<Vec<Declaration>> {
+    /// Parses the whole code, returning the first error if there are any
+    pub fn parse_root_decl_list(&self) -> ParseResult<Vec<Spanned<Declaration>>> {
+        self.parse_root()
+            .map_err(|mut errors| errors.remove(0))
+    }
+
+    /// Parses the whole code, returning every error found in it
+    pub fn parse_root(&self) -> Result<Vec<Spanned<Declaration>>, Vec<ParseError>> {
         let mut lexer = Token::lexer(self.code.as_str());
-        self.parse_decl_list(&mut lexer, &[])
+        self.take_errors();
+        let decl_list_res = self.parse_decl_list(&mut lexer, &[]);
+        let mut errors = self.take_errors();
+        match decl_list_res {
+            Ok(decl_list) if errors.is_empty() => Ok(decl_list),
+            Ok(_) => Err(errors),
+            Err(err) => {
+                errors.push(err);
+                Err(errors)
+            }
+        }
+    }
+
+    /// Returns true if the code starts with a declaration, otherwise it is parsed as a statement list
+    pub fn is_decl_list(&self) -> bool {
+        let lexer = Token::lexer(self.code.as_str());
+        DECL_START_TOKENS.contains(&lexer.token) && lexer.token != Token::Var
+    }
+
+    /// Parses the whole code as a statement list, returning every error found in it
+    pub fn parse_root_stmt_list(&self) -> Result<Vec<Spanned<Statement>>, Vec<ParseError>> {
+        let mut lexer = Token::lexer(self.code.as_str());
+        self.take_errors();
+        let stmt_list_res = self.parse_statement_list(&mut lexer);
+        let mut errors = self.take_errors();
+        if lexer.token != Token::End && errors.is_empty() {
+            errors.push(ParseError::new(ParseErrorType::UnknownStatement, lexer.range()));
+        }
+        match stmt_list_res {
+            Ok(stmt_list) if errors.is_empty() => Ok(stmt_list),
+            Ok(_) => Err(errors),
+            Err(err) => {
+                errors.push(err);
+                Err(errors)
+            }
+        }
     }
 
     pub fn parse_mod_decl(&self, lexer: &mut Lexer) -> ParseResult<Declaration> {
@@ -863,6 +1032,77 @@ impl Parser {
         )
     }
 
+    pub fn parse_for(&self, lexer: &mut Lexer) -> ParseResult<Statement> {
+        if lexer.token != Token::For {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedFor);
+        }
+
+        // Swallow "for"
+        lexer.advance();
+
+        if lexer.token != Token::Text {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedVarName);
+        }
+
+        let var_name = String::from(lexer.slice());
+
+        // Swallow var name
+        lexer.advance();
+
+        if lexer.token != Token::In {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedIn);
+        }
+
+        // Swallow "in"
+        lexer.advance();
+
+        let range_start = self.parse_expr(lexer, &[
+            Token::DoubleDot,
+            Token::DoubleDotEquals
+        ])?;
+
+        let inclusive = match lexer.token {
+            Token::DoubleDot => false,
+            Token::DoubleDotEquals => true,
+            _ => return make_parse_error!(lexer, ParseErrorType::ExpectedRange)
+        };
+
+        // Swallow ".." or "..="
+        lexer.advance();
+
+        let range_end = self.parse_expr(lexer, &[
+            Token::OpenBlock
+        ])?;
+
+        if lexer.token != Token::OpenBlock {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedOpenBlock);
+        }
+
+        // Swallow "{"
+        lexer.advance();
+
+        let stmt_list = self.parse_statement_list(lexer)?;
+
+        if lexer.token != Token::CloseBlock {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedCloseBlock);
+        }
+
+        // Swallow "}"
+        lexer.advance();
+
+        let for_stmt_args = ForStatementArgs {
+            var_name: var_name,
+            range_start: range_start,
+            range_end: range_end,
+            inclusive: inclusive,
+            stmt_list: stmt_list
+        };
+
+        Ok(
+            Statement::For(for_stmt_args)
+        )
+    }
+
     pub fn parse_if(&self, lexer: &mut Lexer) -> ParseResult<Statement> {
         if lexer.token != Token::If {
             return Err(ParseError::new(ParseErrorType::ExpectedIf, lexer.range()));
@@ -953,42 +1193,38 @@ impl Parser {
         )
     }
 
-    pub fn parse_statement_list(&self, lexer: &mut Lexer) -> ParseResult<Vec<Statement>> {
+    pub fn parse_statement_list(&self, lexer: &mut Lexer) -> ParseResult<Vec<Spanned<Statement>>> {
         let mut ret = Vec::new();
 
         while lexer.token != Token::CloseBlock &&
             lexer.token != Token::End &&
             lexer.token != Token::Error {
-            match lexer.token {
-                Token::Var => {
-                    ret.push(self.parse_var_decl(lexer)?);
-                },
-                Token::Return => {
-                    ret.push(self.parse_return(lexer)?);
-                },
-                Token::If => {
-                    ret.push(self.parse_if(lexer)?);
-                },
-                Token::Continue => {
-                    ret.push(self.parse_continue(lexer)?);
-                },
-                Token::Break => {
-                    ret.push(self.parse_break(lexer)?);
-                },
-                Token::While => {
-                    ret.push(self.parse_while(lexer)?);
-                },
-                Token::Loop => {
-                    ret.push(self.parse_loop(lexer)?);
-                },
+            let start = lexer.range().start;
+            let stmt_res = match lexer.token {
+                Token::Var => self.parse_var_decl(lexer),
+                Token::Return => self.parse_return(lexer),
+                Token::If => self.parse_if(lexer),
+                Token::Continue => self.parse_continue(lexer),
+                Token::Break => self.parse_break(lexer),
+                Token::While => self.parse_while(lexer),
+                Token::Loop => self.parse_loop(lexer),
+                Token::For => self.parse_for(lexer),
+                Token::Free => self.parse_free(lexer),
+                Token::Try => self.parse_try(lexer),
+                Token::Throw => self.parse_throw(lexer),
                 _ => {
-                    let expr = self.parse_expr(lexer, &[Token::Semicolon])?;
-                    // Swallow ";"
-                    lexer.advance();
-                    ret.push(Statement::Expression(expr));
+                    self.parse_expr(lexer, &[Token::Semicolon])
+                        .map(|expr| {
+                            // Swallow ";"
+                            lexer.advance();
+                            Statement::Expression(expr)
+                        })
                 }
             };
-            
+            match stmt_res {
+                Ok(stmt) => ret.push(Spanned::new(stmt, self.span_from(start, lexer))),
+                Err(err) => self.recover(lexer, err, start, STMT_START_TOKENS)
+            };
         }
 
         Ok(ret)
@@ -1118,6 +1354,115 @@ impl Parser {
         )
     }
 
+    pub fn parse_free(&self, lexer: &mut Lexer) -> ParseResult<Statement> {
+        if lexer.token != Token::Free {
+            return make_parse_error!(lexer, ParseErrorType::UnknownStatement);
+        }
+
+        // Swallow "free"
+        lexer.advance();
+
+        let free_expr = self.parse_expr(lexer, &[Token::Semicolon])?;
+
+        if lexer.token != Token::Semicolon {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedSemicolon);
+        }
+
+        // Swallow ";"
+        lexer.advance();
+
+        Ok(
+            Statement::Free(free_expr)
+        )
+    }
+
+    /// Parses `try { ... } catch err { ... }`, the name of the error variable is optional
+    pub fn parse_try(&self, lexer: &mut Lexer) -> ParseResult<Statement> {
+        if lexer.token != Token::Try {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedTry);
+        }
+
+        // Swallow "try"
+        lexer.advance();
+
+        if lexer.token != Token::OpenBlock {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedOpenBlock);
+        }
+
+        // Swallow "{"
+        lexer.advance();
+
+        let try_block = self.parse_statement_list(lexer)?;
+
+        if lexer.token != Token::CloseBlock {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedCloseBlock);
+        }
+
+        // Swallow "}"
+        lexer.advance();
+
+        if lexer.token != Token::Catch {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedCatch);
+        }
+
+        // Swallow "catch"
+        lexer.advance();
+
+        let mut catch_var = None;
+        if lexer.token == Token::Text {
+            catch_var = Some(String::from(lexer.slice()));
+            lexer.advance();
+        }
+
+        if lexer.token != Token::OpenBlock {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedOpenBlock);
+        }
+
+        // Swallow "{"
+        lexer.advance();
+
+        let catch_block = self.parse_statement_list(lexer)?;
+
+        if lexer.token != Token::CloseBlock {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedCloseBlock);
+        }
+
+        // Swallow "}"
+        lexer.advance();
+
+        let try_stmt_args = TryStatementArgs {
+            try_block,
+            catch_var,
+            catch_block
+        };
+
+        Ok(
+            Statement::Try(try_stmt_args)
+        )
+    }
+
+    pub fn parse_throw(&self, lexer: &mut Lexer) -> ParseResult<Statement> {
+        if lexer.token != Token::Throw {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedThrow);
+        }
+
+        // Swallow "throw"
+        lexer.advance();
+
+        let throw_expr = self.parse_expr(lexer, &[Token::Semicolon])?;
+
+        if lexer.token != Token::Semicolon {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedSemicolon);
+        }
+
+        // Swallow ";"
+        lexer.advance();
+
+        Ok(
+            Statement::Throw(throw_expr)
+        )
+    }
+
     pub fn parse_return(&self, lexer: &mut Lexer) -> ParseResult<Statement> {
         // Swallow "return"
         lexer.advance();
@@ -1132,7 +1477,21 @@ impl Parser {
         )
     }
 
+    pub fn parse_static_var_decl(&self, lexer: &mut Lexer) -> ParseResult<Declaration> {
+        let var_decl_args = self.parse_var_decl_args(lexer)?;
+        Ok(
+            Declaration::StaticVar(var_decl_args)
+        )
+    }
+
     pub fn parse_var_decl(&self, lexer: &mut Lexer) -> ParseResult<Statement> {
+        let var_decl_args = self.parse_var_decl_args(lexer)?;
+        Ok(
+            Statement::VariableDecl(var_decl_args)
+        )
+    }
+
+    pub fn parse_var_decl_args(&self, lexer: &mut Lexer) -> ParseResult<VariableDeclArgs> {
         let mut lexer_backup = lexer.clone();
 
         // Swallow "var"
@@ -1177,9 +1536,7 @@ impl Parser {
 
         lexer.advance();
 
-        Ok(
-            Statement::VariableDecl(var_decl_args)
-        )
+        Ok(var_decl_args)
     }
 
     pub fn parse_var_assign(&self, lexer: &mut Lexer) -> ParseResult<Statement> {
@@ -1250,121 +1607,76 @@ impl Parser {
         )
     }
 
-    pub fn parse_expr_push(&self, lexer: &mut Lexer, operand_stack: &mut VecDeque<Expression>, operator_stack: &mut VecDeque<Token>) -> ParseResult<Expression> {
-        //println!("parse_expr_push(): operator stack len {}", operator_stack.len());
-        //println!("parse_expr_push(): operand stack len {}", operand_stack.len());
-        let op = operator_stack.pop_front().unwrap();
-        //println!("parse_expr_push(): operator {:?}", op);
-        //println!("parse_expr_push() start");
+    /// Pops the operand of an operator, an operator without operands is an error
+    fn pop_operand(&self, lexer: &Lexer, operand_stack: &mut VecDeque<Spanned<Expression>>) -> ParseResult<Spanned<Expression>> {
+        operand_stack.pop_front()
+            .ok_or_else(|| ParseError::new(ParseErrorType::ExpectedOperand, lexer.range()))
+    }
+
+    /// Pops the operands of a binary operator, the expression spans both of them
+    fn pop_binary(&self, lexer: &Lexer, operand_stack: &mut VecDeque<Spanned<Expression>>,
+        make_expr: fn(Box<Spanned<Expression>>, Box<Spanned<Expression>>) -> Expression) -> ParseResult<Spanned<Expression>> {
+        let rhs = self.pop_operand(lexer, operand_stack)?;
+        let lhs = self.pop_operand(lexer, operand_stack)?;
+        let span = lhs.span.start..rhs.span.end;
+        Ok(Spanned::new(make_expr(Box::new(lhs), Box::new(rhs)), span))
+    }
+
+    /// Pops the operand of a prefix operator, the expression spans the operator and its operand
+    fn pop_prefix(&self, lexer: &Lexer, operand_stack: &mut VecDeque<Spanned<Expression>>, op_span: Span,
+        make_expr: fn(Box<Spanned<Expression>>) -> Expression) -> ParseResult<Spanned<Expression>> {
+        let op = self.pop_operand(lexer, operand_stack)?;
+        let span = op_span.start..op.span.end;
+        Ok(Spanned::new(make_expr(Box::new(op)), span))
+    }
+
+    pub fn parse_expr_push(&self, lexer: &mut Lexer, operand_stack: &mut VecDeque<Spanned<Expression>>, operator_stack: &mut VecDeque<(Token, Span)>) -> ParseResult<Spanned<Expression>> {
+        let (op, op_span) = operator_stack.pop_front().unwrap();
         let expr = match op {
-            Token::Plus => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::Addition(Box::new(lhs), Box::new(rhs))
-            },
-            Token::Minus => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::Subtraction(Box::new(lhs), Box::new(rhs))
-            },
-            Token::Times => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::Multiplication(Box::new(lhs), Box::new(rhs))
-            },
-            Token::Divide => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::Division(Box::new(lhs), Box::new(rhs))
-            },
-            Token::Equals => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::Equals(Box::new(lhs), Box::new(rhs))
-            },
-            Token::NotEquals => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::NotEquals(Box::new(lhs), Box::new(rhs))
-            },
-            Token::GreaterThan => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::GreaterThan(Box::new(lhs), Box::new(rhs))
-            },
-            Token::GreaterThanEquals => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::GreaterThanEquals(Box::new(lhs), Box::new(rhs))
-            },
-            Token::LessThan => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::LessThan(Box::new(lhs), Box::new(rhs))
-            },
-            Token::LessThanEquals => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::LessThanEquals(Box::new(lhs), Box::new(rhs))
-            },
-            Token::Not => {
-                let op = operand_stack.pop_front().unwrap();
-                Expression::Not(Box::new(op))
-            },
-            Token::Tilde => {
-                let op = operand_stack.pop_front().unwrap();
-                Expression::Deref(Box::new(op))
-            },
-            Token::And => {
-                let op = operand_stack.pop_front().unwrap();
-                Expression::Ref(Box::new(op))
-            },
-            Token::Dot => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::MemberAccess(Box::new(lhs), Box::new(rhs))
-            },
-            Token::Assign => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::Assign(Box::new(lhs), Box::new(rhs))
-            },
-            Token::AddAssign => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::AddAssign(Box::new(lhs), Box::new(rhs))
-            },
-            Token::SubAssign => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::SubAssign(Box::new(lhs), Box::new(rhs))
-            },
-            Token::MulAssign => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::MulAssign(Box::new(lhs), Box::new(rhs))
-            },
-            Token::DivAssign => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::DivAssign(Box::new(lhs), Box::new(rhs))
-            },
-            Token::DoubleAnd => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::And(Box::new(lhs), Box::new(rhs))
-            },
-            Token::Or => {
-                let rhs = operand_stack.pop_front().unwrap();
-                let lhs = operand_stack.pop_front().unwrap();
-                Expression::Or(Box::new(lhs), Box::new(rhs))
+            Token::Plus => self.pop_binary(lexer, operand_stack, Expression::Addition)?,
+            Token::Minus => self.pop_binary(lexer, operand_stack, Expression::Subtraction)?,
+            Token::Times => self.pop_binary(lexer, operand_stack, Expression::Multiplication)?,
+            Token::Divide => self.pop_binary(lexer, operand_stack, Expression::Division)?,
+            Token::Percent => self.pop_binary(lexer, operand_stack, Expression::Modulo)?,
+            Token::BitAnd => self.pop_binary(lexer, operand_stack, Expression::BitAnd)?,
+            Token::Pipe => self.pop_binary(lexer, operand_stack, Expression::BitOr)?,
+            Token::Caret => self.pop_binary(lexer, operand_stack, Expression::BitXor)?,
+            Token::ShiftLeft => self.pop_binary(lexer, operand_stack, Expression::ShiftLeft)?,
+            Token::ShiftRight => self.pop_binary(lexer, operand_stack, Expression::ShiftRight)?,
+            Token::Equals => self.pop_binary(lexer, operand_stack, Expression::Equals)?,
+            Token::NotEquals => self.pop_binary(lexer, operand_stack, Expression::NotEquals)?,
+            Token::GreaterThan => self.pop_binary(lexer, operand_stack, Expression::GreaterThan)?,
+            Token::GreaterThanEquals => self.pop_binary(lexer, operand_stack, Expression::GreaterThanEquals)?,
+            Token::LessThan => self.pop_binary(lexer, operand_stack, Expression::LessThan)?,
+            Token::LessThanEquals => self.pop_binary(lexer, operand_stack, Expression::LessThanEquals)?,
+            Token::Not => self.pop_prefix(lexer, operand_stack, op_span, Expression::Not)?,
+            Token::Negate => {
+                let Spanned { node, span } = self.pop_operand(lexer, operand_stack)?;
+                let expr_span = op_span.start..span.end;
+                // Fold negative number literals
+                let expr = match node {
+                    Expression::IntLiteral(int) => Expression::IntLiteral(-int),
+                    Expression::FloatLiteral(float) => Expression::FloatLiteral(-float),
+                    _ => Expression::Negate(Box::new(Spanned::new(node, span)))
+                };
+                Spanned::new(expr, expr_span)
             },
+            Token::Tilde => self.pop_prefix(lexer, operand_stack, op_span, Expression::Deref)?,
+            Token::And => self.pop_prefix(lexer, operand_stack, op_span, Expression::Ref)?,
+            Token::New => self.pop_prefix(lexer, operand_stack, op_span, Expression::New)?,
+            Token::Dot => self.pop_binary(lexer, operand_stack, Expression::MemberAccess)?,
+            Token::Assign => self.pop_binary(lexer, operand_stack, Expression::Assign)?,
+            Token::AddAssign => self.pop_binary(lexer, operand_stack, Expression::AddAssign)?,
+            Token::SubAssign => self.pop_binary(lexer, operand_stack, Expression::SubAssign)?,
+            Token::MulAssign => self.pop_binary(lexer, operand_stack, Expression::MulAssign)?,
+            Token::DivAssign => self.pop_binary(lexer, operand_stack, Expression::DivAssign)?,
+            Token::DoubleAnd => self.pop_binary(lexer, operand_stack, Expression::And)?,
+            Token::Or => self.pop_binary(lexer, operand_stack, Expression::Or)?,
             _ => {
                 return Err(ParseError::new(ParseErrorType::UnsupportedExpression, lexer.range()));
             }
         };
 
-        //println!("parse_expr_push() end");
         Ok(expr)
     }
 
@@ -1416,7 +1728,7 @@ impl Parser {
         )
     }
 
-    fn parse_cont_instance_content(&self, lexer: &mut Lexer) -> ParseResult<HashMap<String, Expression>> {
+    fn parse_cont_instance_content(&self, lexer: &mut Lexer) -> ParseResult<HashMap<String, Spanned<Expression>>> {
         let delims = &[
             Token::CloseBlock,
             Token::End,
@@ -1493,7 +1805,84 @@ impl Parser {
         )
     }
 
-    pub fn parse_expr(&self, lexer: &mut Lexer, delims: &[Token]) -> ParseResult<Expression> {
+    pub fn parse_index_expr(&self, lexer: &mut Lexer, indexed_expr: Spanned<Expression>) -> ParseResult<Spanned<Expression>> {
+        let start = indexed_expr.span.start;
+        let mut expr = indexed_expr;
+
+        while lexer.token == Token::OpenBracket {
+            // Swallow "["
+            lexer.advance();
+
+            let index_expr = self.parse_expr(lexer, &[
+                Token::CloseBracket,
+                Token::DoubleDot
+            ])?;
+
+            // Slices have the form [start..end]
+            let slice_end_expr = if lexer.token == Token::DoubleDot {
+                // Swallow ".."
+                lexer.advance();
+
+                Some(self.parse_expr(lexer, &[
+                    Token::CloseBracket
+                ])?)
+            } else {
+                None
+            };
+
+            if lexer.token != Token::CloseBracket {
+                return make_parse_error!(lexer, ParseErrorType::ExpectedCloseBracket);
+            }
+
+            // Swallow "]"
+            lexer.advance();
+
+            let node = match slice_end_expr {
+                Some(end_expr) => Expression::Slice(Box::new(expr), Box::new(index_expr), Box::new(end_expr)),
+                None => Expression::Index(Box::new(expr), Box::new(index_expr))
+            };
+            expr = Spanned::new(node, self.span_from(start, lexer));
+        }
+
+        Ok(expr)
+    }
+
+    pub fn parse_array_literal(&self, lexer: &mut Lexer) -> ParseResult<Expression> {
+        if lexer.token != Token::OpenBracket {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedOpenBracket);
+        }
+
+        // Swallow "["
+        lexer.advance();
+
+        let mut elements = Vec::new();
+
+        while lexer.token != Token::CloseBracket &&
+            lexer.token != Token::End &&
+            lexer.token != Token::Error {
+            let element = self.parse_expr(lexer, &[
+                Token::Comma,
+                Token::CloseBracket
+            ])?;
+            if lexer.token == Token::Comma {
+                lexer.advance(); // Swallow "," if its there
+            }
+            elements.push(element);
+        }
+
+        if lexer.token != Token::CloseBracket {
+            return make_parse_error!(lexer, ParseErrorType::ExpectedCloseBracket);
+        }
+
+        // Swallow "]"
+        lexer.advance();
+
+        Ok(
+            Expression::ArrayLiteral(elements)
+        )
+    }
+
+    pub fn parse_expr(&self, lexer: &mut Lexer, delims: &[Token]) -> ParseResult<Spanned<Expression>> {
         let mut operator_stack = VecDeque::new();
         let mut operand_stack = VecDeque::new();
 
@@ -1501,6 +1890,9 @@ impl Parser {
         let mut open_paran_count = 0;
         let mut dec_paran_count = false;
 
+        // Whether the next token has to be an operand or a prefix operator
+        let mut expect_operand = true;
+
         while lexer.token != Token::End &&
             lexer.token != Token::Error {
 
@@ -1516,84 +1908,138 @@ impl Parser {
 
             if lexer.token == Token::True {
                 let expr = Expression::BoolLiteral(true);
-                operand_stack.push_front(expr);
+                operand_stack.push_front(Spanned::new(expr, lexer.range()));
+                expect_operand = false;
             }
 
             if lexer.token == Token::False {
                 let expr = Expression::BoolLiteral(false);
-                operand_stack.push_front(expr);
+                operand_stack.push_front(Spanned::new(expr, lexer.range()));
+                expect_operand = false;
             }
             
             if lexer.token == Token::Text {
-                let expr;
+                let start = lexer.range().start;
+                let mut expr;
                 let call_expr_res = self.try_parse_call_expr(lexer);
                 if call_expr_res.is_ok() {
-                    expr = call_expr_res.unwrap();
+                    expr = Spanned::new(call_expr_res.unwrap(), self.span_from(start, lexer));
                 } else {
                     let cont_inst_expr_res = self.try_parse_cont_instance(lexer);
                     if cont_inst_expr_res.is_ok() {
-                        expr = cont_inst_expr_res.unwrap();
+                        expr = Spanned::new(cont_inst_expr_res.unwrap(), self.span_from(start, lexer));
                     } else {
                         let mut var_name = String::from(lexer.slice());
-                        expr = Expression::Variable(var_name);
+                        // Static variables can be addressed by their module path
+                        let mut peek_lexer = lexer.clone();
+                        peek_lexer.advance();
+                        while peek_lexer.token == Token::DoubleColon {
+                            // Swallow "::"
+                            peek_lexer.advance();
+                            if peek_lexer.token != Token::Text {
+                                return make_parse_error!(peek_lexer, ParseErrorType::ExpectedVarName);
+                            }
+                            var_name += "::";
+                            var_name += peek_lexer.slice();
+                            *lexer = peek_lexer.clone();
+                            peek_lexer.advance();
+                        }
+                        expr = Spanned::new(Expression::Variable(var_name), start..lexer.range().end);
+                        // Only swallow the variable name if it is indexed
+                        let mut peek_lexer = lexer.clone();
+                        peek_lexer.advance();
+                        if peek_lexer.token == Token::OpenBracket {
+                            *lexer = peek_lexer;
+                        }
                     }
                 }
+                if lexer.token == Token::OpenBracket {
+                    expr = self.parse_index_expr(lexer, expr)?;
+                }
                 operand_stack.push_front(expr);
+                expect_operand = false;
+            }
+
+            if lexer.token == Token::OpenBracket {
+                let start = lexer.range().start;
+                let expr = self.parse_array_literal(lexer)?;
+                let mut expr = Spanned::new(expr, self.span_from(start, lexer));
+                if lexer.token == Token::OpenBracket {
+                    expr = self.parse_index_expr(lexer, expr)?;
+                }
+                operand_stack.push_front(expr);
+                expect_operand = false;
             }
 
             if lexer.token == Token::IntLiteral {
                 let int = String::from(lexer.slice()).parse::<i64>()
                     .map_err(|_| ParseError::new(ParseErrorType::Unknown, lexer.range()))?;
                 let expr = Expression::IntLiteral(int);
-                operand_stack.push_front(expr);
+                operand_stack.push_front(Spanned::new(expr, lexer.range()));
+                expect_operand = false;
             }
 
             if lexer.token == Token::FloatLiteral {
                 let float = String::from(lexer.slice()).parse::<f32>()
                     .map_err(|_| ParseError::new(ParseErrorType::Unknown, lexer.range()))?;
                 let expr = Expression::FloatLiteral(float);
-                operand_stack.push_front(expr);
+                operand_stack.push_front(Spanned::new(expr, lexer.range()));
+                expect_operand = false;
             }
 
             if lexer.token == Token::StringLiteral {
                 let string = String::from(lexer.slice());
                 //println!("Parsing string literal {}", string);
                 let expr = Expression::StringLiteral(string);
-                operand_stack.push_front(expr);
+                operand_stack.push_front(Spanned::new(expr, lexer.range()));
+                expect_operand = false;
             }
 
             if is_op(&lexer.token) {
-                loop {
+                // A "-" in front of an operand is a unary minus,
+                // a "&" after an operand is a binary and
+                let op_token = if expect_operand && lexer.token == Token::Minus {
+                    Token::Negate
+                } else if !expect_operand && lexer.token == Token::And {
+                    Token::BitAnd
+                } else {
+                    lexer.token.clone()
+                };
+
+                // Prefix operators have no left hand operand to reduce
+                while !expect_operand {
                     let op_opt = operator_stack.get(0);
                     if op_opt.is_none() {
                         break; // Break if operator stack is empty
                     }
-                    let op = op_opt.unwrap();
+                    let (op, _) = op_opt.unwrap();
                     if *op == Token::OpenParan {
                         break; // Break if operator is a "("
                     }
 
-                    if !(op_prec(&lexer.token) - op_prec(op) < 0) &&
-                        !(op_prec(&lexer.token) == op_prec(op) && !is_op_right_assoc(op)) {
+                    if !(op_prec(&op_token) - op_prec(op) < 0) &&
+                        !(op_prec(&op_token) == op_prec(op) && !is_op_right_assoc(op)) {
                         break; // Break if there is no operator of greater precedence on the stack or of equal precedence and right assoc
                     }
 
                     let expr = self.parse_expr_push(lexer, &mut operand_stack, &mut operator_stack)?;
                     operand_stack.push_front(expr);
                 }
-                operator_stack.push_front(lexer.token.clone());
+                operator_stack.push_front((op_token, lexer.range()));
+                expect_operand = true;
             }
 
             if lexer.token == Token::OpenParan {
-                operator_stack.push_front(lexer.token.clone());
+                operator_stack.push_front((lexer.token.clone(), lexer.range()));
                 open_paran_count += 1;
+                expect_operand = true;
             }
 
             if lexer.token == Token::CloseParan {
                 let mut pop = false;               
                 while operator_stack.len() > 0 {
                     {
-                        let op_ref = operator_stack.get(0).unwrap();
+                        let (op_ref, _) = operator_stack.get(0).unwrap();
                         if *op_ref == Token::OpenParan {
                             dec_paran_count = true;
                             pop = true;
@@ -1607,6 +2053,7 @@ impl Parser {
                 if pop {
                     operator_stack.pop_front();
                 }
+                expect_operand = false;
             }
 
             // If Token is delimiter
@@ -0,0 +1,285 @@
+use super::{
+    ast::{
+        Declaration,
+        Expression,
+        Spanned,
+        Statement,
+        Type
+    },
+    lexer::Token
+};
+
+use pglex::prelude::Lexable;
+
+/// Renders the token stream of the code, one `line:column token "slice"` per line
+pub fn print_tokens(code: &str) -> String {
+    let mut out = String::new();
+    let mut lexer = Token::lexer(code);
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut scanned = 0;
+    // The lexer already holds the first token
+    while lexer.token != Token::End {
+        let range = lexer.range();
+        for (i, c) in code[scanned..range.start].char_indices() {
+            if c == '\n' {
+                line += 1;
+                line_start = scanned + i + 1;
+            }
+        }
+        scanned = range.start;
+        let column = code[line_start..range.start].chars().count() + 1;
+        let location = format!("{}:{}", line, column);
+        out.push_str(&format!("{:<8} {:<18} {:?}\n", location, format!("{:?}", lexer.token), lexer.slice()));
+        if lexer.token == Token::Error {
+            break;
+        }
+        lexer.advance();
+    }
+    out
+}
+
+/// Renders a parsed declaration list as an indented tree, one node per line
+pub fn print_decl_list(decl_list: &[Spanned<Declaration>]) -> String {
+    let mut printer = AstPrinter::new();
+    for decl in decl_list.iter() {
+        printer.print_decl(decl);
+    }
+    printer.out
+}
+
+/// Renders a type the way it is written in scripts, e.g. `[int; 4]` or `&Point`
+pub fn type_name(var_type: &Type) -> String {
+    match var_type {
+        Type::Void => String::from("void"),
+        Type::Int => String::from("int"),
+        Type::String => String::from("string"),
+        Type::Float => String::from("float"),
+        Type::Bool => String::from("bool"),
+        Type::Auto => String::from("auto"),
+        Type::Array(inner_type, size) => format!("[{}; {}]", type_name(inner_type), size),
+        Type::AutoArray(inner_type) => format!("[{}]", type_name(inner_type)),
+        Type::Other(name) => name.clone(),
+        Type::Tuple(types) => {
+            let types: Vec<String> = types.iter().map(type_name).collect();
+            format!("({})", types.join(", "))
+        },
+        Type::Reference(inner_type) => format!("&{}", type_name(inner_type))
+    }
+}
+
+struct AstPrinter {
+    out: String,
+    depth: usize
+}
+
+impl AstPrinter {
+    fn new() -> AstPrinter {
+        AstPrinter {
+            out: String::new(),
+            depth: 0
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    /// Prints a node, followed by its children one level deeper
+    fn node<F: FnOnce(&mut AstPrinter)>(&mut self, text: &str, children: F) {
+        self.line(text);
+        self.depth += 1;
+        children(self);
+        self.depth -= 1;
+    }
+
+    fn print_decl(&mut self, decl: &Declaration) {
+        match decl {
+            Declaration::Function(fn_decl_args) => {
+                let arguments: Vec<String> = fn_decl_args.arguments.iter()
+                    .map(|(name, arg_type)| format!("{}: {}", name, type_name(arg_type)))
+                    .collect();
+                let mut header = format!("Function {}({}) ~ {}", fn_decl_args.name, arguments.join(", "), type_name(&fn_decl_args.returns));
+                match &fn_decl_args.code_block {
+                    Some(stmt_list) => self.node(&header, |p| p.print_stmt_list(stmt_list)),
+                    None => {
+                        header.push_str(" (no body)");
+                        self.line(&header);
+                    }
+                };
+            },
+            Declaration::Module(name, decl_list) => {
+                self.node(&format!("Module {}", name), |p| {
+                    for decl in decl_list.iter() {
+                        p.print_decl(decl);
+                    }
+                });
+            },
+            Declaration::Container(cont_decl_args) => {
+                self.node(&format!("Container {}", cont_decl_args.name), |p| {
+                    for (name, member_type) in cont_decl_args.members.iter() {
+                        p.line(&format!("{}: {}", name, type_name(member_type)));
+                    }
+                });
+            },
+            Declaration::Import(import_path, import_as) => {
+                self.line(&format!("Import {} as {}", import_path, import_as));
+            },
+            Declaration::Impl(impl_type, impl_for, decl_list) => {
+                let header = match impl_for.as_str() {
+                    "" => format!("Impl {}", impl_type),
+                    impl_for => format!("Impl {} for {}", impl_type, impl_for)
+                };
+                self.node(&header, |p| {
+                    for decl in decl_list.iter() {
+                        p.print_decl(decl);
+                    }
+                });
+            },
+            Declaration::StaticVar(var_decl_args) => {
+                let header = format!("Static {}: {}", var_decl_args.name, type_name(&var_decl_args.var_type));
+                self.node(&header, |p| p.print_expr(&var_decl_args.assignment));
+            }
+        };
+    }
+
+    fn print_stmt_list(&mut self, stmt_list: &[Spanned<Statement>]) {
+        for stmt in stmt_list.iter() {
+            self.print_stmt(stmt);
+        }
+    }
+
+    fn print_stmt(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::VariableDecl(var_decl_args) => {
+                let header = format!("Var {}: {}", var_decl_args.name, type_name(&var_decl_args.var_type));
+                self.node(&header, |p| p.print_expr(&var_decl_args.assignment));
+            },
+            Statement::Assignment(name, expr) => {
+                self.node(&format!("Assign {}", name), |p| p.print_expr(expr));
+            },
+            Statement::Call(name, args) => {
+                self.node(&format!("Call {}", name), |p| {
+                    for arg in args.iter() {
+                        p.print_expr(arg);
+                    }
+                });
+            },
+            Statement::Return(expr_opt) => {
+                self.node("Return", |p| {
+                    if let Some(expr) = expr_opt {
+                        p.print_expr(expr);
+                    }
+                });
+            },
+            Statement::CodeBlock(stmt_list) => self.node("Block", |p| p.print_stmt_list(stmt_list)),
+            Statement::Loop(stmt_list) => self.node("Loop", |p| p.print_stmt_list(stmt_list)),
+            Statement::While(expr, stmt_list) => {
+                self.node("While", |p| {
+                    p.print_expr(expr);
+                    p.node("Do", |p| p.print_stmt_list(stmt_list));
+                });
+            },
+            Statement::For(for_stmt_args) => {
+                let range = if for_stmt_args.inclusive { "..=" } else { ".." };
+                self.node(&format!("For {} in {}", for_stmt_args.var_name, range), |p| {
+                    p.print_expr(&for_stmt_args.range_start);
+                    p.print_expr(&for_stmt_args.range_end);
+                    p.node("Do", |p| p.print_stmt_list(&for_stmt_args.stmt_list));
+                });
+            },
+            Statement::Break => self.line("Break"),
+            Statement::Continue => self.line("Continue"),
+            Statement::Free(expr) => self.node("Free", |p| p.print_expr(expr)),
+            Statement::Throw(expr) => self.node("Throw", |p| p.print_expr(expr)),
+            Statement::Try(try_stmt_args) => {
+                self.node("Try", |p| p.print_stmt_list(&try_stmt_args.try_block));
+                let header = match &try_stmt_args.catch_var {
+                    Some(var_name) => format!("Catch {}", var_name),
+                    None => String::from("Catch")
+                };
+                self.node(&header, |p| p.print_stmt_list(&try_stmt_args.catch_block));
+            },
+            Statement::Expression(expr) => self.print_expr(expr),
+            Statement::If(if_stmt_args) => {
+                self.node("If", |p| {
+                    p.print_expr(&if_stmt_args.if_expr);
+                    p.node("Then", |p| p.print_stmt_list(&if_stmt_args.if_block));
+                });
+                if let Some(else_if_list) = &if_stmt_args.else_if_list {
+                    for (expr, stmt_list) in else_if_list.iter() {
+                        self.node("Else if", |p| {
+                            p.print_expr(expr);
+                            p.node("Then", |p| p.print_stmt_list(stmt_list));
+                        });
+                    }
+                }
+                if let Some(else_block) = &if_stmt_args.else_block {
+                    self.node("Else", |p| p.print_stmt_list(else_block));
+                }
+            }
+        };
+    }
+
+    fn print_expr(&mut self, expr: &Spanned<Expression>) {
+        let (name, children): (String, Vec<&Spanned<Expression>>) = match &expr.node {
+            Expression::IntLiteral(int) => (format!("Int {}", int), vec![]),
+            Expression::FloatLiteral(float) => (format!("Float {:?}", float), vec![]),
+            Expression::StringLiteral(string) => (format!("String {}", string), vec![]),
+            Expression::BoolLiteral(boolean) => (format!("Bool {}", boolean), vec![]),
+            Expression::Variable(name) => (format!("Variable {}", name), vec![]),
+            Expression::ContainerInstance(name, members) => {
+                let mut members: Vec<(&String, &Spanned<Expression>)> = members.iter().collect();
+                members.sort_by_key(|(name, _)| *name);
+                self.node(&format!("Instance {}", name), |p| {
+                    for (name, expr) in members.into_iter() {
+                        p.node(&format!("{}:", name), |p| p.print_expr(expr));
+                    }
+                });
+                return;
+            },
+            Expression::ArrayLiteral(elements) => (String::from("Array"), elements.iter().collect()),
+            Expression::Index(lhs, rhs) => (String::from("Index"), vec![lhs, rhs]),
+            Expression::Slice(string, start, end) => (String::from("Slice"), vec![string, start, end]),
+            Expression::MemberAccess(lhs, rhs) => (String::from("MemberAccess"), vec![lhs, rhs]),
+            Expression::Deref(inner) => (String::from("Deref"), vec![inner]),
+            Expression::Ref(inner) => (String::from("Ref"), vec![inner]),
+            Expression::New(inner) => (String::from("New"), vec![inner]),
+            Expression::Negate(inner) => (String::from("Negate"), vec![inner]),
+            Expression::Not(inner) => (String::from("Not"), vec![inner]),
+            Expression::Call(name, args) => (format!("Call {}", name), args.iter().collect()),
+            Expression::Addition(lhs, rhs) => (String::from("Addition"), vec![lhs, rhs]),
+            Expression::Subtraction(lhs, rhs) => (String::from("Subtraction"), vec![lhs, rhs]),
+            Expression::Multiplication(lhs, rhs) => (String::from("Multiplication"), vec![lhs, rhs]),
+            Expression::Division(lhs, rhs) => (String::from("Division"), vec![lhs, rhs]),
+            Expression::Modulo(lhs, rhs) => (String::from("Modulo"), vec![lhs, rhs]),
+            Expression::BitAnd(lhs, rhs) => (String::from("BitAnd"), vec![lhs, rhs]),
+            Expression::BitOr(lhs, rhs) => (String::from("BitOr"), vec![lhs, rhs]),
+            Expression::BitXor(lhs, rhs) => (String::from("BitXor"), vec![lhs, rhs]),
+            Expression::ShiftLeft(lhs, rhs) => (String::from("ShiftLeft"), vec![lhs, rhs]),
+            Expression::ShiftRight(lhs, rhs) => (String::from("ShiftRight"), vec![lhs, rhs]),
+            Expression::And(lhs, rhs) => (String::from("And"), vec![lhs, rhs]),
+            Expression::Or(lhs, rhs) => (String::from("Or"), vec![lhs, rhs]),
+            Expression::Equals(lhs, rhs) => (String::from("Equals"), vec![lhs, rhs]),
+            Expression::NotEquals(lhs, rhs) => (String::from("NotEquals"), vec![lhs, rhs]),
+            Expression::GreaterThan(lhs, rhs) => (String::from("GreaterThan"), vec![lhs, rhs]),
+            Expression::LessThan(lhs, rhs) => (String::from("LessThan"), vec![lhs, rhs]),
+            Expression::GreaterThanEquals(lhs, rhs) => (String::from("GreaterThanEquals"), vec![lhs, rhs]),
+            Expression::LessThanEquals(lhs, rhs) => (String::from("LessThanEquals"), vec![lhs, rhs]),
+            Expression::Assign(lhs, rhs) => (String::from("Assign"), vec![lhs, rhs]),
+            Expression::AddAssign(lhs, rhs) => (String::from("AddAssign"), vec![lhs, rhs]),
+            Expression::SubAssign(lhs, rhs) => (String::from("SubAssign"), vec![lhs, rhs]),
+            Expression::MulAssign(lhs, rhs) => (String::from("MulAssign"), vec![lhs, rhs]),
+            Expression::DivAssign(lhs, rhs) => (String::from("DivAssign"), vec![lhs, rhs])
+        };
+        self.node(&name, |p| {
+            for child in children.into_iter() {
+                p.print_expr(child);
+            }
+        });
+    }
+}
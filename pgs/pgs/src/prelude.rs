@@ -0,0 +1,64 @@
+//! Everything needed to embed scripts, e.g. `use pgs::prelude::*;`.
+//! The items of this module are the stable embedding API, the rest of the crate may change with the VM.
+
+pub use crate::{
+    engine::{
+        Engine,
+        EngineError,
+        EngineResult
+    },
+    api::{
+        value::{
+            Value,
+            ValueType,
+            ScriptValue,
+            ScriptArgs
+        },
+        script_function::ScriptFunction,
+        module::Module,
+        function::{
+            Function,
+            ForeignError,
+            ForeignResult
+        },
+        adapter::{
+            Adapter,
+            FromArg,
+            IntoReturn
+        },
+        container::{
+            Container,
+            PgsContainer
+        },
+        arguments::{
+            ArgumentError,
+            ArgumentResult,
+            parse_arguments,
+            get_usage
+        }
+    },
+    parser::{
+        parser::ParseError
+    },
+    codegen::{
+        compiler::CompilerError,
+        bytecode::BytecodeError
+    },
+    assembler::{
+        assembler::AssemblerError
+    },
+    vm::{
+        core::{
+            CoreError,
+            CoreResult
+        },
+        heap::HeapStats
+    },
+    diagnostic::Diagnostic
+};
+
+#[cfg(feature = "derive")]
+pub use crate::derive::{
+    PgsContainer,
+    pgs_function
+};
@@ -0,0 +1,178 @@
+use crate::{
+    assembler::{
+        assembler::{
+            Assembler,
+            AssemblerErrorType
+        },
+        disassembler::Disassembler
+    },
+    codegen::{
+        compiler::Compiler
+    },
+    engine::{
+        Engine,
+        EngineError
+    },
+    parser::{
+        parser::Parser
+    },
+    vm::{
+        core::Core
+    }
+};
+
+#[test]
+fn test_assemble_loop() {
+    let code = String::from("
+        .fn main
+            LDI 0, r0               ; sum
+            LDI 1, r1               ; i
+        loop:
+            LDI 10, r2
+            LTEQI r1, r2, r3
+            JMPF r3, end
+            ADDI r0, r1, r0
+            ADDI_I r1, 1, r1
+            JMP loop
+        end:
+            RET
+    ");
+
+    let assembler = Assembler::new(code);
+    let program_res = assembler.assemble();
+    println!("{:?}", program_res);
+    assert!(program_res.is_ok());
+    let program = program_res.unwrap();
+    let main_uid = program.get_function_uid("main").unwrap();
+
+    let mut core = Core::new(1024);
+    core.load_program(program);
+    let run_res = core.run_fn(main_uid);
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+    assert_eq!(55, core.reg(0).unwrap().get::<i64>());
+}
+
+#[test]
+fn test_assemble_data_and_calls() {
+    let code = String::from("
+        .data
+        greeting: .string \"hi; there\\n\"
+        counter: .static .i64 41
+
+        .code
+        .fn main #7
+            LDA counter, r1
+            MOVI_AR [r1], r2
+            ADDI_I r2, 1, r2
+            MOVI_RA r2, r1, [r1+0]
+            CALL get
+            RET
+        .fn get
+            LDA greeting, r0
+            RET
+    ");
+
+    let program_res = Assembler::new(code.clone()).assemble();
+    assert!(program_res.is_err());
+    assert_eq!(AssemblerErrorType::OperandCount(2, 3), program_res.unwrap_err().error_type);
+
+    let code = code.replace("MOVI_RA r2, r1, [r1+0]", "MOVI_RA r2, [r1+0]");
+    let program = Assembler::new(code).assemble().unwrap();
+    assert_eq!(b"hi; there\n", &program.code[0..10]);
+    assert_eq!(vec![10..18], program.static_vars);
+    assert_eq!(Some(7), program.get_function_uid("main"));
+    assert_eq!(Some(&18), program.functions.get(&7));
+
+    let mut core = Core::new(1024);
+    core.load_program(program);
+    assert!(core.run_fn(7).is_ok());
+    assert_eq!(0, core.reg(0).unwrap().get::<u64>());
+}
+
+#[test]
+fn test_assemble_errors() {
+    let cases = [
+        ("JMP nowhere", AssemblerErrorType::UnknownLabel(String::from("nowhere"))),
+        ("LDI 1, r16", AssemblerErrorType::UnknownRegister(String::from("r16"))),
+        ("CALL nothing", AssemblerErrorType::UnknownFunction(String::from("nothing"))),
+        ("FOO r1", AssemblerErrorType::UnknownMnemonic(String::from("FOO"))),
+        ("a:\na: RET", AssemblerErrorType::DuplicateLabel(String::from("a"))),
+        ("LDB 2, r1", AssemblerErrorType::InvalidOperand(String::from("2")))
+    ];
+    for (code, error_type) in cases.iter() {
+        let code = format!("NOOP\n{}", code);
+        let err = Assembler::new(code).assemble().unwrap_err();
+        assert_eq!(*error_type, err.error_type);
+        assert!(err.line >= 2);
+    }
+}
+
+#[test]
+fn test_disassemble_roundtrip() {
+    let code = String::from("
+        var greeting = \"hello\";
+        var values = [1, 2, 3];
+
+        fn: add(a: int, b: float) ~ int {
+            if b > 1.5 {
+                return a + 1;
+            }
+            return a;
+        }
+
+        fn: main() ~ int {
+            var sum = 0;
+            for i in 0..3 {
+                sum = sum + values[i];
+            }
+            while sum < 100 {
+                sum = add(sum, 2.5);
+            }
+            return sum;
+        }
+    ");
+
+    let parser = Parser::new(code);
+    let decl_list = parser.parse_root().unwrap();
+    let mut compiler = Compiler::new();
+    assert!(compiler.compile_root(&decl_list).is_ok());
+    let program = compiler.get_program().unwrap();
+
+    let text = Disassembler::new(&program).disassemble();
+    println!("{}", text);
+    assert!(text.contains(".fn root::main #"));
+    assert!(text.contains("CALL root::add"));
+    assert!(text.contains(".string \"hello\""));
+
+    let assembled_res = Assembler::new(text).assemble();
+    println!("{:?}", assembled_res);
+    assert!(assembled_res.is_ok());
+    let assembled = assembled_res.unwrap();
+    assert_eq!(program.code, assembled.code);
+    assert_eq!(program.functions, assembled.functions);
+    assert_eq!(program.static_vars, assembled.static_vars);
+
+    let main_uid = assembled.get_function_uid("root::main").unwrap();
+    let mut core = Core::new(1024);
+    core.load_program(assembled);
+    assert!(core.run_fn(main_uid).is_ok());
+    assert_eq!(100, core.reg(0).unwrap().get::<i64>());
+}
+
+#[test]
+fn test_engine_load_assembly() {
+    let code = "
+        .fn main
+            LDI 0, r0
+            RET
+    ";
+    let mut engine = Engine::new(1024);
+    assert!(engine.load_assembly(code).is_ok());
+    assert!(engine.run_fn("main").is_ok());
+
+    match engine.load_assembly(".fn main\n    FOO r0\n").map_err(|e| *e) {
+        Err(EngineError::AssemblerError(err)) => assert_eq!(2, err.line),
+        res => panic!("expected an assembler error, got {:?}", res)
+    };
+}
@@ -1,5 +1,4 @@
-extern crate pgs;
-use pgs::{
+use crate::{
     codegen::{
         compiler::{
             Compiler
@@ -1,7 +1,7 @@
-extern crate pgs;
-use pgs::{
+use crate::{
     vm::{
         core::*,
+        heap::Heap,
         is::Opcode
     },
     codegen::{
@@ -116,6 +116,10 @@ fn test_core_foreign_ptr() {
         int.0 += 10;
     }
 
+    // The pointer can only be accessed as the type it was inserted with
+    assert!(matches!(core.get_foreign_ptr::<i32>(ptr), Err(CoreError::ForeignTypeMismatch(_))));
+    assert!(matches!(core.remove_foreign_ptr::<i32>(ptr), Err(CoreError::ForeignTypeMismatch(_))));
+
     let remove_res = core.remove_foreign_ptr(ptr);
     println!("{:?}", remove_res);
     assert!(remove_res.is_ok());
@@ -126,4 +130,69 @@ fn test_core_foreign_ptr() {
         let int = int_arc.lock().unwrap();
         assert_eq!(int.0, 10);
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_heap_alloc_free() {
+    let mut heap = Heap::new();
+    let a = heap.alloc(12);
+    let b = heap.alloc(8);
+    let c = heap.alloc(8);
+    assert_eq!(0, a);
+    assert_eq!(16, b);
+    assert_eq!(24, c);
+    assert_eq!(3, heap.allocation_count());
+
+    assert!(heap.write(b, &[1, 2, 3, 4, 5, 6, 7, 8]).is_ok());
+    assert_eq!(vec![1, 2, 3, 4, 5, 6, 7, 8], heap.read(b, 8).unwrap());
+    assert!(heap.read(b, 9).is_err());
+
+    // Freed blocks get reused and coalesced
+    assert!(heap.free(a).is_ok());
+    assert!(heap.free(b).is_ok());
+    assert_eq!(24, heap.free_bytes());
+    assert_eq!(0, heap.alloc(20));
+    assert!(heap.free(b).is_err());
+
+    // Freeing the last block shrinks the heap
+    assert!(heap.free(c).is_ok());
+    assert_eq!(24, heap.size());
+    assert_eq!(1, heap.allocation_count());
+}
+
+#[test]
+fn test_core_alloc() {
+    let mut builder = Builder::new();
+
+    let alloc_instr = Instruction::new(Opcode::ALLOC_I) // ALLOC_I 8, r0
+        .with_operand(8u64)
+        .with_operand(0u8);
+    let ldi_instr = Instruction::new(Opcode::LDI) // LDI 42, r1
+        .with_operand(42i64)
+        .with_operand(1u8);
+    let mov_to_heap_instr = Instruction::new(Opcode::MOVI_RA) // MOVI r1, [r0]
+        .with_operand(1u8)
+        .with_operand(0u8)
+        .with_operand::<i16>(0);
+    let mov_from_heap_instr = Instruction::new(Opcode::MOVI_AR) // MOVI [r0], r2
+        .with_operand(0u8)
+        .with_operand::<i16>(0)
+        .with_operand(2u8);
+    let free_instr = Instruction::new(Opcode::FREE) // FREE r0
+        .with_operand(0u8);
+
+    builder.push_instr(alloc_instr);
+    builder.push_instr(ldi_instr);
+    builder.push_instr(mov_to_heap_instr);
+    builder.push_instr(mov_from_heap_instr);
+    builder.push_instr(free_instr);
+
+    let program = Program::new().with_code(builder.build());
+
+    let mut core = Core::new(1024);
+    core.load_program(program);
+    let run_res = core.run();
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+    assert_eq!(42, core.reg(2).unwrap().get::<i64>());
+}
@@ -1,6 +1,4 @@
-extern crate pgs;
-extern crate pglex;
-use pgs::{
+use crate::{
     parser::{
         lexer::Token
     }
@@ -0,0 +1,11 @@
+//! Tests of the internals, the embedding API is tested in the `tests` directory
+
+mod core;
+
+mod compiler;
+
+mod parser;
+
+mod lexer;
+
+mod assembler;
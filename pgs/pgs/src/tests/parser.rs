@@ -1,15 +1,19 @@
-extern crate pgs;
-extern crate pglex;
-use pgs::{
+use crate::{
     parser::{
         parser::*,
         ast::*,
-        lexer::*
+        lexer::*,
+        printer::*
     }
 };
 
 use pglex::prelude::Lexable;
 
+/// Wraps an expected expression, spans are ignored when nodes are compared
+fn spanned(expr: Expression) -> Spanned<Expression> {
+    Spanned::new(expr, 0..0)
+}
+
 #[test]
 fn test_parse_import_decl() {
     let code = String::from("
@@ -195,6 +199,33 @@ fn test_parse_stmt_list() {
     assert_eq!(stmt_list.len(), 2);
 }
 
+#[test]
+fn test_parse_stmt_spans() {
+    let code = String::from("
+        var x: int = 4;
+        if x == 4 {
+            x += 1;
+        }
+    ");
+
+    let mut lexer = Token::lexer(code.as_str());
+    let parser = Parser::new(code.clone());
+    let stmt_list_res = parser.parse_statement_list(&mut lexer);
+
+    assert!(stmt_list_res.is_ok());
+    let stmt_list = stmt_list_res.unwrap();
+
+    assert_eq!(stmt_list.len(), 2);
+    assert_eq!("var x: int = 4;", &code[stmt_list[0].span.clone()]);
+    assert_eq!("if x == 4 {\n            x += 1;\n        }", &code[stmt_list[1].span.clone()]);
+
+    if let Statement::If(if_stmt_args) = &stmt_list[1].node {
+        assert_eq!("x += 1;", &code[if_stmt_args.if_block[0].span.clone()]);
+    } else {
+        panic!("Expected an if statement");
+    }
+}
+
 #[test]
 fn test_parse_stmt_addition() {
     let code = String::from("
@@ -290,7 +321,7 @@ fn test_parse_full_fn() {
 
 #[test]
 fn test_parse_expr_paran_delim() {
-    use pgs::{
+    use crate::{
         parser::ast::*  
     };
 
@@ -304,17 +335,17 @@ fn test_parse_expr_paran_delim() {
     ]);
     assert!(expr_res.is_ok());
     let expr = expr_res.unwrap();
-    match expr {
+    match expr.node {
         Expression::Addition(lhs, rhs) => {
-            match *lhs {
+            match lhs.node {
                 Expression::Addition(lhs, rhs) => {
-                    match *lhs {
+                    match lhs.node {
                         Expression::IntLiteral(_) => {},
                         _ => {
                             panic!("Incorrect expression! Should be IntLiteral.");
                         }
                     };
-                    match *rhs {
+                    match rhs.node {
                         Expression::IntLiteral(_) => {},
                         _ => {
                             panic!("Incorrect expression! Should be IntLiteral.");
@@ -325,7 +356,7 @@ fn test_parse_expr_paran_delim() {
                     panic!("Incorrect expression! Should be Addition.");
                 }
             };
-            match *rhs {
+            match rhs.node {
                 Expression::IntLiteral(_) => {},
                 _ => {
                     panic!("Incorrect expression! Should be IntLiteral.");
@@ -340,7 +371,7 @@ fn test_parse_expr_paran_delim() {
 
 #[test]
 fn test_parse_call_stmt() {
-    use pgs::{
+    use crate::{
         parser::ast::*  
     };
 
@@ -355,15 +386,15 @@ fn test_parse_call_stmt() {
         assert_eq!(name, String::from("add"));
         assert_eq!(args.len(), 2);
         assert_eq!(args, vec![
-            Expression::IntLiteral(5),
-            Expression::IntLiteral(5)
+            spanned(Expression::IntLiteral(5)),
+            spanned(Expression::IntLiteral(5))
         ]);
     }
 }
 
 #[test]
 fn test_parse_call_expr() {
-    use pgs::parser::ast::Expression;
+    use crate::parser::ast::Expression;
 
     let code = String::from("
         add(5, 5);
@@ -376,19 +407,19 @@ fn test_parse_call_expr() {
 
     let expr_res = parser.parse_expr(&mut lexer, &delims);
     assert!(expr_res.is_ok());
-    if let Expression::Call(name, args) = expr_res.unwrap() {
+    if let Expression::Call(name, args) = expr_res.unwrap().node {
         assert_eq!(name, String::from("add"));
         assert_eq!(args.len(), 2);
         assert_eq!(args, vec![
-            Expression::IntLiteral(5),
-            Expression::IntLiteral(5)
+            spanned(Expression::IntLiteral(5)),
+            spanned(Expression::IntLiteral(5))
         ]);
     }
 }
 
 #[test]
 fn test_parse_complex_call_expr() {
-    use pgs::parser::ast::Expression;
+    use crate::parser::ast::Expression;
 
     let code = String::from("
         add(5, 5) + 5;
@@ -400,9 +431,9 @@ fn test_parse_complex_call_expr() {
     ]);
     assert!(expr_res.is_ok());
     let expr = expr_res.unwrap();
-    match expr {
+    match expr.node {
         Expression::Addition(lhs, rhs) => {
-            match *lhs {
+            match lhs.node {
                 Expression::Call(fn_name, args) => {
                     assert_eq!(fn_name, String::from("add"));
                     assert_eq!(args.len(), 2);
@@ -411,7 +442,7 @@ fn test_parse_complex_call_expr() {
                     panic!("Wrong expression! Should be Call.");
                 }
             };
-            match *rhs {
+            match rhs.node {
                 Expression::IntLiteral(int) => {
                     assert_eq!(int, 5);
                 },
@@ -491,6 +522,338 @@ fn test_parse_loop() {
     }
 }
 
+#[test]
+fn test_parse_for() {
+    let code = String::from("
+        for i in 0..10 {
+            var x: int = i;
+        }
+    ");
+
+    let parser = Parser::new(code.clone());
+    let mut lexer = Token::lexer(code.as_str());
+    let stmt_res = parser.parse_for(&mut lexer);
+    assert!(stmt_res.is_ok());
+
+    if let Statement::For(for_stmt_args) = stmt_res.unwrap() {
+        assert_eq!(String::from("i"), for_stmt_args.var_name);
+        assert_eq!(Expression::IntLiteral(0), for_stmt_args.range_start.node);
+        assert_eq!(Expression::IntLiteral(10), for_stmt_args.range_end.node);
+        assert!(!for_stmt_args.inclusive);
+        assert_eq!(1, for_stmt_args.stmt_list.len());
+    } else {
+        panic!("Expected a for statement");
+    }
+}
+
+#[test]
+fn test_parse_for_inclusive() {
+    let code = String::from("
+        for index in start..=start + len {
+            continue;
+        }
+    ");
+
+    let parser = Parser::new(code.clone());
+    let mut lexer = Token::lexer(code.as_str());
+    let stmt_res = parser.parse_for(&mut lexer);
+    assert!(stmt_res.is_ok());
+
+    if let Statement::For(for_stmt_args) = stmt_res.unwrap() {
+        assert_eq!(String::from("index"), for_stmt_args.var_name);
+        assert_eq!(Expression::Variable(String::from("start")), for_stmt_args.range_start.node);
+        assert!(for_stmt_args.inclusive);
+        assert_eq!(1, for_stmt_args.stmt_list.len());
+        assert_eq!(Statement::Continue, for_stmt_args.stmt_list[0].node);
+    } else {
+        panic!("Expected a for statement");
+    }
+}
+
+#[test]
+fn test_parse_array_expr() {
+    let code = String::from("[1, arr[i], grid[0][1]];");
+
+    let parser = Parser::new(code.clone());
+    let mut lexer = Token::lexer(code.as_str());
+    let expr_res = parser.parse_expr(&mut lexer, &[Token::Semicolon]);
+    assert!(expr_res.is_ok());
+
+    let index_expr = Expression::Index(
+        Box::new(spanned(Expression::Variable(String::from("arr")))),
+        Box::new(spanned(Expression::Variable(String::from("i"))))
+    );
+    let nested_index_expr = Expression::Index(
+        Box::new(spanned(Expression::Index(
+            Box::new(spanned(Expression::Variable(String::from("grid")))),
+            Box::new(spanned(Expression::IntLiteral(0)))
+        ))),
+        Box::new(spanned(Expression::IntLiteral(1)))
+    );
+    let expected = Expression::ArrayLiteral(vec![
+        spanned(Expression::IntLiteral(1)),
+        spanned(index_expr),
+        spanned(nested_index_expr)
+    ]);
+    assert_eq!(expected, expr_res.unwrap().node);
+}
+
+#[test]
+fn test_parse_array_literal_index() {
+    let code = String::from("[10, 20][1];");
+
+    let parser = Parser::new(code.clone());
+    let mut lexer = Token::lexer(code.as_str());
+    let expr_res = parser.parse_expr(&mut lexer, &[Token::Semicolon]);
+    assert!(expr_res.is_ok());
+
+    let expected = Expression::Index(
+        Box::new(spanned(Expression::ArrayLiteral(vec![
+            spanned(Expression::IntLiteral(10)),
+            spanned(Expression::IntLiteral(20))
+        ]))),
+        Box::new(spanned(Expression::IntLiteral(1)))
+    );
+    let expr = expr_res.unwrap();
+    assert_eq!(0..11, expr.span);
+    assert_eq!(expected, expr.node);
+}
+
+#[test]
+fn test_parse_slice_expr() {
+    let code = String::from("name[1..len(name)];");
+
+    let parser = Parser::new(code.clone());
+    let mut lexer = Token::lexer(code.as_str());
+    let expr_res = parser.parse_expr(&mut lexer, &[Token::Semicolon]);
+    assert!(expr_res.is_ok());
+
+    let expected = Expression::Slice(
+        Box::new(spanned(Expression::Variable(String::from("name")))),
+        Box::new(spanned(Expression::IntLiteral(1))),
+        Box::new(spanned(Expression::Call(String::from("len"), vec![
+            spanned(Expression::Variable(String::from("name")))
+        ])))
+    );
+    assert_eq!(expected, expr_res.unwrap().node);
+}
+
+#[test]
+fn test_parse_unary_minus() {
+    let code = String::from("-x * -2 - -(y - 1.5);");
+
+    let parser = Parser::new(code.clone());
+    let mut lexer = Token::lexer(code.as_str());
+    let expr_res = parser.parse_expr(&mut lexer, &[Token::Semicolon]);
+    assert!(expr_res.is_ok());
+
+    let expected = Expression::Subtraction(
+        Box::new(spanned(Expression::Multiplication(
+            Box::new(spanned(Expression::Negate(
+                Box::new(spanned(Expression::Variable(String::from("x"))))
+            ))),
+            Box::new(spanned(Expression::IntLiteral(-2)))
+        ))),
+        Box::new(spanned(Expression::Negate(
+            Box::new(spanned(Expression::Subtraction(
+                Box::new(spanned(Expression::Variable(String::from("y")))),
+                Box::new(spanned(Expression::FloatLiteral(1.5)))
+            )))
+        )))
+    );
+    assert_eq!(expected, expr_res.unwrap().node);
+}
+
+#[test]
+fn test_parse_bitwise_ops() {
+    let code = String::from("a & b | c ^ d << 2 == e % 3;");
+
+    let parser = Parser::new(code.clone());
+    let mut lexer = Token::lexer(code.as_str());
+    let expr_res = parser.parse_expr(&mut lexer, &[Token::Semicolon]);
+    assert!(expr_res.is_ok());
+
+    let expected = Expression::Equals(
+        Box::new(spanned(Expression::BitOr(
+            Box::new(spanned(Expression::BitAnd(
+                Box::new(spanned(Expression::Variable(String::from("a")))),
+                Box::new(spanned(Expression::Variable(String::from("b"))))
+            ))),
+            Box::new(spanned(Expression::BitXor(
+                Box::new(spanned(Expression::Variable(String::from("c")))),
+                Box::new(spanned(Expression::ShiftLeft(
+                    Box::new(spanned(Expression::Variable(String::from("d")))),
+                    Box::new(spanned(Expression::IntLiteral(2)))
+                )))
+            )))
+        ))),
+        Box::new(spanned(Expression::Modulo(
+            Box::new(spanned(Expression::Variable(String::from("e")))),
+            Box::new(spanned(Expression::IntLiteral(3)))
+        )))
+    );
+    assert_eq!(expected, expr_res.unwrap().node);
+}
+
+#[test]
+fn test_parse_mul_mod_assoc() {
+    let code = String::from("2 * 3 % 4 * 5;");
+
+    let parser = Parser::new(code.clone());
+    let mut lexer = Token::lexer(code.as_str());
+    let expr_res = parser.parse_expr(&mut lexer, &[Token::Semicolon]);
+    assert!(expr_res.is_ok());
+
+    // Operators of the same precedence group from the left
+    let expected = Expression::Multiplication(
+        Box::new(spanned(Expression::Modulo(
+            Box::new(spanned(Expression::Multiplication(
+                Box::new(spanned(Expression::IntLiteral(2))),
+                Box::new(spanned(Expression::IntLiteral(3)))
+            ))),
+            Box::new(spanned(Expression::IntLiteral(4)))
+        ))),
+        Box::new(spanned(Expression::IntLiteral(5)))
+    );
+    assert_eq!(expected, expr_res.unwrap().node);
+}
+
+#[test]
+fn test_parse_static_var_decl() {
+    let code = String::from("
+        var counter: int = 5;
+        mod: config {
+            var value = 42;
+        }
+    ");
+
+    let parser = Parser::new(code);
+    let decl_list_res = parser.parse_root_decl_list();
+    assert!(decl_list_res.is_ok());
+
+    let decl_list = decl_list_res.unwrap();
+    assert_eq!(decl_list.len(), 2);
+    let expected = Declaration::StaticVar(VariableDeclArgs {
+        var_type: Type::Int,
+        name: String::from("counter"),
+        assignment: Box::new(spanned(Expression::IntLiteral(5)))
+    });
+    assert_eq!(expected, decl_list[0].node);
+}
+
+#[test]
+fn test_parse_path_var_expr() {
+    let code = String::from("config::value + 1;");
+
+    let parser = Parser::new(code.clone());
+    let mut lexer = Token::lexer(code.as_str());
+    let expr_res = parser.parse_expr(&mut lexer, &[Token::Semicolon]);
+    assert!(expr_res.is_ok());
+
+    let expected = Expression::Addition(
+        Box::new(spanned(Expression::Variable(String::from("config::value")))),
+        Box::new(spanned(Expression::IntLiteral(1)))
+    );
+    assert_eq!(expected, expr_res.unwrap().node);
+}
+
+#[test]
+fn test_parse_expr_spans() {
+    let code = String::from("x + foo(1, -2);");
+
+    let parser = Parser::new(code.clone());
+    let mut lexer = Token::lexer(code.as_str());
+    let expr = parser.parse_expr(&mut lexer, &[Token::Semicolon]).unwrap();
+    assert_eq!(0..14, expr.span);
+
+    if let Expression::Addition(lhs, rhs) = expr.node {
+        assert_eq!("x", &code[lhs.span]);
+        assert_eq!("foo(1, -2)", &code[rhs.span.clone()]);
+        if let Expression::Call(_, args) = rhs.node {
+            assert_eq!("-2", &code[args[1].span.clone()]);
+        } else {
+            panic!("Expected a call expression");
+        }
+    } else {
+        panic!("Expected an addition");
+    }
+}
+
+#[test]
+fn test_parse_error_recovery() {
+    let code = String::from("
+        fn: main() ~ int {
+            var x = 1 +;
+            var y = 2;
+            return y;
+        }
+
+        fn: other() ~ int {
+            return 1 * ;
+        }
+
+        cont Broken {}
+
+        fn: third() ~ int {
+            return 3;
+        }
+    ");
+
+    let parser = Parser::new(code.clone());
+    let errors = match parser.parse_root() {
+        Err(errors) => errors,
+        Ok(_) => panic!("Expected parse errors")
+    };
+
+    assert_eq!(3, errors.len());
+    match errors[0].error_type {
+        ParseErrorType::ExpectedOperand => {},
+        _ => panic!("Expected an ExpectedOperand error, got {:?}", errors[0])
+    };
+    assert_eq!(";", &code[errors[0].token_pos.clone()]);
+    match errors[1].error_type {
+        ParseErrorType::ExpectedOperand => {},
+        _ => panic!("Expected an ExpectedOperand error, got {:?}", errors[1])
+    };
+    match errors[2].error_type {
+        ParseErrorType::ExpectedColon => {},
+        _ => panic!("Expected an ExpectedColon error, got {:?}", errors[2])
+    };
+}
+
+#[test]
+fn test_parse_error_recovery_unmatched_block() {
+    let code = String::from("cont: C { x: int }");
+
+    let parser = Parser::new(code.clone());
+    let errors = match parser.parse_root() {
+        Err(errors) => errors,
+        Ok(_) => panic!("Expected parse errors")
+    };
+
+    // The "}" ending the broken declaration doesn't cause a second error
+    assert_eq!(1, errors.len());
+    match errors[0].error_type {
+        ParseErrorType::ExpectedSemicolon => {},
+        _ => panic!("Expected an ExpectedSemicolon error, got {:?}", errors[0])
+    };
+
+    // Inside of a module as well
+    let code = String::from("
+        mod: config {
+            cont: C { x: int }
+            fn: get() ~ int { return 1; }
+        }
+        fn: main() ~ int { return 0; }
+    ");
+    let parser = Parser::new(code);
+    let errors = match parser.parse_root() {
+        Err(errors) => errors,
+        Ok(_) => panic!("Expected parse errors")
+    };
+    assert_eq!(1, errors.len());
+}
+
 #[test]
 fn test_parse_if() {
     let code = String::from("
@@ -663,10 +1026,120 @@ fn test_parse_cont_instance() {
     assert!(decl_list_res.is_ok());
 
     for decl in decl_list_res.unwrap() {
-        if let Declaration::Function(fn_decl_args) = decl {
+        if let Declaration::Function(fn_decl_args) = decl.node {
             for stmt in fn_decl_args.code_block.iter() {
                 println!("{:?}", stmt);
             }
         }
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_print_tokens() {
+    let code = "fn: main() {\n    return 4;\n}";
+
+    let tokens = print_tokens(code);
+    let lines: Vec<&str> = tokens.lines().collect();
+    assert_eq!(10, lines.len());
+    assert_eq!("1:1      Fn                 \"fn\"", lines[0]);
+    assert_eq!("2:5      Return             \"return\"", lines[6]);
+    assert_eq!("3:1      CloseBlock         \"}\"", lines[9]);
+}
+
+#[test]
+fn test_print_decl_list() {
+    let code = String::from("
+        cont: Point {
+            x: int;
+        }
+
+        fn: main(values: [int; 4]) ~ int {
+            var sum = 0;
+            for i in 0..=3 {
+                sum += values[i];
+            }
+            if sum > 10 {
+                return -sum;
+            } else {
+                return sum;
+            }
+        }
+    ");
+
+    let parser = Parser::new(code);
+    let decl_list = parser.parse_root().unwrap();
+    let tree = print_decl_list(&decl_list);
+    println!("{}", tree);
+    assert_eq!("Container Point
+  x: int
+Function main(values: [int; 4]) ~ int
+  Var sum: auto
+    Int 0
+  For i in ..=
+    Int 0
+    Int 3
+    Do
+      AddAssign
+        Variable sum
+        Index
+          Variable values
+          Variable i
+  If
+    GreaterThan
+      Variable sum
+      Int 10
+    Then
+      Return
+        Negate
+          Variable sum
+  Else
+    Return
+      Variable sum
+", tree);
+}
+
+#[test]
+fn test_parse_try_catch() {
+    let code = String::from("
+        fn: main() {
+            try {
+                throw \"failed\";
+            } catch err {
+                var message = err;
+            }
+            try {
+                var a = 1 / 0;
+            } catch {}
+        }
+    ");
+
+    let parser = Parser::new(code);
+    let decl_list = parser.parse_root().unwrap();
+    let tree = print_decl_list(&decl_list);
+    println!("{}", tree);
+    assert_eq!("Function main() ~ void
+  Try
+    Throw
+      String \"failed\"
+  Catch err
+    Var message: auto
+      Variable err
+  Try
+    Var a: auto
+      Division
+        Int 1
+        Int 0
+  Catch
+", tree);
+
+    let code = String::from("return 1;");
+    let parser = Parser::new(code.clone());
+    let mut lexer = Token::lexer(code.as_str());
+    match parser.parse_try(&mut lexer).map_err(|err| err.error_type) {
+        Err(ParseErrorType::ExpectedTry) => {},
+        res => panic!("Expected an ExpectedTry error, got {:?}", res)
+    };
+    match parser.parse_throw(&mut lexer).map_err(|err| err.error_type) {
+        Err(ParseErrorType::ExpectedThrow) => {},
+        res => panic!("Expected an ExpectedThrow error, got {:?}", res)
+    };
+}
@@ -6,6 +6,10 @@ use super::{
         Address,
         AddressType
     },
+    heap::{
+        Heap,
+        HeapStats
+    },
     register::{
         Register,
         RegisterAccess
@@ -19,7 +23,11 @@ use crate::{
     api::{
         module::Module,
         function::*,
+        script_function::ScriptFunction,
         adapter::Adapter
+    },
+    parser::{
+        ast::Type
     }
 };
 
@@ -38,9 +46,9 @@ use std::{
     },
     convert::TryFrom,
     ops::{
-        Deref,
         Range
     },
+    cmp::Ordering,
     fmt::{
         Debug,
         Display,
@@ -48,6 +56,10 @@ use std::{
         Result as FmtResult
     },
     error::Error,
+    any::{
+        Any,
+        type_name
+    },
     sync::{
         Arc,
         Mutex
@@ -77,21 +89,47 @@ pub type CoreResult<T> = Result<T, CoreError>;
 pub const STACK_GROW_INCREMENT: usize = 1024;
 pub const STACK_GROW_THRESHOLD: usize = 64;
 pub const SWAP_SPACE_SIZE: usize = 64;
+/// Amount of bytes allocated in between automatic garbage collections
+pub const GC_THRESHOLD: usize = 1024 * 1024;
 
 pub struct Core {
     stack: Vec<u8>,
-    heap: Vec<u8>,
-    heap_pointers: Vec<Range<usize>>,
-    foreign_pointers: HashMap<u64, u64>,
+    heap: Heap,
+    gc_threshold: usize,
+    gc_allocated_bytes: usize,
+    gc_collections: usize,
+    gc_collected_bytes: usize,
+    foreign_pointers: HashMap<u64, ForeignPointer>,
     foreign_function_uids: HashSet<u64>,
     swap: Vec<u8>,
     program: Option<Program>,
-    call_stack: VecDeque<usize>,
+    /// Return addresses and the saved registers of the callers
+    call_stack: VecDeque<(usize, [Register; 16])>,
+    /// The try blocks that are currently entered, innermost last
+    try_stack: Vec<TryFrame>,
     registers: [Register; 16],
     ip: Register,
     sp: Register,
 }
 
+/// A Rust value handed to scripts
+struct ForeignPointer {
+    /// The `Arc<Mutex<T>>` the pointer was inserted with
+    value: Box<dyn Any + Send>,
+    /// Canonical name of the native container, if the value is an instance of one
+    container: Option<String>,
+    /// Pinned pointers are held outside of scripts and not dropped by the garbage collector
+    pinned: bool
+}
+
+/// The state to restore when an error is caught by a try block
+struct TryFrame {
+    handler_ip: usize,
+    call_depth: usize,
+    sp: u64,
+    registers: [Register; 16]
+}
+
 #[derive(Debug)]
 pub enum CoreError {
     Unknown,
@@ -106,12 +144,51 @@ pub enum CoreError {
     InvalidStackPointer,
     InvalidRegister,
     NoReturnValue,
-    Halted(u8)
+    IndexOutOfBounds(i64, u64),
+    InvalidHeapPointer,
+    InvalidForeignPointer,
+    /// A foreign pointer was accessed as a different Rust type than it was inserted with
+    ForeignTypeMismatch(&'static str),
+    DivisionByZero,
+    Halted(u8),
+    /// A foreign function returned an error
+    ForeignFunction {
+        name: String,
+        message: String
+    },
+    /// An error raised by a `throw` statement
+    Thrown(String),
+    EmptyTryStack
 }
 
 impl Display for CoreError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        write!(f, "{:?}", self)
+        match self {
+            CoreError::ForeignFunction { name, message } => {
+                write!(f, "foreign function {} failed: {}", name, message)
+            },
+            CoreError::DivisionByZero => write!(f, "division by zero"),
+            CoreError::IndexOutOfBounds(index, size) => {
+                write!(f, "index {} is out of bounds for size {}", index, size)
+            },
+            CoreError::ForeignTypeMismatch(type_name) => {
+                write!(f, "foreign pointer is not of type {}", type_name)
+            },
+            CoreError::Thrown(message) => write!(f, "{}", message),
+            _ => write!(f, "{:?}", self)
+        }
+    }
+}
+
+impl CoreError {
+    /// Whether scripts can catch this error, the others are faults of the VM or the program
+    pub fn is_catchable(&self) -> bool {
+        matches!(self,
+            CoreError::DivisionByZero |
+            CoreError::IndexOutOfBounds(_, _) |
+            CoreError::ForeignFunction { .. } |
+            CoreError::Thrown(_)
+        )
     }
 }
 
@@ -132,11 +209,15 @@ impl Core {
             program: None,
             swap: swap,
             stack: stack,
-            heap: Vec::new(),
-            heap_pointers: Vec::new(),
+            heap: Heap::new(),
+            gc_threshold: GC_THRESHOLD,
+            gc_allocated_bytes: 0,
+            gc_collections: 0,
+            gc_collected_bytes: 0,
             foreign_pointers: HashMap::new(),
             foreign_function_uids: HashSet::new(),
             call_stack: VecDeque::new(),
+            try_stack: Vec::new(),
             registers: [Register::new(); 16],
             ip: Register::new(),
             sp: sp
@@ -150,6 +231,38 @@ impl Core {
         self.program = Some(program);
     }
 
+    /// Replaces the loaded program with one compiled on top of it.
+    /// Static variables both programs have in common keep their current values.
+    pub fn reload_program(&mut self, mut program: Program) {
+        if let Some(old_program) = self.program.as_ref() {
+            for range in old_program.static_vars.iter() {
+                if program.static_vars.contains(range) && range.end <= program.code.len() {
+                    program.code[range.clone()].copy_from_slice(&old_program.code[range.clone()]);
+                }
+            }
+        }
+        self.load_program(program);
+    }
+
+    /// Gets the uid of a function of the loaded program by its full name
+    pub fn get_function_uid(&self, name: &str) -> Option<u64> {
+        self.program.as_ref()
+            .and_then(|program| program.get_function_uid(name))
+    }
+
+    /// Gets the signature of a script function of the loaded program by its full name
+    pub fn get_script_function(&self, name: &str) -> Option<&ScriptFunction> {
+        self.program.as_ref()
+            .and_then(|program| program.get_script_function(name))
+    }
+
+    /// Gets the full names of the script functions of the loaded program
+    pub fn get_script_function_names(&self) -> Vec<String> {
+        self.program.as_ref()
+            .map(|program| program.script_functions.values().map(|function| function.name.clone()).collect())
+            .unwrap_or_default()
+    }
+
     #[inline]
     pub fn program_len(&self) -> CoreResult<usize> {
         let program = self.program.as_ref()
@@ -186,6 +299,11 @@ impl Core {
     
     #[inline]
     pub fn run_fn(&mut self, uid: u64) -> CoreResult<()> {
+        // Foreign functions have no code to run
+        if self.foreign_function_uids.contains(&uid) {
+            return self.call_foreign_fn(uid);
+        }
+
         let fn_offset = {
             let program = self.program.as_ref()
                 .ok_or(CoreError::NoProgram)?;
@@ -198,6 +316,42 @@ impl Core {
     }
 
     pub fn run_at(&mut self, offset: usize) -> CoreResult<()> {
+        let mut run_res = self.execute_at(offset);
+        while let Err(err) = run_res {
+            if !err.is_catchable() || self.try_stack.is_empty() {
+                // The next call would return into the frames of the failed one
+                self.call_stack.clear();
+                self.try_stack.clear();
+                return Err(err);
+            }
+            let handler_ip = self.unwind(err)?;
+            run_res = self.execute_at(handler_ip);
+        }
+        Ok(())
+    }
+
+    /// Returns from every call made inside of the innermost try block and
+    /// pushes the error message, which becomes the variable of its catch block.
+    /// Returns the position of the catch block.
+    fn unwind(&mut self, err: CoreError) -> CoreResult<usize> {
+        let frame = self.try_stack.pop()
+            .ok_or(CoreError::EmptyTryStack)?;
+        // Calls push their frames to the front, so the newest ones are dropped from there
+        while self.call_stack.len() > frame.call_depth {
+            self.call_stack.pop_front();
+        }
+        self.registers = frame.registers;
+        self.sp.set(frame.sp);
+
+        let message = err.to_string();
+        let message_addr = self.alloc_bytes(message.as_bytes())?;
+        self.push_stack(message.len() as u64)?;
+        self.push_stack(message_addr)?;
+
+        Ok(frame.handler_ip)
+    }
+
+    fn execute_at(&mut self, offset: usize) -> CoreResult<()> {
         self.ip.set(offset);
         let program_len = self.program_len()?;
         //println!("Program length: {}", program_len);
@@ -473,7 +627,10 @@ impl Core {
                     let rhs: i64 = {
                         self.reg(rhs_reg)?.get()
                     };
-                    self.reg(target_reg)?.set(lhs / rhs)
+                    if rhs == 0 {
+                        return Err(CoreError::DivisionByZero);
+                    }
+                    self.reg(target_reg)?.set(lhs.wrapping_div(rhs));
                 },
                 Opcode::ADDI_I => {
                     let lhs_reg: u8 = self.get_op()?;
@@ -509,7 +666,10 @@ impl Core {
                     let lhs: i64 = {
                         self.reg(lhs_reg)?.get()
                     };
-                    self.reg(target_reg)?.set(lhs / rhs);
+                    if rhs == 0 {
+                        return Err(CoreError::DivisionByZero);
+                    }
+                    self.reg(target_reg)?.set(lhs.wrapping_div(rhs));
                 },
                 Opcode::ADDU => {
                     let lhs_reg: u8 = self.get_op()?;
@@ -557,7 +717,10 @@ impl Core {
                     let rhs: u64 = {
                         self.reg(rhs_reg)?.get()
                     };
-                    self.reg(target_reg)?.set(lhs / rhs)
+                    if rhs == 0 {
+                        return Err(CoreError::DivisionByZero);
+                    }
+                    self.reg(target_reg)?.set(lhs.wrapping_div(rhs));
                 },
                 Opcode::ADDU_I => {
                     let lhs_reg: u8 = self.get_op()?;
@@ -606,7 +769,10 @@ impl Core {
                     let lhs: u64 = {
                         self.reg(lhs_reg)?.get()
                     };
-                    self.reg(target_reg)?.set(lhs / rhs);
+                    if rhs == 0 {
+                        return Err(CoreError::DivisionByZero);
+                    }
+                    self.reg(target_reg)?.set(lhs.wrapping_div(rhs));
                 },
                 Opcode::ADDF => {
                     let lhs_reg: u8 = self.get_op()?;
@@ -759,6 +925,25 @@ impl Core {
                     }
                     self.ret()?;
                 },
+                Opcode::TRY => {
+                    let handler_ip: u64 = self.get_op()?;
+                    self.try_stack.push(TryFrame {
+                        handler_ip: handler_ip as usize,
+                        call_depth: self.call_stack.len(),
+                        sp: self.sp.get(),
+                        registers: self.registers
+                    });
+                },
+                Opcode::ENDTRY => {
+                    self.try_stack.pop()
+                        .ok_or(CoreError::EmptyTryStack)?;
+                },
+                Opcode::THROW => {
+                    // The message is on top of the stack
+                    let sp: u64 = self.sp.get();
+                    let message = self.mem_get_string(sp - 16)?;
+                    return Err(CoreError::Thrown(message));
+                },
                 Opcode::NOT => {
                     let lhs_reg: u8 = self.get_op()?;
                     let rhs_reg: u8 = self.get_op()?;
@@ -767,6 +952,154 @@ impl Core {
                     };
                     self.reg(rhs_reg)?.set(!lhs);
                 },
+                Opcode::NEGI => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    self.reg(rhs_reg)?.set(lhs.wrapping_neg());
+                },
+                Opcode::NEGF => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs_reg: u8 = self.get_op()?;
+                    let lhs: f32 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    self.reg(rhs_reg)?.set(-lhs);
+                },
+                Opcode::MODI => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs_reg: u8 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    let rhs: i64 = {
+                        self.reg(rhs_reg)?.get()
+                    };
+                    if rhs == 0 {
+                        return Err(CoreError::DivisionByZero);
+                    }
+                    self.reg(target_reg)?.set(lhs.wrapping_rem(rhs));
+                },
+                Opcode::MODI_I => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs: i64 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    if rhs == 0 {
+                        return Err(CoreError::DivisionByZero);
+                    }
+                    self.reg(target_reg)?.set(lhs.wrapping_rem(rhs));
+                },
+                Opcode::BANDI => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs_reg: u8 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    let rhs: i64 = {
+                        self.reg(rhs_reg)?.get()
+                    };
+                    self.reg(target_reg)?.set(lhs & rhs);
+                },
+                Opcode::BANDI_I => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs: i64 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    self.reg(target_reg)?.set(lhs & rhs);
+                },
+                Opcode::BORI => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs_reg: u8 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    let rhs: i64 = {
+                        self.reg(rhs_reg)?.get()
+                    };
+                    self.reg(target_reg)?.set(lhs | rhs);
+                },
+                Opcode::BORI_I => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs: i64 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    self.reg(target_reg)?.set(lhs | rhs);
+                },
+                Opcode::BXORI => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs_reg: u8 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    let rhs: i64 = {
+                        self.reg(rhs_reg)?.get()
+                    };
+                    self.reg(target_reg)?.set(lhs ^ rhs);
+                },
+                Opcode::BXORI_I => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs: i64 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    self.reg(target_reg)?.set(lhs ^ rhs);
+                },
+                Opcode::SHLI => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs_reg: u8 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    let rhs: i64 = {
+                        self.reg(rhs_reg)?.get()
+                    };
+                    self.reg(target_reg)?.set(lhs.wrapping_shl(rhs as u32));
+                },
+                Opcode::SHLI_I => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs: i64 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    self.reg(target_reg)?.set(lhs.wrapping_shl(rhs as u32));
+                },
+                Opcode::SHRI => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs_reg: u8 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    let rhs: i64 = {
+                        self.reg(rhs_reg)?.get()
+                    };
+                    self.reg(target_reg)?.set(lhs.wrapping_shr(rhs as u32));
+                },
+                Opcode::SHRI_I => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs: i64 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs: i64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    self.reg(target_reg)?.set(lhs.wrapping_shr(rhs as u32));
+                },
                 Opcode::AND => {
                     let lhs_reg: u8 = self.get_op()?;
                     let rhs_reg: u8 = self.get_op()?;
@@ -935,6 +1268,121 @@ impl Core {
                     };
                     self.reg(target_reg)?.set(lhs >= rhs);
                 },
+                Opcode::BNDCHK => {
+                    let index_reg: u8 = self.get_op()?;
+                    let size: u64 = self.get_op()?;
+                    let index: i64 = {
+                        self.reg(index_reg)?.get()
+                    };
+                    if index < 0 || index as u64 >= size {
+                        return Err(CoreError::IndexOutOfBounds(index, size));
+                    }
+                },
+                Opcode::BNDCHK_R => {
+                    let index_reg: u8 = self.get_op()?;
+                    let size_reg: u8 = self.get_op()?;
+                    let index: i64 = {
+                        self.reg(index_reg)?.get()
+                    };
+                    let size: u64 = {
+                        self.reg(size_reg)?.get()
+                    };
+                    if index < 0 || index as u64 >= size {
+                        return Err(CoreError::IndexOutOfBounds(index, size));
+                    }
+                },
+                Opcode::ALLOC => {
+                    let size_reg: u8 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let size: u64 = {
+                        self.reg(size_reg)?.get()
+                    };
+                    self.collect_garbage_if_needed();
+                    let addr = self.alloc(size as usize);
+                    self.reg(target_reg)?.set(addr);
+                },
+                Opcode::ALLOC_I => {
+                    let size: u64 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    self.collect_garbage_if_needed();
+                    let addr = self.alloc(size as usize);
+                    self.reg(target_reg)?.set(addr);
+                },
+                Opcode::FREE => {
+                    let addr_reg: u8 = self.get_op()?;
+                    let addr: u64 = {
+                        self.reg(addr_reg)?.get()
+                    };
+                    self.free(addr)?;
+                },
+                Opcode::SCONCAT => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs_reg: u8 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs_addr: u64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    let rhs_addr: u64 = {
+                        self.reg(rhs_reg)?.get()
+                    };
+                    let target_addr: u64 = {
+                        self.reg(target_reg)?.get()
+                    };
+                    let mut data = self.mem_get_string_data(lhs_addr)?;
+                    data.append(&mut self.mem_get_string_data(rhs_addr)?);
+                    self.collect_garbage_if_needed();
+                    let data_addr = self.alloc_bytes(&data)?;
+                    self.mem_set((target_addr, 0), data.len() as u64)?;
+                    self.mem_set((target_addr, 8), data_addr)?;
+                },
+                Opcode::SCMP => {
+                    let lhs_reg: u8 = self.get_op()?;
+                    let rhs_reg: u8 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let lhs_addr: u64 = {
+                        self.reg(lhs_reg)?.get()
+                    };
+                    let rhs_addr: u64 = {
+                        self.reg(rhs_reg)?.get()
+                    };
+                    let lhs = self.mem_get_string_data(lhs_addr)?;
+                    let rhs = self.mem_get_string_data(rhs_addr)?;
+                    let ordering: i64 = match lhs.cmp(&rhs) {
+                        Ordering::Less => -1,
+                        Ordering::Equal => 0,
+                        Ordering::Greater => 1
+                    };
+                    self.reg(target_reg)?.set(ordering);
+                },
+                Opcode::SSLICE => {
+                    let string_reg: u8 = self.get_op()?;
+                    let start_reg: u8 = self.get_op()?;
+                    let end_reg: u8 = self.get_op()?;
+                    let target_reg: u8 = self.get_op()?;
+                    let string_addr: u64 = {
+                        self.reg(string_reg)?.get()
+                    };
+                    let start: i64 = {
+                        self.reg(start_reg)?.get()
+                    };
+                    let end: i64 = {
+                        self.reg(end_reg)?.get()
+                    };
+                    let target_addr: u64 = {
+                        self.reg(target_reg)?.get()
+                    };
+                    let string_size: u64 = self.mem_get((string_addr, 0))?;
+                    let data_addr: u64 = self.mem_get((string_addr, 8))?;
+                    if start < 0 || start > end {
+                        return Err(CoreError::IndexOutOfBounds(start, string_size));
+                    }
+                    if end as u64 > string_size {
+                        return Err(CoreError::IndexOutOfBounds(end, string_size));
+                    }
+                    // Slices share the data of the sliced string
+                    self.mem_set((target_addr, 0), (end - start) as u64)?;
+                    self.mem_set((target_addr, 8), data_addr + start as u64)?;
+                },
                 _ => {
                     return Err(CoreError::UnimplementedOpcode(opcode));
                 }
@@ -950,31 +1398,7 @@ impl Core {
         let source_addr = lhs_addr.real_address as usize;
         let target_addr = rhs_addr.real_address as usize;
 
-        let bytes = {
-            let source: &[u8] = match lhs_addr.address_type {
-                AddressType::Stack => {
-                    &self.stack
-                },
-                AddressType::Program => {
-                    let program = self.program.as_ref()
-                        .ok_or(CoreError::Unknown)?;
-                    &program.code
-                },
-                AddressType::Swap => {
-                    &self.swap
-                },
-                _ => return Err(CoreError::Unknown)
-            };
-            
-            let mut ret = Vec::with_capacity(n);
-            ret.resize(n, 0);
-
-            for i in 0..n {
-                ret[i] = source[source_addr + i];
-            }
-
-            ret
-        };
+        let bytes = self.mem_get_n(lhs, n)?;
 
         match rhs_addr.address_type {
             AddressType::Stack => {
@@ -994,13 +1418,17 @@ impl Core {
                     self.swap[target_addr + i] = bytes[i];
                 }
             },
+            AddressType::Heap => {
+                self.heap.write(target_addr, &bytes)?;
+            },
             _ => return Err(CoreError::Unknown)
         };
 
         Ok(())
     }
 
-    fn mem_get_n(&self, addr: (u64, i16), n: usize) -> CoreResult<Vec<u8>> {
+    /// Reads n bytes at an address with an offset
+    pub fn mem_get_n(&self, addr: (u64, i16), n: usize) -> CoreResult<Vec<u8>> {
         let mut data = Vec::with_capacity(n);
         data.resize(n, 0);
 
@@ -1010,6 +1438,10 @@ impl Core {
 
         let source_addr = lhs_addr.real_address as usize;
 
+        if lhs_addr.address_type == AddressType::Heap {
+            return self.heap.read(source_addr, n);
+        }
+
         let source: &[u8] = match lhs_addr.address_type {
             AddressType::Stack => {
                 &self.stack
@@ -1035,13 +1467,21 @@ impl Core {
     #[inline]
     pub fn mem_get_string(&self, addr: u64) -> CoreResult<String> {
         //println!("mem_get_string(): string addr: {:?}", Address::from(addr));
+        let string_data = self.mem_get_string_data(addr)?;
+        String::from_utf8(string_data)
+            .map_err(|_| CoreError::OperatorDeserialize)
+    }
+
+    /// Reads the raw bytes of the string whose (size, address) header is at `addr`
+    pub fn mem_get_string_data(&self, addr: u64) -> CoreResult<Vec<u8>> {
         let string_size: u64 = self.mem_get((addr, 0))?;
         //println!("String size: {}", string_size);
+        if string_size == 0 {
+            return Ok(Vec::new());
+        }
         let string_addr: u64 = self.mem_get((addr + 8, 0))?;
         //println!("String addr: {}", string_addr);
-        let string_data = self.mem_get_n((string_addr, 0), string_size as usize)?;
-        String::from_utf8(string_data)
-            .map_err(|_| CoreError::OperatorDeserialize)
+        self.mem_get_n((string_addr, 0), string_size as usize)
     }
 
     #[inline]
@@ -1078,12 +1518,142 @@ impl Core {
                     program.code[target_addr + i] = data[i];
                 }
             },
+            AddressType::Heap => {
+                self.heap.write(target_addr, &data[0..n])?;
+            },
             _ => return Err(CoreError::Unknown)
         };
 
         Ok(())
     }
 
+    /// Allocates `size` bytes on the heap and returns the tagged address
+    pub fn alloc(&mut self, size: usize) -> u64 {
+        let real_addr = self.heap.alloc(size);
+        self.gc_allocated_bytes += size;
+        Address::new(real_addr as u64, AddressType::Heap).into()
+    }
+
+    /// Copies `data` into a new heap allocation and returns the tagged address
+    pub fn alloc_bytes(&mut self, data: &[u8]) -> CoreResult<u64> {
+        let addr = self.alloc(data.len());
+        let real_addr = Address::from(addr).real_address as usize;
+        self.heap.write(real_addr, data)?;
+        Ok(addr)
+    }
+
+    /// Frees a heap allocation by its tagged address
+    pub fn free(&mut self, addr: u64) -> CoreResult<()> {
+        let heap_addr = Address::from(addr);
+        if heap_addr.address_type != AddressType::Heap {
+            return Err(CoreError::InvalidHeapPointer);
+        }
+        self.heap.free(heap_addr.real_address as usize)
+    }
+
+    /// Frees every heap allocation that can not be reached from the registers, the stack, the swap space or static variables.
+    /// Scanning is conservative: every 8 byte value pointing into a live allocation keeps it alive.
+    /// Unreachable foreign pointers are dropped as well, unless they are pinned.
+    /// Returns the amount of bytes that were freed.
+    pub fn collect_garbage(&mut self) -> usize {
+        let mut marked = HashSet::new();
+        let mut pending = Vec::new();
+        let mut marked_foreign = HashSet::new();
+
+        let saved_registers = self.call_stack.iter()
+            .flat_map(|(_, registers)| registers.iter())
+            .chain(self.try_stack.iter().flat_map(|frame| frame.registers.iter()));
+        for register in self.registers.iter().chain(saved_registers) {
+            self.mark_heap_value(register.get(), &mut marked, &mut pending, &mut marked_foreign);
+        }
+
+        let stack_size = self.get_stack_size().min(self.stack.len());
+        let roots = self.stack[..stack_size].windows(8)
+            .chain(self.swap.windows(8));
+        for window in roots {
+            self.mark_heap_value(Core::read_u64(window), &mut marked, &mut pending, &mut marked_foreign);
+        }
+
+        // Static variables live in the data segment of the program
+        if let Some(program) = self.program.as_ref() {
+            for range in program.static_vars.iter() {
+                for window in program.code[range.clone()].windows(8) {
+                    self.mark_heap_value(Core::read_u64(window), &mut marked, &mut pending, &mut marked_foreign);
+                }
+            }
+        }
+
+        // Allocations can point to other allocations
+        while let Some((start, size)) = pending.pop() {
+            let memory = match self.heap.read(start, size) {
+                Ok(memory) => memory,
+                Err(_) => continue
+            };
+            for window in memory.windows(8) {
+                self.mark_heap_value(Core::read_u64(window), &mut marked, &mut pending, &mut marked_foreign);
+            }
+        }
+
+        let freed_bytes = self.heap.sweep(&marked);
+        self.foreign_pointers.retain(|ptr, pointer| pointer.pinned || marked_foreign.contains(ptr));
+        self.gc_collections += 1;
+        self.gc_collected_bytes += freed_bytes;
+        self.gc_allocated_bytes = 0;
+        freed_bytes
+    }
+
+    /// Sets the amount of bytes allocated in between automatic garbage collections.
+    /// A threshold of 0 disables automatic garbage collection.
+    pub fn set_gc_threshold(&mut self, threshold: usize) {
+        self.gc_threshold = threshold;
+    }
+
+    pub fn get_heap_stats(&self) -> HeapStats {
+        HeapStats {
+            allocation_count: self.heap.allocation_count(),
+            allocated_bytes: self.heap.allocated_bytes(),
+            free_bytes: self.heap.free_bytes(),
+            heap_size: self.heap.size(),
+            collections: self.gc_collections,
+            collected_bytes: self.gc_collected_bytes
+        }
+    }
+
+    #[inline]
+    fn collect_garbage_if_needed(&mut self) {
+        if self.gc_threshold > 0 && self.gc_allocated_bytes >= self.gc_threshold {
+            self.collect_garbage();
+        }
+    }
+
+    /// Marks the allocation `value` points into, if it is a heap address, or the foreign pointer it is
+    fn mark_heap_value(&self, value: u64, marked: &mut HashSet<usize>, pending: &mut Vec<(usize, usize)>, marked_foreign: &mut HashSet<u64>) {
+        // Values with an unknown address type can not be pointers
+        if value >> 61 > 4 {
+            return;
+        }
+        let address = Address::from(value);
+        if address.address_type == AddressType::Foreign && self.foreign_pointers.contains_key(&value) {
+            marked_foreign.insert(value);
+            return;
+        }
+        if address.address_type != AddressType::Heap {
+            return;
+        }
+        if let Some((start, size)) = self.heap.get_allocation(address.real_address as usize) {
+            if marked.insert(start) {
+                pending.push((start, size));
+            }
+        }
+    }
+
+    #[inline]
+    fn read_u64(bytes: &[u8]) -> u64 {
+        let mut raw = [0; 8];
+        raw.copy_from_slice(&bytes[0..8]);
+        u64::from_le_bytes(raw)
+    }
+
     #[inline]
     pub fn reg(&mut self, reg: u8) -> CoreResult<&mut Register> {
         if reg == 16 {
@@ -1113,8 +1683,10 @@ impl Core {
         let new_ip = program.functions.get(&fn_uid)
             .ok_or(CoreError::UnknownFunctionUid)?;
         
+        // The callee is free to use every register,
+        // so the ones of the caller are restored on return
         let old_ip: usize = self.ip.get();
-        self.call_stack.push_front(old_ip);
+        self.call_stack.push_front((old_ip, self.registers));
         self.ip.set(*new_ip);
 
         Ok(())
@@ -1122,50 +1694,65 @@ impl Core {
 
     /// Retrieves a foreign pointer and returns the correct
     /// Arc<Mutex<T>> if found.
-    pub fn get_foreign_ptr<T>(&self, ptr: u64) -> CoreResult<Arc<Mutex<T>>> {
-        let arc_box_int = self.foreign_pointers.get(&ptr)
-            .ok_or(CoreError::Unknown)?;
-        let arc = unsafe {
-            let arc_box_raw: *mut Arc<Mutex<T>> = std::mem::transmute(*arc_box_int);
-            let arc_box = Box::from_raw(arc_box_raw);
-            let ret = arc_box.deref().clone();
-            std::mem::forget(arc_box);
-            ret
-        };
-        Ok(arc)
+    /// Fails if the pointer was inserted with a different type.
+    pub fn get_foreign_ptr<T: Send + 'static>(&self, ptr: u64) -> CoreResult<Arc<Mutex<T>>> {
+        self.foreign_pointers.get(&ptr)
+            .ok_or(CoreError::InvalidForeignPointer)?
+            .value
+            .downcast_ref::<Arc<Mutex<T>>>()
+            .cloned()
+            .ok_or(CoreError::ForeignTypeMismatch(type_name::<T>()))
     }
 
     /// Inserts a foreign pointer
-    pub fn insert_foreign_ptr<T>(&mut self, item: Arc<Mutex<T>>) -> CoreResult<u64> {
+    pub fn insert_foreign_ptr<T: Send + 'static>(&mut self, item: Arc<Mutex<T>>) -> CoreResult<u64> {
+        self.insert_pointer(Box::new(item), None)
+    }
+
+    /// Inserts a foreign pointer to an instance of the native container with the given canonical name
+    pub fn insert_native_ptr<T: Send + 'static>(&mut self, item: Arc<Mutex<T>>, container: &str) -> CoreResult<u64> {
+        self.insert_pointer(Box::new(item), Some(String::from(container)))
+    }
+
+    /// Pins or unpins a foreign pointer.
+    /// Unpinned pointers are dropped by the garbage collector once scripts can't reach them anymore.
+    pub fn set_foreign_ptr_pinned(&mut self, ptr: u64, pinned: bool) -> CoreResult<()> {
+        self.foreign_pointers.get_mut(&ptr)
+            .ok_or(CoreError::InvalidForeignPointer)?
+            .pinned = pinned;
+        Ok(())
+    }
+
+    /// Gets the canonical name of the native container a foreign pointer is an instance of
+    pub fn get_foreign_container(&self, ptr: u64) -> Option<&str> {
+        self.foreign_pointers.get(&ptr)
+            .and_then(|pointer| pointer.container.as_deref())
+    }
+
+    fn insert_pointer(&mut self, value: Box<dyn Any + Send>, container: Option<String>) -> CoreResult<u64> {
         let mut uid_gen = UIDGenerator::new();
 
-        let mut addr = Address::new(uid_gen.generate(), AddressType::Foreign);
+        // The 3 left most bits of an address hold its type
+        let mut addr = Address::new(uid_gen.generate() >> 3, AddressType::Foreign);
         while self.foreign_pointers.contains_key(&addr.raw_address) {
-            addr = Address::new(uid_gen.generate(), AddressType::Foreign);
+            addr = Address::new(uid_gen.generate() >> 3, AddressType::Foreign);
         }
 
         let ptr = addr.into();
-        
-        let arc_box = Box::new(item);
-        let arc_box_int: u64 = unsafe {
-            let arc_box_raw = Box::into_raw(arc_box);
-            std::mem::transmute(arc_box_raw)
-        };
-
-        self.foreign_pointers.insert(ptr, arc_box_int);
+        self.foreign_pointers.insert(ptr, ForeignPointer {
+            value,
+            container,
+            pinned: false
+        });
 
         Ok(ptr)
     }
 
-    /// Removes a foreign pointer
-    pub fn remove_foreign_ptr<T>(&mut self, ptr: u64) -> CoreResult<Arc<Mutex<T>>> {
-        let arc_box_int = self.foreign_pointers.remove(&ptr)
-            .ok_or(CoreError::Unknown)?;
-        let arc = unsafe {
-            let arc_box_raw: *mut Arc<Mutex<T>> = std::mem::transmute(arc_box_int);
-            let arc_box = Box::from_raw(arc_box_raw);
-            *arc_box
-        };
+    /// Removes a foreign pointer.
+    /// A pointer of a different type is left in place.
+    pub fn remove_foreign_ptr<T: Send + 'static>(&mut self, ptr: u64) -> CoreResult<Arc<Mutex<T>>> {
+        let arc = self.get_foreign_ptr(ptr)?;
+        self.foreign_pointers.remove(&ptr);
         Ok(arc)
     }
 
@@ -1180,23 +1767,43 @@ impl Core {
 
         //println!("Calling foreign function {}", function.name);
 
-        {
+        let (run_res, has_returned) = {
             let mut adapter = Adapter::new(&function, self);
-            function.run(&mut adapter);
-        }
+            let run_res = function.run(&mut adapter);
+            (run_res, adapter.has_returned())
+        };
+
+        // Non-primitive return values have to be pushed onto the stack by the function
+        let missing_return = !has_returned &&
+            function.return_type != Type::Void &&
+            !function.return_type.is_primitive();
 
+        let name = function.name.clone();
         self.program.as_mut()
             .ok_or(CoreError::NoProgram)?
             .foreign_functions
             .insert(uid, function);
 
+        if let Err(err) = run_res {
+            return Err(CoreError::ForeignFunction {
+                name: name,
+                message: err.message
+            });
+        }
+
+        if missing_return {
+            return Err(CoreError::NoReturnValue);
+        }
+
         Ok(())
     }
 
     #[inline]
     fn ret(&mut self) -> CoreResult<()> {
-        let old_ip = self.call_stack.pop_front()
+        let (old_ip, registers) = self.call_stack.pop_front()
             .ok_or(CoreError::EmptyCallStack)?;
+        // R0 holds the return value
+        self.registers[1..].copy_from_slice(&registers[1..]);
         self.ip.uint64 = old_ip as u64;
         Ok(())
     }
@@ -1238,6 +1845,10 @@ impl Core {
         let sp_addr = Address::from(self.sp.get::<u64>());
         let sp_real = sp_addr.real_address as usize;
 
+        if sp_real + op_size > self.stack.len() {
+            return Err(CoreError::StackOverflow);
+        }
+
         for i in 0..op_size {
             self.stack[sp_real + i] = raw_bytes[i];
         }
@@ -1248,6 +1859,38 @@ impl Core {
     }
 
     #[inline]
+    /// Pushes raw bytes on the stack, e.g. values already in the layout scripts use
+    pub fn push_stack_bytes(&mut self, bytes: &[u8]) -> CoreResult<()> {
+        let sp_real = self.get_stack_size();
+        if sp_real + bytes.len() > self.stack.len() {
+            return Err(CoreError::StackOverflow);
+        }
+        self.stack[sp_real..sp_real + bytes.len()].copy_from_slice(bytes);
+        self.sp.inc(bytes.len());
+        Ok(())
+    }
+
+    /// Pops n raw bytes off the stack
+    pub fn pop_stack_bytes(&mut self, n: usize) -> CoreResult<Vec<u8>> {
+        let sp_real = self.get_stack_size();
+        if n > sp_real {
+            return Err(CoreError::InvalidStackPointer);
+        }
+        let bytes = self.stack[sp_real - n..sp_real].to_vec();
+        self.sp.dec(n);
+        Ok(bytes)
+    }
+
+    /// Resets the stack to a previous size, e.g. after calling a function from the outside
+    pub fn set_stack_size(&mut self, size: usize) -> CoreResult<()> {
+        if size > self.stack.len() {
+            return Err(CoreError::InvalidStackPointer);
+        }
+        let address = Address::new(size as u64, AddressType::Stack);
+        self.sp.set::<u64>(address.into());
+        Ok(())
+    }
+
     pub fn pop_stack<T: DeserializeOwned>(&mut self) -> CoreResult<T> {
         let op_size = size_of::<T>();
 
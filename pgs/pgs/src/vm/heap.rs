@@ -0,0 +1,215 @@
+use super::{
+    core::{
+        CoreResult,
+        CoreError
+    }
+};
+
+use std::{
+    collections::{
+        BTreeMap,
+        HashSet
+    },
+    ops::{
+        Range
+    }
+};
+
+/// All allocations are aligned to (and a multiple of) this many bytes
+pub const HEAP_ALIGNMENT: usize = 8;
+
+/// Snapshot of the heap usage
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeapStats {
+    /// Amount of live allocations
+    pub allocation_count: usize,
+    /// Amount of bytes in live allocations
+    pub allocated_bytes: usize,
+    /// Amount of reusable bytes in between live allocations
+    pub free_bytes: usize,
+    /// Total size of the heap memory
+    pub heap_size: usize,
+    /// Amount of garbage collections that ran so far
+    pub collections: usize,
+    /// Amount of bytes reclaimed by all garbage collections so far
+    pub collected_bytes: usize
+}
+
+/// A first-fit allocator backing the heap address space
+#[derive(Default)]
+pub struct Heap {
+    memory: Vec<u8>,
+    /// Start address -> size of every live allocation
+    allocations: BTreeMap<usize, usize>,
+    /// Free blocks inside of memory, sorted by start and never adjacent to each other
+    free_list: Vec<Range<usize>>
+}
+
+impl Heap {
+    pub fn new() -> Heap {
+        Heap {
+            memory: Vec::new(),
+            allocations: BTreeMap::new(),
+            free_list: Vec::new()
+        }
+    }
+
+    /// Allocates a block of at least `size` bytes and returns its address
+    pub fn alloc(&mut self, size: usize) -> usize {
+        let size = Heap::align(size);
+
+        // Reuse the first free block that is big enough
+        let free_pos = self.free_list.iter()
+            .position(|block| block.len() >= size);
+
+        let addr = if let Some(pos) = free_pos {
+            let block = &mut self.free_list[pos];
+            let addr = block.start;
+            block.start += size;
+            if block.start == block.end {
+                self.free_list.remove(pos);
+            }
+            addr
+        } else {
+            // Grow the last free block if it touches the end of memory
+            let memory_end = self.memory.len();
+            let addr = match self.free_list.last() {
+                Some(block) if block.end == memory_end => block.start,
+                _ => memory_end
+            };
+            if addr != memory_end {
+                self.free_list.pop();
+            }
+            self.memory.resize(addr + size, 0);
+            addr
+        };
+
+        // Hand out zeroed memory
+        for byte in self.memory[addr..addr + size].iter_mut() {
+            *byte = 0;
+        }
+
+        self.allocations.insert(addr, size);
+        addr
+    }
+
+    /// Frees the allocation starting at `addr`
+    pub fn free(&mut self, addr: usize) -> CoreResult<()> {
+        let size = self.allocations.remove(&addr)
+            .ok_or(CoreError::InvalidHeapPointer)?;
+
+        let mut block = addr..addr + size;
+
+        let pos = self.free_list.iter()
+            .position(|free_block| free_block.start > addr)
+            .unwrap_or(self.free_list.len());
+
+        // Coalesce with the following block
+        if pos < self.free_list.len() && self.free_list[pos].start == block.end {
+            block.end = self.free_list[pos].end;
+            self.free_list.remove(pos);
+        }
+
+        // Coalesce with the preceding block
+        if pos > 0 && self.free_list[pos - 1].end == block.start {
+            self.free_list[pos - 1].end = block.end;
+        } else {
+            self.free_list.insert(pos, block);
+        }
+
+        // Give memory at the end back
+        let memory_end = self.memory.len();
+        let shrink_to = match self.free_list.last() {
+            Some(block) if block.end == memory_end => Some(block.start),
+            _ => None
+        };
+        if let Some(new_end) = shrink_to {
+            self.free_list.pop();
+            self.memory.truncate(new_end);
+        }
+
+        Ok(())
+    }
+
+    /// Reads `n` bytes at `addr`, which have to lie inside of a live allocation
+    pub fn read(&self, addr: usize, n: usize) -> CoreResult<Vec<u8>> {
+        self.check_range(addr, n)?;
+        Ok(
+            self.memory[addr..addr + n].to_vec()
+        )
+    }
+
+    /// Writes `data` to `addr`, which has to lie inside of a live allocation
+    pub fn write(&mut self, addr: usize, data: &[u8]) -> CoreResult<()> {
+        self.check_range(addr, data.len())?;
+        self.memory[addr..addr + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    /// Returns the (start, size) of the allocation containing `addr`
+    pub fn get_allocation(&self, addr: usize) -> Option<(usize, usize)> {
+        let (start, size) = self.allocations.range(..=addr)
+            .next_back()?;
+        if addr < start + size {
+            Some((*start, *size))
+        } else {
+            None
+        }
+    }
+
+    /// Frees every allocation whose start address is not contained in `marked`.
+    /// Returns the amount of bytes that were freed.
+    pub fn sweep(&mut self, marked: &HashSet<usize>) -> usize {
+        let garbage: Vec<(usize, usize)> = self.allocations.iter()
+            .filter(|(start, _)| !marked.contains(start))
+            .map(|(start, size)| (*start, *size))
+            .collect();
+
+        let mut freed_bytes = 0;
+        for (start, size) in garbage {
+            if self.free(start).is_ok() {
+                freed_bytes += size;
+            }
+        }
+        freed_bytes
+    }
+
+    /// Amount of live allocations
+    pub fn allocation_count(&self) -> usize {
+        self.allocations.len()
+    }
+
+    /// Amount of bytes in live allocations
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocations.values().sum()
+    }
+
+    /// Amount of bytes in free blocks that can be reused
+    pub fn free_bytes(&self) -> usize {
+        self.free_list.iter().map(|block| block.len()).sum()
+    }
+
+    /// Total size of the heap memory
+    pub fn size(&self) -> usize {
+        self.memory.len()
+    }
+
+    fn check_range(&self, addr: usize, n: usize) -> CoreResult<()> {
+        if n == 0 {
+            return Ok(());
+        }
+        let (start, size) = self.get_allocation(addr)
+            .ok_or(CoreError::InvalidHeapPointer)?;
+        if addr + n > start + size {
+            return Err(CoreError::InvalidHeapPointer);
+        }
+        Ok(())
+    }
+
+    fn align(size: usize) -> usize {
+        if size == 0 {
+            return HEAP_ALIGNMENT;
+        }
+        size.div_ceil(HEAP_ALIGNMENT) * HEAP_ALIGNMENT
+    }
+}
@@ -92,7 +92,32 @@ pub enum Opcode {
     LTF = 67,
     GTF = 68,
     LTEQF = 69,
-    GTEQF = 70
+    GTEQF = 70,
+    BNDCHK = 71,
+    ALLOC = 72,
+    ALLOC_I = 73,
+    FREE = 74,
+    SCONCAT = 75,
+    SCMP = 76,
+    SSLICE = 77,
+    NEGI = 78,
+    NEGF = 79,
+    MODI = 80,
+    MODI_I = 81,
+    BANDI = 82,
+    BANDI_I = 83,
+    BORI = 84,
+    BORI_I = 85,
+    BXORI = 86,
+    BXORI_I = 87,
+    SHLI = 88,
+    SHLI_I = 89,
+    SHRI = 90,
+    SHRI_I = 91,
+    TRY = 92,
+    ENDTRY = 93,
+    THROW = 94,
+    BNDCHK_R = 95
 }
 
 impl TryFrom<u8> for Opcode {
@@ -1,7 +1,11 @@
 pub mod core;
 
+// The opcodes are named after their mnemonics
+#[allow(clippy::upper_case_acronyms)]
 pub mod is;
 
 pub mod address;
 
+pub mod heap;
+
 pub mod register;
\ No newline at end of file
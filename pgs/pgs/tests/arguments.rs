@@ -0,0 +1,93 @@
+extern crate pgs;
+use pgs::prelude::*;
+
+fn main_arguments() -> Vec<(String, ValueType)> {
+    vec![
+        (String::from("count"), ValueType::Int),
+        (String::from("name"), ValueType::String),
+        (String::from("verbose"), ValueType::Bool),
+        (String::from("files"), ValueType::Array(Box::new(ValueType::String), 2))
+    ]
+}
+
+#[test]
+fn test_parse_arguments() {
+    let arguments = main_arguments();
+    let expected = vec![
+        Value::Int(3),
+        Value::String(String::from("bob")),
+        Value::Bool(true),
+        Value::Array(vec![
+            Value::String(String::from("a.txt")),
+            Value::String(String::from("--b.txt"))
+        ])
+    ];
+
+    let parsed = parse_arguments(&arguments, &["3", "bob", "--verbose", "a.txt", "--", "--b.txt"]);
+    assert_eq!(Ok(expected.clone()), parsed);
+
+    let parsed = parse_arguments(&arguments, &["--name", "bob", "--count=3", "--verbose=true", "a.txt", "--", "--b.txt"]);
+    assert_eq!(Ok(expected), parsed);
+
+    let parsed = parse_arguments(&arguments, &["3", "bob", "a.txt", "b.txt"]).unwrap();
+    assert_eq!(Value::Bool(false), parsed[2]);
+
+    assert_eq!("<count: int> <name: string> [--verbose] <files: [string; 2]>", get_usage(&arguments));
+}
+
+#[test]
+fn test_parse_auto_array_arguments() {
+    let arguments = vec![
+        (String::from("name"), ValueType::String),
+        (String::from("files"), ValueType::AutoArray(Box::new(ValueType::String))),
+        (String::from("verbose"), ValueType::Bool),
+        (String::from("count"), ValueType::Int)
+    ];
+    let files = |files: &[&str]| Value::AutoArray(files.iter().map(|file| Value::String(file.to_string())).collect());
+
+    let parsed = parse_arguments(&arguments, &["bob", "a.txt", "b.txt", "--verbose", "c.txt", "3"]);
+    assert_eq!(Ok(vec![
+        Value::String(String::from("bob")),
+        files(&["a.txt", "b.txt", "c.txt"]),
+        Value::Bool(true),
+        Value::Int(3)
+    ]), parsed);
+
+    // Named values don't need a positional value
+    let parsed = parse_arguments(&arguments, &["--count=3", "bob", "a.txt", "--", "--b.txt"]).unwrap();
+    assert_eq!(files(&["a.txt", "--b.txt"]), parsed[1]);
+
+    let parsed = parse_arguments(&arguments, &["bob", "3"]).unwrap();
+    assert_eq!(files(&[]), parsed[1]);
+
+    assert_eq!(Err(ArgumentError::MissingValue(String::from("count"))), parse_arguments(&arguments, &["bob"]));
+    assert_eq!(Err(ArgumentError::UnknownName(String::from("files"))), parse_arguments(&arguments, &["--files", "a.txt"]));
+    assert_eq!("<name: string> <files: [string]...> [--verbose] <count: int>", get_usage(&arguments));
+
+    let arguments = vec![
+        (String::from("files"), ValueType::AutoArray(Box::new(ValueType::String))),
+        (String::from("counts"), ValueType::AutoArray(Box::new(ValueType::Int)))
+    ];
+    assert_eq!(Err(ArgumentError::AmbiguousAutoArray(String::from("counts"))), parse_arguments(&arguments, &[]));
+}
+
+#[test]
+fn test_parse_arguments_errors() {
+    let arguments = main_arguments();
+
+    match parse_arguments(&arguments, &["three", "bob", "a", "b"]) {
+        Err(ArgumentError::InvalidValue { name, expected, .. }) => {
+            assert_eq!("count", name);
+            assert_eq!(ValueType::Int, expected);
+        },
+        res => panic!("Expected invalid value, got {:?}", res)
+    };
+    assert_eq!(Err(ArgumentError::MissingValue(String::from("files"))), parse_arguments(&arguments, &["3", "bob", "a"]));
+    assert_eq!(Err(ArgumentError::MissingValue(String::from("name"))), parse_arguments(&arguments, &["3", "a", "b", "--name"]));
+    assert_eq!(Err(ArgumentError::UnknownName(String::from("size"))), parse_arguments(&arguments, &["--size", "3"]));
+    assert_eq!(Err(ArgumentError::DuplicateValue(String::from("count"))), parse_arguments(&arguments, &["--count=1", "--count=2"]));
+    assert_eq!(Err(ArgumentError::TooManyValues(vec![String::from("c")])), parse_arguments(&arguments, &["3", "bob", "a", "b", "c"]));
+
+    let arguments = vec![(String::from("point"), ValueType::Container(String::from("Point")))];
+    assert!(matches!(parse_arguments(&arguments, &[]), Err(ArgumentError::UnsupportedType(_, _))));
+}
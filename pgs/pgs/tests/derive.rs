@@ -0,0 +1,132 @@
+extern crate pgs;
+use pgs::prelude::*;
+
+use std::sync::{
+    Arc,
+    Mutex
+};
+
+#[pgs_function]
+fn scale(value: i64, factor: f32) -> f32 {
+    value as f32 * factor
+}
+
+#[pgs_function]
+fn greet(name: String, excited: bool) -> String {
+    if excited {
+        format!("Hello, {}!", name)
+    } else {
+        format!("Hello, {}", name)
+    }
+}
+
+#[pgs_function]
+fn checked_div(lhs: i64, rhs: i64) -> ForeignResult<i64> {
+    if rhs == 0 {
+        return Err(ForeignError::new("division by zero"));
+    }
+    Ok(lhs / rhs)
+}
+
+#[derive(PgsContainer)]
+struct Account {
+    owner: String,
+    balance: i64,
+    #[pgs(skip)]
+    history: Vec<i64>
+}
+
+impl Account {
+    #[pgs_function(static)]
+    fn open(owner: String) -> Arc<Mutex<Account>> {
+        Arc::new(Mutex::new(Account {
+            owner,
+            balance: 0,
+            history: Vec::new()
+        }))
+    }
+
+    #[pgs_function]
+    fn deposit(&mut self, amount: i64) -> i64 {
+        self.history.push(amount);
+        self.balance += amount;
+        self.balance
+    }
+
+    #[pgs_function]
+    fn transactions(&self) -> i64 {
+        self.history.len() as i64
+    }
+}
+
+#[test]
+fn test_derive_function() {
+    let function = scale_function();
+    assert_eq!("scale", function.name);
+    assert_eq!(vec![ValueType::Int, ValueType::Float], function.arg_types());
+    assert_eq!(ValueType::Float, function.return_type());
+
+    let module = Module::new("util")
+        .with_function(scale_function())
+        .with_function(greet_function())
+        .with_function(checked_div_function());
+    let mut engine = Engine::new(1024);
+    assert!(engine.register_module(module).is_ok());
+
+    let code = "
+        fn: main() ~ float {
+            return util::scale(4, 2.5);
+        }
+
+        fn: hello(name: string) ~ string {
+            return util::greet(name, true);
+        }
+
+        fn: div(lhs: int, rhs: int) ~ int {
+            return util::checked_div(lhs, rhs);
+        }
+    ";
+    assert!(engine.load_code(code).is_ok());
+    assert_eq!(10.0, engine.call::<_, f32>("root::main", ()).unwrap());
+    assert_eq!(String::from("Hello, pgs!"), engine.call::<_, String>("root::hello", (String::from("pgs"),)).unwrap());
+    assert_eq!(3, engine.call::<_, i64>("root::div", (7i64, 2i64)).unwrap());
+    let err = engine.call::<_, i64>("root::div", (7i64, 0i64)).unwrap_err();
+    assert_eq!("error: foreign function checked_div failed: division by zero", err.to_string());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_derive_container() {
+    assert_eq!("Account", Account::container_name());
+    let container = Account::container()
+        .with_static_function(Account::open_function())
+        .with_function(Account::deposit_function())
+        .with_function(Account::transactions_function());
+    assert!(container.members.contains_key("owner"));
+    assert!(container.members.contains_key("balance"));
+    assert!(!container.members.contains_key("history"));
+
+    let mut engine = Engine::new(1024);
+    assert!(engine.register_module(Module::new("bank").with_container(container)).is_ok());
+
+    let code = "
+        fn: main() ~ int {
+            var account = bank::Account::open(\"bob\");
+            account.deposit(20);
+            account.deposit(22);
+            var balance = account.balance;
+            return balance + account.transactions();
+        }
+
+        fn: owner() ~ string {
+            var account = bank::Account::open(\"alice\");
+            return account.owner;
+        }
+    ";
+    let load_res = engine.load_code(code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+    assert_eq!(44, engine.call::<_, i64>("root::main", ()).unwrap());
+    assert_eq!(String::from("alice"), engine.call::<_, String>("root::owner", ()).unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
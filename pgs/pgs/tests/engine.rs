@@ -1,19 +1,17 @@
 extern crate pgs;
-use pgs::{
-    codegen::{
-        compiler::Compiler,
-        register::Register
-    },
-    parser::{
-        parser::Parser,
-        ast::Type
+use pgs::prelude::*;
+
+use std::{
+    sync::{
+        Arc,
+        Mutex,
+        atomic::{
+            AtomicUsize,
+            Ordering
+        }
     },
-    engine::Engine,
-    api::{
-        module::Module,
-        function::Function,
-        adapter::Adapter
-    }
+    io::Cursor,
+    ops::Deref
 };
 /*
 #[test]
@@ -28,16 +26,11 @@ fn test_engine_simple_function() {
     let load_res = engine.load_code(&code);
     assert!(load_res.is_ok());
 
-    let builder = engine.compiler.get_builder();
-    for instr in builder.instructions.iter() {
-        println!("{:?}", instr);
-    }
-
-    let run_res = engine.run_fn(&String::from("root::main"));
+    let run_res = engine.call::<_, i64>("root::main", ());
     println!("{:?}", run_res);
     assert!(run_res.is_ok());
 
-    let result_res = engine.get_register_value::<i64>(Register::R0);
+    let result_res = run_res;
     assert!(result_res.is_ok());
 
     assert_eq!(4, result_res.unwrap());
@@ -68,11 +61,11 @@ fn test_engine_if_else() {
         println!("{:?}", instr);
     }*/
 
-    let run_res = engine.run_fn("root::main");
+    let run_res = engine.call::<_, i64>("root::main", ());
     println!("{:?}", run_res);
     assert!(run_res.is_ok());
 
-    let result_res = engine.get_register_value::<i64>(Register::R0);
+    let result_res = run_res;
     assert!(result_res.is_ok());
 
     assert_eq!(1, result_res.unwrap());
@@ -101,18 +94,11 @@ fn test_engine_if_else_if() {
     println!("{:?}", load_res);
     assert!(load_res.is_ok());
 
-    let mut offset = 0;
-    let builder = engine.compiler.get_builder();
-    for instr in builder.instructions.iter() {
-        println!("{}: {:?}", offset, instr);
-        offset += instr.get_size();
-    }
-
-    let run_res = engine.run_fn(&String::from("root::main"));
+    let run_res = engine.call::<_, i64>("root::main", ());
     println!("{:?}", run_res);
     assert!(run_res.is_ok());
 
-    let result_res = engine.get_register_value::<i64>(Register::R0);
+    let result_res = run_res;
     assert!(result_res.is_ok());
 
     assert_eq!(3, result_res.unwrap());
@@ -141,11 +127,11 @@ fn test_engine_while() {
     println!("{:?}", load_res);
     assert!(load_res.is_ok());
 
-    let run_res = engine.run_fn("root::main");
+    let run_res = engine.call::<_, f32>("root::main", ());
     println!("{:?}", run_res);
     assert!(run_res.is_ok());
 
-    let reg_val_res = engine.get_register_value::<f32>(Register::R0);
+    let reg_val_res = run_res;
     println!("{:?}", reg_val_res);
     assert_eq!(7.0, reg_val_res.unwrap());
     assert_eq!(0, engine.get_stack_size());
@@ -179,28 +165,31 @@ fn test_engine_foreign_module() {
     ");
 
     let printi_function = Function::new("printi")
-        .with_arg(Type::Int)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::Int)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter: &mut Adapter| {
             //println!("Calling printi!");
-            let arg: i64 = adapter.get_arg(0);
+            let arg: i64 = adapter.get_arg(0)?;
             print!("{}", arg);
+            Ok(())
         }));
     let print_function = Function::new("print")
-        .with_arg(Type::String)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::String)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter: &mut Adapter| {
             //println!("Calling print!");
-            let arg: String = adapter.get_arg(0);
+            let arg: String = adapter.get_arg(0)?;
             print!("{}", arg);
+            Ok(())
         }));
     let println_function = Function::new("println")
-        .with_arg(Type::String)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::String)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter: &mut Adapter| {
             //println!("Calling println!");
-            let arg: String = adapter.get_arg(0);
+            let arg: String = adapter.get_arg(0)?;
             println!("{}", arg);
+            Ok(())
         }));
     
     let module = Module::new("std")
@@ -218,17 +207,11 @@ fn test_engine_foreign_module() {
     println!("{:?}", load_res);
     assert!(load_res.is_ok());
 
-    let mut offset = 0;
-    for instr in engine.compiler.get_builder().instructions.iter() {
-        //println!("{}: {:?}", offset, instr);
-        offset += instr.get_size();
-    }
-
-    let run_res = engine.run_fn("root::main");
+    let run_res = engine.call::<_, i64>("root::main", ());
     //println!("{:?}", run_res);
     assert!(run_res.is_ok());
 
-    let reg_val_res = engine.get_register_value::<i64>(Register::R0);
+    let reg_val_res = run_res;
     assert!(reg_val_res.is_ok());
     assert_eq!(0, engine.get_stack_size());
     assert_eq!(7, reg_val_res.unwrap());
@@ -268,35 +251,39 @@ fn test_engine_cont_simple() {
         }
     ");
     let printf_function = Function::new("printf")
-        .with_arg(Type::Float)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::Float)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter| {
-            let arg: f32 = adapter.get_arg(0);
+            let arg: f32 = adapter.get_arg(0)?;
             print!("{}", arg);
+            Ok(())
         }));
     let printi_function = Function::new("printi")
-        .with_arg(Type::Int)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::Int)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter: &mut Adapter| {
             //println!("Calling printi!");
-            let arg: i64 = adapter.get_arg(0);
+            let arg: i64 = adapter.get_arg(0)?;
             print!("{}", arg);
+            Ok(())
         }));
     let print_function = Function::new("print")
-        .with_arg(Type::String)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::String)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter: &mut Adapter| {
             //println!("Calling print!");
-            let arg: String = adapter.get_arg(0);
+            let arg: String = adapter.get_arg(0)?;
             print!("{}", arg);
+            Ok(())
         }));
     let println_function = Function::new("println")
-        .with_arg(Type::String)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::String)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter: &mut Adapter| {
             //println!("Calling println!");
-            let arg: String = adapter.get_arg(0);
+            let arg: String = adapter.get_arg(0)?;
             println!("{}", arg);
+            Ok(())
         }));
     let std_module = Module::new("std")
         .with_function(printi_function)
@@ -314,13 +301,7 @@ fn test_engine_cont_simple() {
     println!("{:?}", load_res);
     assert!(load_res.is_ok());
 
-    let mut offset = 0;
-    for instr in engine.compiler.get_builder().instructions.iter() {
-        println!("{}: {:?}", offset, instr);
-        offset += instr.get_size();
-    }
-
-    let run_res = engine.run_fn("root::main");
+    let run_res = engine.call::<_, i64>("root::main", ());
     //println!("{:?}", run_res);
     assert!(run_res.is_ok());
 }
@@ -368,35 +349,39 @@ fn test_engine_member_call() {
     ");
 
     let printf_function = Function::new("printf")
-        .with_arg(Type::Float)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::Float)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter| {
-            let arg: f32 = adapter.get_arg(0);
+            let arg: f32 = adapter.get_arg(0)?;
             print!("{}", arg);
+            Ok(())
         }));
     let printi_function = Function::new("printi")
-        .with_arg(Type::Int)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::Int)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter: &mut Adapter| {
             //println!("Calling printi!");
-            let arg: i64 = adapter.get_arg(0);
+            let arg: i64 = adapter.get_arg(0)?;
             print!("{}", arg);
+            Ok(())
         }));
     let print_function = Function::new("print")
-        .with_arg(Type::String)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::String)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter: &mut Adapter| {
             //println!("Calling print!");
-            let arg: String = adapter.get_arg(0);
+            let arg: String = adapter.get_arg(0)?;
             print!("{}", arg);
+            Ok(())
         }));
     let println_function = Function::new("println")
-        .with_arg(Type::String)
-        .with_ret_type(Type::Void)
+        .with_arg(ValueType::String)
+        .with_ret_type(ValueType::Void)
         .with_closure(Box::new(|adapter: &mut Adapter| {
             //println!("Calling println!");
-            let arg: String = adapter.get_arg(0);
+            let arg: String = adapter.get_arg(0)?;
             println!("{}", arg);
+            Ok(())
         }));
     let std_module = Module::new("std")
         .with_function(printi_function)
@@ -424,9 +409,1827 @@ fn test_engine_member_call() {
     }
     */
 
-    let run_res = engine.run_fn("root::main");
+    let run_res = engine.call::<_, i64>("root::main", ());
 
     assert_eq!(engine.get_stack_size(), 0);
     println!("{:?}", run_res);
     assert!(run_res.is_ok());
-}
\ No newline at end of file
+}
+#[test]
+fn test_engine_early_return() {
+    let code = String::from("
+        fn: early(x: int) ~ int {
+            var a: int = 1;
+            if x > 0 {
+                var b: int = 2;
+                return a + b;
+            }
+            var c: int = 5;
+            return a + c;
+        }
+
+        fn: main() ~ int {
+            var first: int = early(1);
+            var second: int = early(0);
+            return first * 10 + second;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let result_res = run_res;
+    assert!(result_res.is_ok());
+    assert_eq!(36, result_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_loop_break() {
+    let code = String::from("
+        fn: main() ~ int {
+            var x = 0;
+            loop {
+                x += 1;
+                if x == 5 {
+                    break;
+                }
+            }
+            return x;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(5, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_loop_nested() {
+    let code = String::from("
+        fn: main() ~ int {
+            var sum = 0;
+            var i = 0;
+            loop {
+                if i == 4 {
+                    break;
+                }
+                i += 1;
+                var j = 0;
+                loop {
+                    j += 1;
+                    if j == 2 {
+                        continue;
+                    }
+                    if j > 3 {
+                        break;
+                    }
+                    sum += j;
+                }
+            }
+            return sum;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(16, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_for() {
+    let code = String::from("
+        fn: main() ~ int {
+            var sum = 0;
+            for i in 0..5 {
+                sum += i;
+            }
+            for i in 1..=3 {
+                sum += i;
+            }
+            return sum;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(16, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_for_break_continue() {
+    let code = String::from("
+        fn: main() ~ int {
+            var n = 10;
+            var sum = 0;
+            for i in 0..n {
+                if i == 2 {
+                    continue;
+                }
+                for j in 0..i {
+                    if j == 1 {
+                        break;
+                    }
+                    sum += 100;
+                }
+                if i == 6 {
+                    break;
+                }
+                sum += i;
+            }
+            return sum;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(513, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_array() {
+    let code = String::from("
+        fn: sum(values: &[int; 4]) ~ int {
+            var ret = 0;
+            for i in 0..4 {
+                ret += values[i];
+            }
+            return ret;
+        }
+
+        fn: main() ~ int {
+            var values: [int; 4] = [1, 2, 3, 4];
+            values[2] = 10;
+            values[0] += 4;
+            var grid = [[1, 2], [3, 4]];
+            grid[1][0] = 7;
+            return sum(&values) + grid[1][0] * grid[0][1];
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(35, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_array_literal_index() {
+    // Only arrays stored in variables can be indexed, other arrays are rejected instead of misread
+    let code = String::from("fn: main() ~ int {
+    var t = [10, 20][1];
+    return t;
+}
+");
+
+    let mut engine = Engine::new(1024);
+    let err = match engine.load_source("script.pgs", &code) {
+        Err(err) => err,
+        Ok(_) => panic!("Expected an UnsupportedExpression error")
+    };
+    match &*err {
+        EngineError::Located(inner, _) => {
+            match inner.deref() {
+                EngineError::CompileError(CompilerError::UnsupportedExpression(_)) => {},
+                _ => panic!("Expected an UnsupportedExpression error, got {:?}", inner)
+            };
+        },
+        _ => panic!("Expected a located error, got {:?}", err)
+    };
+    assert_eq!("[10, 20]", &code[err.diagnostic().unwrap().span.clone()]);
+}
+
+#[test]
+fn test_engine_array_out_of_bounds() {
+    let code = String::from("
+        fn: main() ~ int {
+            var values = [1.0, 2.0, 3.0];
+            var i = 3;
+            values[i] = 4.0;
+            return 0;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    match run_res {
+        Err(err) => {
+            match *err {
+                EngineError::CoreError(CoreError::IndexOutOfBounds(3, 3)) => {},
+                _ => panic!("Expected an IndexOutOfBounds error, got {:?}", err)
+            };
+        },
+        Ok(_) => panic!("Expected an IndexOutOfBounds error")
+    };
+}
+
+#[test]
+fn test_engine_heap() {
+    let code = String::from("
+        cont: Point {
+            x: int;
+            y: int;
+        }
+
+        fn: make_point(x: int, y: int) ~ &Point {
+            return new Point {
+                x: x,
+                y: y
+            };
+        }
+
+        fn: main() ~ int {
+            var point = make_point(3, 4);
+            var counter = new 10;
+            ~counter += point.y;
+            var scale = ~counter;
+            var ret = point.x;
+            free point;
+            free counter;
+            return ret * scale;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(42, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_double_free() {
+    let code = String::from("
+        fn: main() ~ int {
+            var value = new 1;
+            free value;
+            free value;
+            return 0;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    match run_res {
+        Err(err) => {
+            match *err {
+                EngineError::CoreError(CoreError::InvalidHeapPointer) => {},
+                _ => panic!("Expected an InvalidHeapPointer error, got {:?}", err)
+            };
+        },
+        Ok(_) => panic!("Expected an InvalidHeapPointer error")
+    };
+}
+
+#[test]
+fn test_engine_garbage_collection() {
+    let code = String::from("
+        cont: Boxed {
+            value: &int;
+        }
+
+        fn: main() ~ int {
+            var boxed = new Boxed {
+                value: new 7
+            };
+            var sum = 0;
+            for i in 0..100 {
+                var tmp = new i;
+                sum += ~tmp;
+            }
+            var value = ~boxed.value;
+            return sum + value;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    engine.set_gc_threshold(64);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(4957, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+
+    let stats = engine.get_heap_stats();
+    println!("{:?}", stats);
+    assert!(stats.collections > 0);
+    assert!(stats.allocation_count < 102);
+
+    engine.collect_garbage();
+    let stats: HeapStats = engine.get_heap_stats();
+    println!("{:?}", stats);
+    assert!(stats.allocation_count <= 2);
+}
+
+#[test]
+fn test_engine_dynamic_strings() {
+    let code = String::from("
+        fn: greet(name: string) ~ string {
+            return \"Hello, \" + name + \"!\";
+        }
+
+        fn: main() ~ int {
+            var greeting = greet(\"World\");
+            std::record(greeting);
+            var ret = len(greeting);
+            if greeting[7..12] == \"World\" {
+                ret += 100;
+            }
+            if \"abc\" < \"abd\" && greeting != \"\" {
+                ret += 1000;
+            }
+            return ret;
+        }
+    ");
+
+    let recorded = Arc::new(Mutex::new(String::new()));
+    let recorded_clone = recorded.clone();
+    let record_function = Function::new("record")
+        .with_arg(ValueType::String)
+        .with_ret_type(ValueType::Void)
+        .with_closure(Box::new(move |adapter: &mut Adapter| {
+            let arg: String = adapter.get_arg(0)?;
+            *recorded_clone.lock().unwrap() = arg;
+            Ok(())
+        }));
+    let module = Module::new("std")
+        .with_function(record_function);
+
+    let mut engine = Engine::new(1024);
+    let reg_res = engine.register_module(module);
+    assert!(reg_res.is_ok());
+
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(1113, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+    assert_eq!("Hello, World!", recorded.lock().unwrap().as_str());
+}
+
+#[test]
+fn test_engine_string_slice_out_of_bounds() {
+    let code = String::from("
+        fn: main() ~ int {
+            var name = \"pgs\";
+            var slice = name[1..4];
+            return 0;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    match run_res {
+        Err(err) => {
+            match *err {
+                EngineError::CoreError(CoreError::IndexOutOfBounds(4, 3)) => {},
+                _ => panic!("Expected an IndexOutOfBounds error, got {:?}", err)
+            };
+        },
+        Ok(_) => panic!("Expected an IndexOutOfBounds error")
+    };
+}
+
+#[test]
+fn test_engine_foreign_string_return() {
+    let code = String::from("
+        fn: main() ~ int {
+            var line = std::repeat(\"ab\", 3) + \"!\";
+            std::record(line);
+            if std::repeat(\"x\", 0) == \"\" {
+                return len(line);
+            }
+            return 0;
+        }
+    ");
+
+    let recorded = Arc::new(Mutex::new(String::new()));
+    let recorded_clone = recorded.clone();
+    let repeat_function = Function::new("repeat")
+        .with_arg(ValueType::String)
+        .with_arg(ValueType::Int)
+        .with_ret_type(ValueType::String)
+        .with_closure(Box::new(|adapter: &mut Adapter| {
+            let string: String = adapter.get_arg(0)?;
+            let count: i64 = adapter.get_arg(1)?;
+            adapter.return_value(string.repeat(count as usize))
+        }));
+    let record_function = Function::new("record")
+        .with_arg(ValueType::String)
+        .with_ret_type(ValueType::Void)
+        .with_closure(Box::new(move |adapter: &mut Adapter| {
+            let arg: String = adapter.get_arg(0)?;
+            *recorded_clone.lock().unwrap() = arg;
+            Ok(())
+        }));
+    let module = Module::new("std")
+        .with_function(repeat_function)
+        .with_function(record_function);
+
+    let mut engine = Engine::new(1024);
+    let reg_res = engine.register_module(module);
+    assert!(reg_res.is_ok());
+
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(7, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+    assert_eq!("ababab!", recorded.lock().unwrap().as_str());
+}
+
+#[test]
+fn test_engine_foreign_missing_return() {
+    let code = String::from("
+        fn: main() ~ int {
+            var name = std::name();
+            return 0;
+        }
+    ");
+
+    let name_function = Function::new("name")
+        .with_ret_type(ValueType::String)
+        .with_closure(Box::new(|_: &mut Adapter| Ok(())));
+    let module = Module::new("std")
+        .with_function(name_function);
+
+    let mut engine = Engine::new(1024);
+    let reg_res = engine.register_module(module);
+    assert!(reg_res.is_ok());
+
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    match run_res {
+        Err(err) => {
+            match *err {
+                EngineError::CoreError(CoreError::NoReturnValue) => {},
+                _ => panic!("Expected a NoReturnValue error, got {:?}", err)
+            };
+        },
+        Ok(_) => panic!("Expected a NoReturnValue error")
+    };
+}
+
+#[test]
+fn test_engine_foreign_return_overflow() {
+    let code = String::from("
+        fn: main() ~ int {
+            var a = 1;
+            var b = 2;
+            var name = std::name();
+            return a + b;
+        }
+    ");
+
+    let name_function = Function::new("name")
+        .with_ret_type(ValueType::String)
+        .with_closure(Box::new(|adapter: &mut Adapter| {
+            adapter.return_value("pgs")
+        }));
+    let module = Module::new("std")
+        .with_function(name_function);
+
+    // The returned string doesn't fit onto the stack anymore
+    let mut engine = Engine::new(24);
+    let reg_res = engine.register_module(module);
+    assert!(reg_res.is_ok());
+
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    match run_res {
+        Err(err) => {
+            match *err {
+                EngineError::CoreError(CoreError::ForeignFunction { ref name, ref message }) => {
+                    assert_eq!("name", name);
+                    assert_eq!("StackOverflow", message);
+                },
+                _ => panic!("Expected a ForeignFunction error, got {:?}", err)
+            };
+        },
+        Ok(_) => panic!("Expected a ForeignFunction error")
+    };
+}
+
+#[test]
+fn test_engine_unary_minus() {
+    let code = String::from("
+        fn: abs(x: float) ~ float {
+            if x < 0.0 {
+                return -x;
+            }
+            return x;
+        }
+
+        fn: main() ~ int {
+            var a = -5;
+            var b = 3;
+            var ret = -a * -b + -(b - 10);
+            if abs(-6.3) == 6.3 {
+                ret -= -100;
+            }
+            return ret;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(92, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_bitwise_ops() {
+    let code = String::from("
+        fn: is_even(x: int) ~ bool {
+            return x % 2 == 0;
+        }
+
+        fn: main() ~ int {
+            var flags = 0;
+            flags = flags | 1 << 3;
+            flags = flags | 1 << 1;
+            var ret = flags & 10;
+            ret = ret ^ 2;
+            ret += -64 >> 4;
+            if is_even(ret) {
+                ret = ret << 2;
+            }
+            var m = 3;
+            ret += 2 * 3 % 4 * 100;
+            return ret + 17 % m;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(218, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_modulo_by_zero() {
+    let code = String::from("
+        fn: main() ~ int {
+            var a = 5;
+            var b = 0;
+            return a % b;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    match run_res {
+        Err(err) => {
+            match *err {
+                EngineError::CoreError(CoreError::DivisionByZero) => {},
+                _ => panic!("Expected a DivisionByZero error, got {:?}", err)
+            };
+        },
+        Ok(_) => panic!("Expected a DivisionByZero error")
+    };
+}
+
+#[test]
+fn test_engine_cont_member_order() {
+    let code = String::from("
+        cont: Pair {
+            second: int;
+            first: float;
+        }
+
+        fn: main() ~ int {
+            var pair = Pair {
+                second: 2,
+                first: 1.0
+            };
+            pair.second += 10;
+            if pair.first == 1.0 {
+                return pair.second;
+            }
+            return 0;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(12, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_call_in_expression() {
+    let code = String::from("
+        fn: square(x: int) ~ int {
+            var y = x * x;
+            return y + 0;
+        }
+
+        fn: main() ~ int {
+            var a = 1;
+            return a + square(2) * 10 + square(3);
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(50, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_method_call() {
+    let code = String::from("
+        cont: Vector {
+            x: int;
+            y: int;
+        }
+
+        cont: Line {
+            start: Vector;
+            end: Vector;
+            weight: int;
+        }
+
+        impl: Vector {
+            fn: sum(&this) ~ int {
+                return this.x + this.y;
+            }
+            fn: scaled(&this, factor: int) ~ Vector {
+                return Vector {
+                    x: this.x * factor,
+                    y: this.y * factor
+                };
+            }
+        }
+
+        impl: Line {
+            fn: get_end(&this) ~ &Vector {
+                return &this.end;
+            }
+            fn: get_weight(&this) ~ int {
+                return this.weight;
+            }
+        }
+
+        fn: main() ~ int {
+            var line = Line {
+                start: Vector {
+                    x: 1,
+                    y: 2
+                },
+                end: Vector {
+                    x: 3,
+                    y: 4
+                },
+                weight: 5
+            };
+            var line_ref = &line;
+            var ret = line.start.sum();
+            ret += line.get_weight() * 10;
+            ret += line_ref.get_end().sum() * 100;
+            ret += line.end.scaled(2).scaled(5).sum() * 1000;
+            return ret;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(70753, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_static_vars() {
+    let code = String::from("
+        mod: config {
+            var value = 42;
+        }
+
+        import: config::value;
+
+        cont: Vector {
+            x: int;
+            y: int;
+        }
+
+        var counter: int = 5;
+        var vec = Vector {
+            x: 3,
+            y: 4
+        };
+        var greeting = \"hello\";
+
+        fn: inc() {
+            counter += 1;
+        }
+
+        fn: bump() {
+            vec.y += 10;
+            greeting = greeting + \"!\";
+        }
+
+        fn: shadow() ~ int {
+            var counter = 100;
+            return counter;
+        }
+
+        fn: main() ~ int {
+            inc();
+            inc();
+            bump();
+            value += 1;
+            var ret = counter + shadow() - 100;
+            ret += config::value * 10;
+            ret += vec.y * 1000;
+            ret += len(greeting) * 100000;
+            return ret;
+        }
+    ");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(&code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let run_res = engine.call::<_, i64>("root::main", ());
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert!(reg_val_res.is_ok());
+    assert_eq!(614437, reg_val_res.unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_compile_error_location() {
+    let code = String::from("fn: main() ~ int {
+    var x = 1;
+    return x + y;
+}
+");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_source("script.pgs", &code);
+    let err = match load_res {
+        Err(err) => err,
+        Ok(_) => panic!("Expected an UnknownVariable error")
+    };
+    match &*err {
+        EngineError::Located(inner, _) => {
+            match inner.deref() {
+                EngineError::CompileError(CompilerError::UnknownVariable(var_name)) => {
+                    assert_eq!("y", var_name);
+                },
+                _ => panic!("Expected an UnknownVariable error, got {:?}", inner)
+            };
+        },
+        _ => panic!("Expected a located error, got {:?}", err)
+    };
+
+    let diagnostic = err.diagnostic().unwrap();
+    assert_eq!(String::from("script.pgs"), diagnostic.location.file);
+    assert_eq!(3, diagnostic.location.line);
+    assert_eq!(16, diagnostic.location.column);
+    assert_eq!(
+        "error: UnknownVariable(\"y\")\n --> script.pgs:3:16\n  |\n3 |     return x + y;\n  |                ^",
+        diagnostic.render()
+    );
+}
+
+#[test]
+fn test_engine_compile_error_sub_expression() {
+    let code = String::from("fn: main() ~ int {
+    var x = 2 * (1 + \"a\") - 3;
+    return x;
+}
+");
+
+    let mut engine = Engine::new(1024);
+    let err = match engine.load_source("script.pgs", &code) {
+        Err(err) => err,
+        Ok(_) => panic!("Expected a TypeMismatch error")
+    };
+    let diagnostic = err.diagnostic().unwrap();
+    assert_eq!("1 + \"a\"", &code[diagnostic.span.clone()]);
+
+    // The failed declarations don't stay around for the next load
+    assert!(engine.load_source("script.pgs", "fn: main() ~ int { return 1; }").is_ok());
+    assert!(engine.call::<_, i64>("root::main", ()).is_ok());
+}
+
+#[test]
+fn test_engine_parse_error_location() {
+    let code = String::from("fn: main() ~ int {
+    return 0;
+}
+cont Vector {}
+");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_source("script.pgs", &code);
+    let err = match load_res {
+        Err(err) => err,
+        Ok(_) => panic!("Expected a parse error")
+    };
+    match &*err {
+        EngineError::Located(inner, _) => {
+            match inner.deref() {
+                EngineError::ParseError(_) => {},
+                _ => panic!("Expected a parse error, got {:?}", inner)
+            };
+        },
+        _ => panic!("Expected a located error, got {:?}", err)
+    };
+
+    let diagnostic = err.diagnostic().unwrap();
+    assert_eq!(4, diagnostic.location.line);
+    assert!(diagnostic.render().contains("4 | cont Vector {}"));
+}
+
+#[test]
+fn test_engine_multiple_errors() {
+    let code = String::from("fn: main() ~ int {
+    return a;
+}
+
+fn: valid() ~ int {
+    return 1;
+}
+
+fn: other() ~ int {
+    if true {
+        var z = nope(1);
+    }
+    return 0;
+}
+");
+
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_source("script.pgs", &code);
+    let err = match load_res {
+        Err(err) => err,
+        Ok(_) => panic!("Expected compile errors")
+    };
+    match &*err {
+        EngineError::Multiple(errors) => assert_eq!(2, errors.len()),
+        _ => panic!("Expected multiple errors, got {:?}", err)
+    };
+
+    let diagnostics = err.diagnostics();
+    assert_eq!(2, diagnostics.len());
+    assert_eq!("UnknownVariable(\"a\")", diagnostics[0].message);
+    assert_eq!(2, diagnostics[0].location.line);
+    assert_eq!("UnknownFunction(\"nope\")", diagnostics[1].message);
+    assert_eq!(11, diagnostics[1].location.line);
+}
+
+fn bytecode_math_module(ret_type: ValueType) -> Module {
+    let add_function = Function::new("add")
+        .with_arg(ValueType::Int)
+        .with_arg(ValueType::Int)
+        .with_ret_type(ret_type)
+        .with_closure(Box::new(|adapter: &mut Adapter| {
+            let lhs: i64 = adapter.get_arg(0)?;
+            let rhs: i64 = adapter.get_arg(1)?;
+            adapter.return_value(lhs + rhs)
+        }));
+    Module::new("math")
+        .with_function(add_function)
+}
+
+const BYTECODE_CODE: &str = "
+    import: math::add;
+
+    var offset = 100;
+
+    fn: twice(x: int) ~ int {
+        return x * 2;
+    }
+
+    fn: scale(x: float, factor: int) ~ float {
+        if factor == 2 {
+            return x * 2.0;
+        }
+        return x;
+    }
+
+    fn: main() ~ int {
+        var s = \"abc\";
+        if s == \"abc\" {
+            return add(twice(offset), 3);
+        }
+        return 0;
+    }
+";
+
+#[test]
+fn test_engine_bytecode_roundtrip() {
+    let mut engine = Engine::new(1024);
+    assert!(engine.register_module(bytecode_math_module(ValueType::Int)).is_ok());
+    let bytes = engine.precompile("script.pgs", BYTECODE_CODE).unwrap();
+    assert!(bytes.starts_with(b"PGC\0"));
+
+    let mut engine = Engine::new(1024);
+    assert!(engine.register_module(bytecode_math_module(ValueType::Int)).is_ok());
+    let load_res = engine.load_bytecode(&bytes);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    let main = ScriptFunction::new("root::main")
+        .with_ret_type(ValueType::Int);
+    let run_res = engine.call_function(&main, &[]);
+    println!("{:?}", run_res);
+    assert!(run_res.is_ok());
+
+    let reg_val_res = run_res;
+    assert_eq!(0, engine.get_stack_size());
+    assert_eq!(Some(Value::Int(203)), reg_val_res.unwrap());
+
+    // Script functions keep their signatures
+    let names: Vec<String> = engine.get_functions().into_iter()
+        .map(|function| function.name)
+        .collect();
+    assert_eq!(vec!["root::main", "root::scale", "root::twice"], names);
+    let scale = engine.get_function("root::scale").unwrap();
+    assert_eq!(vec![(String::from("x"), ValueType::Float), (String::from("factor"), ValueType::Int)], scale.arguments());
+    assert_eq!(ValueType::Float, scale.return_type());
+    assert_eq!(1.5, engine.call::<_, f32>("root::scale", (0.75f32, 2i64)).unwrap());
+    assert_eq!(8, engine.call::<_, i64>("root::twice", (4i64,)).unwrap());
+    assert!(engine.call::<_, i64>("root::twice", (4.0f32,)).is_err());
+    assert!(engine.call_function(&scale, &[Value::Int(1), Value::Int(2)]).is_err());
+    assert_eq!(0, engine.get_stack_size());
+}
+
+#[test]
+fn test_engine_bytecode_link_errors() {
+    let mut engine = Engine::new(1024);
+    assert!(engine.register_module(bytecode_math_module(ValueType::Int)).is_ok());
+    let bytes = engine.precompile("script.pgs", BYTECODE_CODE).unwrap();
+
+    let mut engine = Engine::new(1024);
+    match engine.load_bytecode(&bytes).map_err(|err| *err) {
+        Err(EngineError::BytecodeError(BytecodeError::MissingForeignFunction(name))) => assert_eq!("root::math::add", name),
+        res => panic!("Expected missing foreign function, got {:?}", res)
+    };
+
+    let mut engine = Engine::new(1024);
+    assert!(engine.register_module(bytecode_math_module(ValueType::Float)).is_ok());
+    match engine.load_bytecode(&bytes).map_err(|err| *err) {
+        Err(EngineError::BytecodeError(BytecodeError::ForeignFunctionMismatch { name, .. })) => assert_eq!("root::math::add", name),
+        res => panic!("Expected foreign function mismatch, got {:?}", res)
+    };
+
+    let mut engine = Engine::new(1024);
+    let mut newer = bytes.clone();
+    newer[4] = 0xFF;
+    match engine.load_bytecode(&newer).map_err(|err| *err) {
+        Err(EngineError::BytecodeError(BytecodeError::UnsupportedVersion(_))) => {},
+        res => panic!("Expected unsupported version, got {:?}", res)
+    };
+    match engine.load_bytecode(&bytes[..bytes.len() - 3]).map_err(|err| *err) {
+        Err(EngineError::BytecodeError(BytecodeError::Malformed)) => {},
+        res => panic!("Expected malformed bytecode, got {:?}", res)
+    };
+}
+
+#[test]
+fn test_engine_eval() {
+    let mut engine = Engine::new(1024);
+
+    assert_eq!(None, engine.eval("var x = 5;").unwrap());
+    assert_eq!(Some(String::from("10")), engine.eval("x * 2").unwrap());
+
+    let fn_decl = "
+        fn: square(a: int) ~ int {
+            return a * a;
+        }
+    ";
+    assert_eq!(None, engine.eval(fn_decl).unwrap());
+    assert_eq!(Some(String::from("26")), engine.eval("square(x) + 1").unwrap());
+
+    assert_eq!(None, engine.eval("while x < 8 { x += 1; }").unwrap());
+    assert_eq!(Some(String::from("8")), engine.eval("x").unwrap());
+
+    assert_eq!(None, engine.eval("cont: Point { x: int; y: float; }").unwrap());
+    assert_eq!(None, engine.eval("var p = Point { x: x, y: 0.5 };").unwrap());
+    assert_eq!(Some(String::from("Point { x: 8, y: 0.5 }")), engine.eval("p").unwrap());
+
+    assert_eq!(Some(String::from("\"abc\"")), engine.eval("var s = \"abc\"; s").unwrap());
+    assert_eq!(Some(String::from("[1, 2, 3]")), engine.eval("[1, 2, 3]").unwrap());
+    assert_eq!(Some(String::from("true")), engine.eval("x > 2").unwrap());
+}
+
+#[test]
+fn test_engine_eval_rollback() {
+    let mut engine = Engine::new(1024);
+    assert!(engine.eval("var x = 1;").is_ok());
+
+    // Nothing of a failed input is kept
+    let eval_res = engine.eval("var y = 2; var z = y + true;");
+    assert!(eval_res.unwrap_err().diagnostic().is_some());
+    assert!(engine.eval("y").is_err());
+    assert!(engine.eval("fn: broken() ~ int { return true; }").is_err());
+    assert!(engine.eval("fn: broken() ~ int { return 3; }").is_ok());
+
+    assert_eq!(Some(String::from("4")), engine.eval("x + broken()").unwrap());
+}
+
+#[test]
+fn test_engine_eval_runtime_errors() {
+    let mut engine = Engine::new(1024);
+    assert!(engine.eval("fn: f(a: int) ~ int { return 10 / (a - 2); }").is_ok());
+
+    // Failed runs don't leave anything on the stack
+    for _ in 0..100 {
+        assert!(engine.eval("1 / 0").is_err());
+        assert!(engine.eval("f(2)").is_err());
+    }
+    assert_eq!(0, engine.get_stack_size());
+
+    assert_eq!(Some(String::from("2")), engine.eval("1 + 1").unwrap());
+    assert_eq!(Some(String::from("\"a\"")), engine.eval("\"a\"").unwrap());
+    assert_eq!(Some(String::from("5")), engine.eval("f(4)").unwrap());
+}
+
+#[test]
+fn test_engine_run_stream() {
+    let code = "#!/usr/bin/env pgsh
+fn: main() ~ int {
+    return 42;
+}
+";
+    let mut engine = Engine::new(1024);
+    let run_res = engine.run_stream(Box::new(Cursor::new(code)));
+    assert!(run_res.is_ok());
+    assert_eq!(42, engine.call::<_, i64>("root::main", ()).unwrap());
+
+    let bytes = Engine::new(1024).precompile("script.pgs", code).unwrap();
+    let mut engine = Engine::new(1024);
+    let run_res = engine.run_stream(Box::new(Cursor::new(bytes)));
+    assert!(run_res.is_ok());
+    let main = engine.get_function("root::main").unwrap();
+    assert_eq!(ValueType::Int, main.return_type());
+    assert_eq!(Some(Value::Int(42)), engine.call_function(&main, &[]).unwrap());
+
+    let mut engine = Engine::new(1024);
+    let run_res = engine.run_stream(Box::new(Cursor::new("fn: main() ~ int { return true; }")));
+    assert!(run_res.unwrap_err().diagnostic().is_some());
+}
+
+#[test]
+fn test_engine_string_arguments() {
+    let code = "
+        fn: main(name: string, empty: string, flag: bool, values: [int; 2]) ~ int {
+            if name == \"pgs\" {
+                if empty == \"\" {
+                    if flag {
+                        return values[0] + values[1];
+                    }
+                }
+            }
+            return 0;
+        }
+    ";
+    let mut engine = Engine::new(1024);
+    assert!(engine.load_code(code).is_ok());
+
+    let main = engine.get_function("root::main").unwrap();
+    assert_eq!(ValueType::String, main.arguments()[0].1);
+    assert_eq!(ValueType::Array(Box::new(ValueType::Int), 2), main.arguments()[3].1);
+
+    let args = vec![
+        Value::String(String::from("pgs")),
+        Value::String(String::new()),
+        Value::Bool(true),
+        Value::Array(vec![Value::Int(40), Value::Int(2)])
+    ];
+    assert_eq!(Some(Value::Int(42)), engine.call_function(&main, &args).unwrap());
+}
+
+#[test]
+fn test_engine_auto_array_arguments() {
+    let code = "
+        fn: count_chars(files: [string]) ~ int {
+            var count = 0;
+            var i = 0;
+            while i < len(files) {
+                count = count + len(files[i]);
+                i = i + 1;
+            }
+            return count;
+        }
+
+        fn: main(prefix: string, files: [string], numbers: [int]) ~ int {
+            numbers[0] = numbers[0] * 10;
+            if files[len(files) - 1] == prefix {
+                return count_chars(files) + numbers[0] + numbers[len(numbers) - 1];
+            }
+            return 0;
+        }
+
+        fn: rest(files: [string], index: int) ~ [string] {
+            var first = files[index];
+            return files;
+        }
+    ";
+    let mut engine = Engine::new(1024);
+    assert!(engine.load_code(code).is_ok());
+
+    let main = engine.get_function("root::main").unwrap();
+    assert_eq!(ValueType::AutoArray(Box::new(ValueType::String)), main.arguments()[1].1);
+
+    let files = Value::AutoArray(vec![
+        Value::String(String::from("a.txt")),
+        Value::String(String::from("pgs"))
+    ]);
+    let args = vec![
+        Value::String(String::from("pgs")),
+        files.clone(),
+        Value::AutoArray(vec![Value::Int(3), Value::Int(4), Value::Int(5)])
+    ];
+    assert_eq!(Some(Value::Int(43)), engine.call_function(&main, &args).unwrap());
+    assert_eq!(0, engine.get_stack_size());
+
+    let args = vec![
+        Value::String(String::from("pgs")),
+        Value::AutoArray(Vec::new()),
+        Value::AutoArray(vec![Value::Int(1)])
+    ];
+    match engine.call_function(&main, &args) {
+        Err(err) => {
+            match *err {
+                EngineError::CoreError(CoreError::IndexOutOfBounds(-1, 0)) => {},
+                _ => panic!("Expected an IndexOutOfBounds error, got {:?}", err)
+            };
+        },
+        Ok(_) => panic!("Expected an IndexOutOfBounds error")
+    };
+
+    let rest = engine.get_function("root::rest").unwrap();
+    assert_eq!(Some(files.clone()), engine.call_function(&rest, &[files.clone(), Value::Int(1)]).unwrap());
+    assert!(engine.call_function(&rest, &[files, Value::Int(2)]).is_err());
+    assert!(engine.call_function(&rest, &[Value::AutoArray(vec![Value::Int(1)]), Value::Int(0)]).is_err());
+}
+
+#[derive(PartialEq, Debug)]
+struct Point {
+    x: i64,
+    y: f32
+}
+
+impl ScriptValue for Point {
+    fn script_type() -> ValueType {
+        ValueType::Container(String::from("Point"))
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Container(String::from("Point"), vec![
+            (String::from("x"), self.x.to_value()),
+            (String::from("y"), self.y.to_value())
+        ])
+    }
+
+    fn from_value(value: Value) -> Option<Point> {
+        Some(Point {
+            x: i64::from_value(value.member("x")?.clone())?,
+            y: f32::from_value(value.member("y")?.clone())?
+        })
+    }
+}
+
+#[test]
+fn test_engine_call() {
+    let code = "
+        cont: Point {
+            x: int;
+            y: float;
+        }
+
+        fn: add(a: int, b: int) ~ int {
+            return a + b;
+        }
+
+        fn: greet(name: string, excited: bool) ~ string {
+            if excited {
+                return \"Hello, \" + name + \"!\";
+            }
+            return \"Hello, \" + name;
+        }
+
+        fn: scale(p: Point, factor: int) ~ Point {
+            return Point {
+                x: p.x * factor,
+                y: p.y * 2.0
+            };
+        }
+
+        fn: sum(values: [int; 3]) ~ int {
+            return values[0] + values[1] + values[2];
+        }
+    ";
+    let mut engine = Engine::new(1024);
+    assert!(engine.load_code(code).is_ok());
+
+    assert_eq!(42, engine.call::<_, i64>("root::add", (40i64, 2i64)).unwrap());
+    assert_eq!(
+        "Hello, pgs!",
+        engine.call::<_, String>("root::greet", (String::from("pgs"), true)).unwrap()
+    );
+    assert_eq!(
+        Point { x: 6, y: 1.0 },
+        engine.call::<_, Point>("root::scale", (Point { x: 2, y: 0.5 }, 3i64)).unwrap()
+    );
+    assert_eq!(6, engine.call::<_, i64>("root::sum", ([1i64, 2, 3],)).unwrap());
+
+    let call_err = engine.call::<_, i64>("root::add", (1.0f32, 2i64)).unwrap_err();
+    match *call_err {
+        EngineError::SignatureMismatch { ref expected, ref found, .. } => {
+            assert_eq!("(Int, Int) ~ Int", expected);
+            assert_eq!("(Float, Int) ~ Int", found);
+        },
+        _ => panic!("Unexpected error {:?}", call_err)
+    }
+    assert!(engine.call::<_, String>("root::add", (1i64, 2i64)).is_err());
+    assert!(engine.call::<_, i64>("root::add", (1i64,)).is_err());
+}
+
+#[test]
+fn test_engine_call_function() {
+    let code = "
+        cont: Point {
+            x: int;
+            y: float;
+        }
+
+        fn: mirror(p: Point) ~ Point {
+            return Point {
+                x: 0 - p.x,
+                y: p.y
+            };
+        }
+
+        fn: pass(handle: &int) ~ &int {
+            return handle;
+        }
+
+        fn: main() ~ int {
+            return 0;
+        }
+    ";
+    let mut engine = Engine::new(1024);
+    assert!(engine.load_code(code).is_ok());
+    assert!(engine.eval("1 + 2").is_ok());
+
+    let names: Vec<String> = engine.get_functions().into_iter()
+        .map(|function| function.name)
+        .collect();
+    assert_eq!(vec!["root::main", "root::mirror", "root::pass"], names);
+
+    let mirror = engine.get_function("root::mirror").unwrap();
+    assert_eq!("(Other(\"root::Point\")) ~ Other(\"root::Point\")", mirror.signature());
+    let point = Value::Container(String::from("Point"), vec![
+        (String::from("x"), Value::Int(3)),
+        (String::from("y"), Value::Float(0.5))
+    ]);
+    let mirrored = Value::Container(String::from("root::Point"), vec![
+        (String::from("x"), Value::Int(-3)),
+        (String::from("y"), Value::Float(0.5))
+    ]);
+    assert_eq!(Some(mirrored), engine.call_function(&mirror, &[point]).unwrap());
+
+    // Members have to be given in declaration order
+    let swapped = Value::Container(String::from("Point"), vec![
+        (String::from("y"), Value::Float(0.5)),
+        (String::from("x"), Value::Int(3))
+    ]);
+    assert!(engine.call_function(&mirror, &[swapped]).is_err());
+    assert!(engine.call_function(&mirror, &[Value::Int(3)]).is_err());
+
+    let handle = engine.insert_foreign(Arc::new(Mutex::new(String::from("native")))).unwrap();
+    let pass = engine.get_function("root::pass").unwrap();
+    let passed = engine.call_function(&pass, &[handle.clone()]).unwrap().unwrap();
+    assert_eq!(handle, passed);
+    let native = engine.get_foreign::<String>(&passed).unwrap();
+    assert_eq!("native", native.lock().unwrap().as_str());
+    assert!(engine.get_foreign::<String>(&Value::Int(1)).is_err());
+    match engine.get_foreign::<i64>(&passed).map_err(|err| *err) {
+        Err(EngineError::CoreError(CoreError::ForeignTypeMismatch(_))) => {},
+        res => panic!("Expected a foreign type mismatch, got {:?}", res)
+    };
+}
+
+struct Counter {
+    count: i64
+}
+
+static DROPPED_COUNTERS: AtomicUsize = AtomicUsize::new(0);
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        DROPPED_COUNTERS.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn counter_module() -> Module {
+    let counter = Container::new(String::from("Counter"))
+        .with_static_function(
+            Function::new("create")
+                .with_arg(ValueType::Int)
+                .with_ret_type(ValueType::Container(String::from("Counter")))
+                .with_closure(Box::new(|adapter: &mut Adapter| {
+                    let count: i64 = adapter.get_arg(0)?;
+                    adapter.return_native(Arc::new(Mutex::new(Counter { count })))
+                }))
+        )
+        .with_function(
+            Function::new("increment")
+                .with_arg(ValueType::Int)
+                .with_ret_type(ValueType::Int)
+                .with_closure(Box::new(|adapter: &mut Adapter| {
+                    // The instance is the first argument
+                    let by: i64 = adapter.get_arg(1)?;
+                    let this = adapter.get_this::<Counter>()?;
+                    let mut counter = this.lock().unwrap();
+                    counter.count += by;
+                    adapter.return_value(counter.count)
+                }))
+        )
+        .with_variable((
+            String::from("count"),
+            Function::new("get_count")
+                .with_ret_type(ValueType::Int)
+                .with_closure(Box::new(|adapter: &mut Adapter| {
+                    let this = adapter.get_this::<Counter>()?;
+                    let count = this.lock().unwrap().count;
+                    adapter.return_value(count)
+                }))
+        ));
+    let sum = Function::new("sum")
+        .with_arg(ValueType::Container(String::from("Counter")))
+        .with_arg(ValueType::Container(String::from("Counter")))
+        .with_ret_type(ValueType::Int)
+        .with_closure(Box::new(|adapter: &mut Adapter| {
+            let lhs = adapter.get_native::<Counter>(0)?;
+            let rhs = adapter.get_native::<Counter>(1)?;
+            // Both arguments may be the same instance
+            let lhs_count = lhs.lock().unwrap().count;
+            let rhs_count = rhs.lock().unwrap().count;
+            adapter.return_value(lhs_count + rhs_count)
+        }));
+    Module::new("native")
+        .with_container(counter)
+        .with_function(sum)
+}
+
+#[test]
+fn test_engine_native_container() {
+    let code = "
+        fn: make(count: int) ~ native::Counter {
+            return native::Counter::create(count);
+        }
+
+        fn: pass(counter: native::Counter) ~ native::Counter {
+            return counter;
+        }
+
+        fn: main() ~ int {
+            var counter = native::Counter::create(5);
+            counter.increment(2);
+            var other = make(10);
+            var count = counter.count;
+            var incremented = counter.increment(3);
+            return count + incremented + native::sum(counter, other);
+        }
+    ";
+    let mut engine = Engine::new(1024);
+    assert!(engine.register_module(counter_module()).is_ok());
+    let load_res = engine.load_code(code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    // 7 + 10 + (10 + 10)
+    assert_eq!(37, engine.call::<_, i64>("root::main", ()).unwrap());
+    assert_eq!(0, engine.get_stack_size());
+
+    // The instances of main can't be reached anymore
+    engine.collect_garbage();
+    assert_eq!(2, DROPPED_COUNTERS.load(Ordering::SeqCst));
+
+    // Instances reach the embedder as handles
+    let make = engine.get_function("root::make").unwrap();
+    let counter = engine.call_function(&make, &[Value::Int(4)]).unwrap().unwrap();
+    assert_eq!(4, engine.get_foreign::<Counter>(&counter).unwrap().lock().unwrap().count);
+    let sum = engine.get_function("root::native::sum").unwrap();
+    assert_eq!(Some(Value::Int(8)), engine.call_function(&sum, &[counter.clone(), counter.clone()]).unwrap());
+
+    // Only handles created as instances of the container are accepted
+    let foreign = engine.insert_foreign(Arc::new(Mutex::new(Counter { count: 1 }))).unwrap();
+    assert!(engine.call_function(&sum, &[foreign, counter.clone()]).is_err());
+    let pass = engine.get_function("root::pass").unwrap();
+    assert_eq!(Some(counter.clone()), engine.call_function(&pass, &[counter.clone()]).unwrap());
+    assert!(engine.call_function(&sum, &[Value::ForeignRef(1), counter.clone()]).is_err());
+
+    // Handles held by the embedder survive garbage collection until they are released
+    let native = engine.get_foreign::<Counter>(&counter).unwrap();
+    engine.collect_garbage();
+    assert_eq!(2, Arc::strong_count(&native));
+    assert!(engine.release_foreign(&counter).is_ok());
+    engine.collect_garbage();
+    assert_eq!(1, Arc::strong_count(&native));
+    match engine.get_foreign::<Counter>(&counter).map_err(|err| *err) {
+        Err(EngineError::CoreError(CoreError::InvalidForeignPointer)) => {},
+        res => panic!("Expected an invalid foreign pointer, got {:?}", res.map(|_| ()))
+    };
+
+    assert!(engine.eval("var c = native::Counter::create(1); c").unwrap().unwrap().starts_with("Counter(0x"));
+    assert!(engine.load_code("fn: broken() ~ int { var c = native::Counter {}; return 0; }").is_err());
+}
+
+#[test]
+fn test_engine_foreign_error() {
+    let div_function = Function::new("div")
+        .with_arg(ValueType::Int)
+        .with_arg(ValueType::Int)
+        .with_ret_type(ValueType::Int)
+        .with_closure(Box::new(|adapter: &mut Adapter| {
+            let lhs: i64 = adapter.get_arg(0)?;
+            let rhs: i64 = adapter.get_arg(1)?;
+            if rhs == 0 {
+                return Err(ForeignError::new("division by zero"));
+            }
+            adapter.return_value(lhs / rhs)
+        }));
+    let module = Module::new("math")
+        .with_function(div_function);
+
+    let code = "
+        fn: div(lhs: int, rhs: int) ~ int {
+            return math::div(lhs, rhs);
+        }
+
+        fn: main(lhs: int, rhs: int) ~ int {
+            return div(lhs, rhs) + 1;
+        }
+    ";
+    let mut engine = Engine::new(1024);
+    assert!(engine.register_module(module).is_ok());
+    assert!(engine.load_code(code).is_ok());
+
+    assert_eq!(5, engine.call::<_, i64>("root::main", (8i64, 2i64)).unwrap());
+
+    let err = engine.call::<_, i64>("root::main", (8i64, 0i64)).unwrap_err();
+    println!("{}", err);
+    match *err {
+        EngineError::CoreError(CoreError::ForeignFunction { ref name, ref message }) => {
+            assert_eq!("div", name);
+            assert_eq!("division by zero", message);
+        },
+        _ => panic!("Unexpected error: {:?}", err)
+    }
+    assert_eq!("error: foreign function div failed: division by zero", err.to_string());
+    assert_eq!(0, engine.get_stack_size());
+
+    // The engine is still usable after the failure
+    assert_eq!(3, engine.call::<_, i64>("root::main", (4i64, 2i64)).unwrap());
+}
+
+#[test]
+fn test_engine_try_catch() {
+    let div_function = Function::new("div")
+        .with_arg(ValueType::Int)
+        .with_arg(ValueType::Int)
+        .with_ret_type(ValueType::Int)
+        .with_closure(Box::new(|adapter: &mut Adapter| {
+            let lhs: i64 = adapter.get_arg(0)?;
+            let rhs: i64 = adapter.get_arg(1)?;
+            if rhs == 0 {
+                return Err(ForeignError::new("cannot divide by zero"));
+            }
+            adapter.return_value(lhs / rhs)
+        }));
+    let module = Module::new("math")
+        .with_function(div_function);
+
+    let code = "
+        fn: check(value: int) ~ int {
+            if value < 0 {
+                throw \"negative value\";
+            }
+            return value;
+        }
+
+        fn: nested(value: int) ~ int {
+            var offset = 1;
+            return check(value) + offset;
+        }
+
+        fn: divide(lhs: int, rhs: int) ~ int {
+            var result = 0;
+            try {
+                result = lhs / rhs;
+            } catch {
+                result = -1;
+            }
+            return result;
+        }
+
+        fn: index(i: int) ~ string {
+            var values = [1, 2, 3];
+            try {
+                var value = values[i];
+                return \"found\";
+            } catch err {
+                return err;
+            }
+            return \"unreachable\";
+        }
+
+        fn: foreign(lhs: int, rhs: int) ~ string {
+            try {
+                var value = math::div(lhs, rhs);
+            } catch err {
+                return err;
+            }
+            return \"ok\";
+        }
+
+        fn: thrown(value: int) ~ string {
+            var prefix = \"caught: \";
+            try {
+                var result = nested(value);
+            } catch err {
+                return prefix + err;
+            }
+            return \"ok\";
+        }
+
+        fn: loop_break() ~ int {
+            var count = 0;
+            loop {
+                try {
+                    count += 1;
+                    if count == 3 {
+                        break;
+                    }
+                } catch {
+                    return -1;
+                }
+            }
+            var zero = 0;
+            return count / zero;
+        }
+
+        fn: rethrow() ~ int {
+            try {
+                throw \"inner\";
+            } catch err {
+                throw \"outer: \" + err;
+            }
+            return 0;
+        }
+
+        fn: uncaught() ~ int {
+            return nested(-1);
+        }
+    ";
+    let mut engine = Engine::new(1024);
+    assert!(engine.register_module(module).is_ok());
+    let load_res = engine.load_code(code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    assert_eq!(4, engine.call::<_, i64>("root::divide", (8i64, 2i64)).unwrap());
+    assert_eq!(-1, engine.call::<_, i64>("root::divide", (8i64, 0i64)).unwrap());
+    assert_eq!(0, engine.get_stack_size());
+
+    assert_eq!("found", engine.call::<_, String>("root::index", (2i64,)).unwrap());
+    assert_eq!("index 3 is out of bounds for size 3", engine.call::<_, String>("root::index", (3i64,)).unwrap());
+    assert_eq!(0, engine.get_stack_size());
+
+    assert_eq!("ok", engine.call::<_, String>("root::foreign", (8i64, 2i64)).unwrap());
+    assert_eq!("foreign function div failed: cannot divide by zero", engine.call::<_, String>("root::foreign", (8i64, 0i64)).unwrap());
+    assert_eq!(0, engine.get_stack_size());
+
+    assert_eq!("ok", engine.call::<_, String>("root::thrown", (1i64,)).unwrap());
+    assert_eq!("caught: negative value", engine.call::<_, String>("root::thrown", (-1i64,)).unwrap());
+    assert_eq!(0, engine.get_stack_size());
+
+    // Leaving a try block with break ends it, so later errors are not caught by it
+    let err = engine.call::<_, i64>("root::loop_break", ()).unwrap_err();
+    match *err {
+        EngineError::CoreError(CoreError::DivisionByZero) => {},
+        _ => panic!("Expected a DivisionByZero error, got {:?}", err)
+    };
+    assert_eq!(0, engine.get_stack_size());
+
+    let err = engine.call::<_, i64>("root::rethrow", ()).unwrap_err();
+    assert_eq!("error: outer: inner", err.to_string());
+    assert_eq!(0, engine.get_stack_size());
+
+    let err = engine.call::<_, i64>("root::uncaught", ()).unwrap_err();
+    match *err {
+        EngineError::CoreError(CoreError::Thrown(ref message)) => assert_eq!("negative value", message),
+        _ => panic!("Expected a Thrown error, got {:?}", err)
+    };
+    assert_eq!(0, engine.get_stack_size());
+
+    // The engine is still usable after an uncaught error
+    assert_eq!(3, engine.call::<_, i64>("root::nested", (2i64,)).unwrap());
+}
+
+#[test]
+fn test_engine_try_catch_nested_call() {
+    let code = "
+        fn: div(a: int, b: int) ~ int {
+            return a / b;
+        }
+
+        fn: inner(b: int) ~ int {
+            var r = 0;
+            try {
+                r = div(1, b);
+            } catch e {
+                r = 7;
+            }
+            return r;
+        }
+
+        fn: outer(b: int) ~ int {
+            var offset = 10;
+            return inner(b) + offset;
+        }
+
+        fn: main() ~ int {
+            var value = inner(0);
+            return value + outer(0) + outer(1);
+        }
+    ";
+    let mut engine = Engine::new(1024);
+    let load_res = engine.load_code(code);
+    println!("{:?}", load_res);
+    assert!(load_res.is_ok());
+
+    // The catch blocks return into the frames of their callers
+    assert_eq!(7, engine.call::<_, i64>("root::inner", (0i64,)).unwrap());
+    assert_eq!(17, engine.call::<_, i64>("root::outer", (0i64,)).unwrap());
+    assert_eq!(35, engine.call::<_, i64>("root::main", ()).unwrap());
+    assert_eq!(0, engine.get_stack_size());
+}
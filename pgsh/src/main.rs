@@ -4,31 +4,17 @@ extern crate pgs;
 #[cfg(feature = "static_std")]
 extern crate pgs_std;
 
-use pgs::{
-    engine::{
-        Engine,
-        EngineResult
-    },
-    codegen::{
-        register::Register
-    },
-    api::{
-        function::{
-            Function
-        },
-        module::{
-            Module
-        }
-    },
-    parser::{
-        ast::Type
-    },
-    vm::{
-        core::Core
-    }
-};
+use pgs::prelude::*;
 
 use std::{
+    fs,
+    io::{
+        self,
+        BufRead,
+        IsTerminal,
+        Read,
+        Write
+    },
     path::Path,
     error::Error,
     boxed::Box
@@ -36,6 +22,8 @@ use std::{
 
 use clap::{
     App,
+    AppSettings,
+    ArgMatches,
     SubCommand,
     Arg
 };
@@ -44,6 +32,125 @@ fn bootstrap_engine(engine: &mut Engine) -> EngineResult<()> {
     pgs_std::register_extension(engine)
 }
 
+/// Reads a script file, "-" reads from stdin
+fn read_input(filename: &str) -> io::Result<Vec<u8>> {
+    if filename == "-" {
+        let mut content = Vec::new();
+        io::stdin().read_to_end(&mut content)?;
+        Ok(content)
+    } else {
+        fs::read(filename)
+    }
+}
+
+/// Prints an intermediate representation of a script or a precompiled program
+fn emit(engine: &mut Engine, filename: &str, kind: &str) -> Result<(), Box<dyn Error>> {
+    let content = read_input(filename)?;
+    if Engine::is_bytecode(&content) {
+        if kind != "bytecode" {
+            return Err(format!("{} is precompiled, only its bytecode can be emitted", filename).into());
+        }
+        print!("{}", Engine::disassemble_bytecode(&content)?);
+        return Ok(());
+    }
+
+    let code = String::from_utf8(content)?;
+    match kind {
+        "tokens" => print!("{}", Engine::print_tokens(&code)),
+        "ast" => print!("{}", engine.print_ast(filename, &code)?),
+        _ => print!("{}", engine.disassemble(filename, &code)?)
+    };
+    Ok(())
+}
+
+/// Returns how many more blocks the line opens than it closes, braces in string literals are ignored
+fn block_depth(line: &str) -> i64 {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in line.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '{' if !in_string => depth += 1,
+            '}' if !in_string => depth -= 1,
+            _ => {}
+        };
+    }
+    depth
+}
+
+/// Runs an interactive session, input is read until all opened blocks are closed
+fn repl(engine: &mut Engine) -> Result<(), Box<dyn Error>> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut input = String::new();
+    let mut depth = 0;
+    loop {
+        print!("{}", if input.is_empty() { ">> " } else { ".. " });
+        io::stdout().flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break
+        };
+        if input.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+        depth += block_depth(&line);
+        input.push_str(&line);
+        input.push('\n');
+        if depth > 0 {
+            continue;
+        }
+
+        match engine.eval(&input) {
+            Ok(Some(value)) => println!("{}", value),
+            Ok(None) => {},
+            Err(err) => eprintln!("{}", err)
+        };
+        input.clear();
+        depth = 0;
+    }
+    println!();
+    Ok(())
+}
+
+/// Matches the command line values to the arguments of the main function
+fn parse_main_arguments(engine: &Engine, filename: &str, values: &[&str]) -> (ScriptFunction, Vec<Value>) {
+    let main = match engine.get_function("root::main") {
+        Ok(main) => main,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    match parse_arguments(&main.arguments(), values) {
+        Ok(parsed) => (main, parsed),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            match main.arguments().is_empty() {
+                true => eprintln!("usage: pgsh {}", filename),
+                false => eprintln!("usage: pgsh {} {}", filename, get_usage(&main.arguments()))
+            };
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Returns the values after the filename as they were passed to pgsh
+fn script_arguments<'a>(app_matches: &'a ArgMatches, raw_args: &'a [String]) -> Vec<&'a str> {
+    let mut arguments: Vec<&str> = app_matches.values_of("arguments")
+        .map(|values| values.collect())
+        .unwrap_or_default();
+    // clap drops a "--" right after the filename, but for the script it ends the named values
+    let before = raw_args.len().checked_sub(arguments.len() + 1);
+    if before.map(|i| raw_args[i] == "--").unwrap_or(false) {
+        arguments.insert(0, "--");
+    }
+    arguments
+}
 
 fn build_app<'a>() -> App<'a, 'a> {
     let about_string;
@@ -60,19 +167,38 @@ fn build_app<'a>() -> App<'a, 'a> {
         .author("Daniel Wanner <daniel.wanner@pm.me>")
         .about(about_string)
         .version("0.1.0")
+        // Everything after the filename belongs to the script, so shebang scripts can take arguments
+        .setting(AppSettings::TrailingVarArg)
+        .setting(AppSettings::AllowLeadingHyphen)
         .arg(
             Arg::with_name("filename")
                 .index(1)
                 .takes_value(true)
-                .help("Filename of the script to execute")
+                .help("Filename of the script to execute, \"-\" reads it from stdin. Without one, a script piped on stdin is executed or an interactive session is started")
+        )
+        .arg(
+            Arg::with_name("compile")
+                .short("c")
+                .long("compile")
+                .takes_value(true)
+                .value_name("OUTPUT")
+                .help("Precompiles the script into a .pgc file instead of executing it")
+        )
+        .arg(
+            Arg::with_name("emit")
+                .long("emit")
+                .takes_value(true)
+                .possible_values(&["tokens", "ast", "bytecode"])
+                .help("Prints the tokens, the syntax tree or the bytecode of the script instead of executing it")
         )
         .arg(
             Arg::with_name("arguments")
+                .index(2)
                 .required(false)
                 .takes_value(true)
-                .help("Arguments to pass to the scripts main function")
+                .allow_hyphen_values(true)
+                .help("Arguments to pass to the scripts main function, positionally or as --name value")
                 .multiple(true)
-                .last(true)
         )
 }
 
@@ -81,39 +207,75 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let app_matches = app.get_matches();
 
-    let filename_opt = app_matches.value_of("filename");
-    assert!(filename_opt.is_some());
-
-    let filename = filename_opt.unwrap();
+    // Scripts piped into pgsh are run like a file
+    let filename_opt = match app_matches.value_of("filename") {
+        None if !io::stdin().is_terminal() => Some("-"),
+        filename_opt => filename_opt
+    };
+    if filename_opt.is_none() && (app_matches.is_present("emit") || app_matches.is_present("compile")) {
+        eprintln!("error: --emit and --compile require a filename");
+        std::process::exit(1);
+    }
 
     let mut engine = Engine::new(1024);
 
-    let arguments_opt = app_matches.values_of("arguments");
-    if arguments_opt.is_some() {
-        let arguments: Vec<&str> = arguments_opt.unwrap().collect();
-        for arg in arguments {
-            let int_res = String::from(arg).parse::<i64>();
-            let float_res = String::from(arg).parse::<f32>();
+    #[cfg(feature = "static_std")]
+    bootstrap_engine(&mut engine)?;
 
-            if int_res.is_err() && float_res.is_err() {
-                println!("ERROR! Not an integer or float.");
-            }
-            if int_res.is_ok() {
-                engine.push_stack(int_res.unwrap())?;
-            } else if float_res.is_ok() {
-                engine.push_stack(float_res.unwrap())?;
+    let filename = match filename_opt {
+        Some(filename) => filename,
+        None => return repl(&mut engine)
+    };
+    // Leading hyphens are allowed for the script arguments, the filename can't be an option
+    if filename.starts_with('-') && filename != "-" {
+        eprintln!("error: Found argument '{}' which wasn't expected, or isn't valid in this context", filename);
+        std::process::exit(1);
+    }
+
+    if let Some(kind) = app_matches.value_of("emit") {
+        if let Err(err) = emit(&mut engine, filename, kind) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(output) = app_matches.value_of("compile") {
+        let code = String::from_utf8(read_input(filename)?)?;
+        match engine.precompile(filename, &code) {
+            Ok(bytes) => fs::write(output, bytes)?,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
             }
         }
+        return Ok(());
     }
 
-    #[cfg(feature = "static_std")]
-    bootstrap_engine(&mut engine)?;
+    let load_res = if filename == "-" {
+        engine.load_stream(Box::new(io::stdin()))
+    } else {
+        engine.load_file(Path::new(filename))
+    };
+    if let Err(err) = load_res {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
 
-    engine.run_file(Path::new(filename))?;
+    let raw_args: Vec<String> = std::env::args().collect();
+    let arguments = script_arguments(&app_matches, &raw_args);
+    let (main, main_arguments) = parse_main_arguments(&engine, filename, &arguments);
 
-    //println!("Script run. stack size: {}", engine.get_stack_size());
+    let exit_code = match engine.call_function(&main, &main_arguments) {
+        Ok(Some(Value::Int(exit_code))) => exit_code,
+        Ok(_) => 0,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
 
-    let exit_code = engine.get_register_value::<i64>(Register::R0)?;
+    //println!("Script run. stack size: {}", engine.get_stack_size());
 
     //println!("Script exited. Stack size: {}, Exit code: 0x{:X}/{}", engine.get_stack_size(), exit_code, exit_code);
 